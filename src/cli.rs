@@ -2,13 +2,20 @@ use clap::{Args, Parser, Subcommand, ValueEnum};
 
 use anyhow::Result;
 
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::dns::{DnsRecordType, DnsTransport};
+use crate::dns_bench::{resolve_dns_bench_options, DnsBenchOptions, DnsBenchOverrides};
 use crate::probe::{
-    resolve_probe_options, BandwidthProviderPreset, MeasurementProfile, ProbeOptions,
+    resolve_probe_options, BandwidthProviderPreset, FamilyMode, MeasurementProfile, ProbeOptions,
     ProbeOverrides,
 };
 
 const DEFAULT_INTERVAL_SECONDS: u64 = 15;
 const DEFAULT_HISTORY_LIMIT: usize = 10;
+const DEFAULT_SERVE_BIND: &str = "0.0.0.0:8080";
+const DEFAULT_REFLECT_BIND: &str = "0.0.0.0:40000";
 
 #[derive(Debug, Parser)]
 #[command(
@@ -27,9 +34,12 @@ pub enum Commands {
     Watch(WatchArgs),
     Tui(TuiArgs),
     Providers(ProvidersArgs),
+    DnsBench(DnsBenchArgs),
     History(HistoryArgs),
     Export(ExportArgs),
     Compare(CompareArgs),
+    Serve(ServeArgs),
+    Reflect(ReflectArgs),
 }
 
 #[derive(Debug, Clone, Args)]
@@ -42,8 +52,51 @@ pub struct SharedProbeArgs {
     pub provider: BandwidthProviderPreset,
     #[arg(long)]
     pub endpoint: Option<String>,
-    #[arg(short = 'n', long)]
+    // Native ping sequence numbers are 16 bits wide.
+    #[arg(short = 'n', long, value_parser = clap::value_parser!(u32).range(1..=65_535))]
     pub samples: Option<u32>,
+    #[arg(long, value_enum)]
+    pub family: Option<FamilyMode>,
+    #[arg(long)]
+    pub ping_payload_bytes: Option<usize>,
+    #[arg(long)]
+    pub ping_interval_ms: Option<u64>,
+    #[arg(long)]
+    pub ping_ttl: Option<u8>,
+    #[arg(long)]
+    pub ping_timeout_ms: Option<u64>,
+    #[arg(long)]
+    pub ping_all_addresses: bool,
+    #[arg(long)]
+    pub tcp_port: Option<u16>,
+    #[arg(long)]
+    pub tls_port: Option<u16>,
+    #[arg(long)]
+    pub dns_server: Vec<String>,
+    #[arg(long, value_enum)]
+    pub dns_record: Option<DnsRecordType>,
+    #[arg(long, value_enum)]
+    pub dns_transport: Option<DnsTransport>,
+    #[arg(long)]
+    pub udp_port: Option<u16>,
+    #[arg(long)]
+    pub udp_packets: Option<u32>,
+    #[arg(long)]
+    pub udp_interval_ms: Option<u64>,
+    #[arg(long)]
+    pub udp_payload_bytes: Option<usize>,
+    #[arg(long)]
+    pub trace: bool,
+    #[arg(long)]
+    pub trace_max_hops: Option<u8>,
+    #[arg(long)]
+    pub trace_cycles: Option<u32>,
+    #[arg(long)]
+    pub pmtu: bool,
+    #[arg(long, value_parser = clap::value_parser!(u32).range(576..))]
+    pub pmtu_max_bytes: Option<u32>,
+    #[arg(long)]
+    pub http_url: Vec<String>,
     #[arg(long)]
     pub download_url: Vec<String>,
     #[arg(long)]
@@ -66,10 +119,20 @@ pub struct SharedProbeArgs {
     pub upload_streams: Option<u32>,
     #[arg(long)]
     pub target_transfer_duration_ms: Option<u64>,
+    #[arg(long, value_parser = parse_duration)]
+    pub transfer_duration: Option<Duration>,
+    #[arg(long)]
+    pub ramp_up_exclusion_ms: Option<u64>,
+    #[arg(long)]
+    pub min_successful_runs: Option<u32>,
     #[arg(long)]
     pub max_download_size_bytes: Option<usize>,
     #[arg(long)]
     pub max_upload_size_bytes: Option<usize>,
+    #[arg(long)]
+    pub loaded_latency: bool,
+    #[arg(long)]
+    pub responsiveness: bool,
 }
 
 impl SharedProbeArgs {
@@ -80,6 +143,27 @@ impl SharedProbeArgs {
             provider: self.provider,
             endpoint: self.endpoint.clone(),
             samples: self.samples,
+            family: self.family,
+            ping_payload_bytes: self.ping_payload_bytes,
+            ping_interval_ms: self.ping_interval_ms,
+            ping_ttl: self.ping_ttl,
+            ping_timeout_ms: self.ping_timeout_ms,
+            ping_all_addresses: self.ping_all_addresses,
+            tcp_port: self.tcp_port,
+            tls_port: self.tls_port,
+            dns_servers: self.dns_server.clone(),
+            dns_record: self.dns_record,
+            dns_transport: self.dns_transport,
+            udp_port: self.udp_port,
+            udp_packets: self.udp_packets,
+            udp_interval_ms: self.udp_interval_ms,
+            udp_payload_bytes: self.udp_payload_bytes,
+            trace: self.trace,
+            trace_max_hops: self.trace_max_hops,
+            trace_cycles: self.trace_cycles,
+            pmtu: self.pmtu,
+            pmtu_max_bytes: self.pmtu_max_bytes,
+            http_urls: self.http_url.clone(),
             download_urls: self.download_url.clone(),
             upload_urls: self.upload_url.clone(),
             download_size_bytes: self.download_size_bytes,
@@ -91,12 +175,42 @@ impl SharedProbeArgs {
             download_streams: self.download_streams,
             upload_streams: self.upload_streams,
             target_transfer_duration_ms: self.target_transfer_duration_ms,
+            transfer_duration_ms: self
+                .transfer_duration
+                .map(|duration| duration.as_millis() as u64),
+            ramp_up_exclusion_ms: self.ramp_up_exclusion_ms,
+            min_successful_runs: self.min_successful_runs,
             max_download_size_bytes: self.max_download_size_bytes,
             max_upload_size_bytes: self.max_upload_size_bytes,
+            loaded_latency: self.loaded_latency,
+            responsiveness: self.responsiveness,
         })
     }
 }
 
+// Accepts `10s`, `1500ms`, or `2m`; a bare number is seconds.
+fn parse_duration(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, unit_ms) = if let Some(number) = value.strip_suffix("ms") {
+        (number, 1)
+    } else if let Some(number) = value.strip_suffix('s') {
+        (number, 1_000)
+    } else if let Some(number) = value.strip_suffix('m') {
+        (number, 60_000)
+    } else {
+        (value, 1_000)
+    };
+    let number = number
+        .trim()
+        .parse::<u64>()
+        .map_err(|_| format!("invalid duration `{value}`; use e.g. 10s, 1500ms, or 2m"))?;
+
+    match number.saturating_mul(unit_ms) {
+        0 => Err("duration must be greater than zero".to_string()),
+        ms => Ok(Duration::from_millis(ms)),
+    }
+}
+
 #[derive(Debug, Clone, Args)]
 pub struct RunArgs {
     #[command(flatten)]
@@ -111,6 +225,40 @@ pub struct ProvidersArgs {
     pub json: bool,
 }
 
+#[derive(Debug, Clone, Args)]
+pub struct DnsBenchArgs {
+    #[arg(long)]
+    pub config: Option<PathBuf>,
+    #[arg(long)]
+    pub resolver: Vec<String>,
+    #[arg(long)]
+    pub domain: Vec<String>,
+    #[arg(long, value_enum, default_value_t = DnsRecordType::A)]
+    pub record: DnsRecordType,
+    #[arg(long, value_enum, default_value_t = DnsTransport::Udp)]
+    pub transport: DnsTransport,
+    #[arg(short = 'n', long)]
+    pub queries: Option<u32>,
+    #[arg(long)]
+    pub timeout_ms: Option<u64>,
+    #[arg(long)]
+    pub json: bool,
+}
+
+impl DnsBenchArgs {
+    pub fn to_bench_options(&self) -> Result<DnsBenchOptions> {
+        resolve_dns_bench_options(DnsBenchOverrides {
+            config: self.config.clone(),
+            resolvers: self.resolver.clone(),
+            domains: self.domain.clone(),
+            record_type: self.record,
+            transport: self.transport,
+            queries: self.queries,
+            timeout_ms: self.timeout_ms,
+        })
+    }
+}
+
 #[derive(Debug, Clone, Args)]
 pub struct HistoryArgs {
     #[arg(short, long)]
@@ -166,3 +314,15 @@ pub struct TuiArgs {
     #[arg(short, long, default_value_t = DEFAULT_INTERVAL_SECONDS)]
     pub interval: u64,
 }
+
+#[derive(Debug, Clone, Args)]
+pub struct ServeArgs {
+    #[arg(short, long, default_value = DEFAULT_SERVE_BIND)]
+    pub bind: String,
+}
+
+#[derive(Debug, Clone, Args)]
+pub struct ReflectArgs {
+    #[arg(short, long, default_value = DEFAULT_REFLECT_BIND)]
+    pub bind: String,
+}
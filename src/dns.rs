@@ -0,0 +1,942 @@
+use anyhow::{anyhow, Context, Result};
+use clap::ValueEnum;
+use reqwest::Url;
+use rustls_platform_verifier::ConfigVerifierExt;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::ClientConfig;
+use tokio_rustls::TlsConnector;
+
+use crate::http_timing;
+
+pub const DEFAULT_DNS_PORT: u16 = 53;
+pub const DEFAULT_DOT_PORT: u16 = 853;
+pub const DEFAULT_DOH_PORT: u16 = 443;
+
+const HEADER_LEN: usize = 12;
+const CLASS_IN: u16 = 1;
+const FLAG_RESPONSE: u16 = 0x8000;
+const FLAG_TRUNCATED: u16 = 0x0200;
+const FLAG_RECURSION_DESIRED: u16 = 0x0100;
+const MAX_UDP_RESPONSE_LEN: usize = 4_096;
+const MAX_COMPRESSION_JUMPS: usize = 32;
+const DOH_CONTENT_TYPE: &str = "application/dns-message";
+const MAX_HTTP_HEAD_LEN: usize = 16 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "UPPERCASE")]
+#[value(rename_all = "UPPERCASE")]
+pub enum DnsRecordType {
+    A,
+    Aaaa,
+    Cname,
+    Mx,
+    Txt,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum DnsTransport {
+    Udp,
+    Tcp,
+    Dot,
+    Doh,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DnsServer {
+    Plain {
+        address: SocketAddr,
+        transport: DnsTransport,
+    },
+    Tls {
+        host: String,
+        port: u16,
+    },
+    Https {
+        url: String,
+    },
+}
+
+pub struct DnsSession {
+    server: DnsServer,
+    channel: Channel,
+}
+
+enum Channel {
+    Plain,
+    Tls(Box<TlsStream<TcpStream>>),
+    Https {
+        stream: Box<dyn HttpStream>,
+        url: Url,
+    },
+}
+
+trait HttpStream: AsyncRead + AsyncWrite + Unpin + Send {}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> HttpStream for S {}
+
+#[derive(Debug, Clone)]
+pub struct DnsHandshake {
+    pub remote_address: SocketAddr,
+    pub connect: Duration,
+    pub tls: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DnsResponse {
+    pub rcode: u8,
+    pub truncated: bool,
+    pub answers: Vec<DnsAnswer>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DnsAnswer {
+    pub record_type: u16,
+    pub ttl: u32,
+    pub data: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct DnsExchange {
+    pub response: DnsResponse,
+    pub rtt: Duration,
+    pub transport: DnsTransport,
+}
+
+impl DnsRecordType {
+    pub fn code(self) -> u16 {
+        match self {
+            Self::A => 1,
+            Self::Cname => 5,
+            Self::Mx => 15,
+            Self::Txt => 16,
+            Self::Aaaa => 28,
+        }
+    }
+}
+
+impl fmt::Display for DnsRecordType {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::A => "A",
+            Self::Aaaa => "AAAA",
+            Self::Cname => "CNAME",
+            Self::Mx => "MX",
+            Self::Txt => "TXT",
+        })
+    }
+}
+
+impl fmt::Display for DnsTransport {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::Udp => "udp",
+            Self::Tcp => "tcp",
+            Self::Dot => "dot",
+            Self::Doh => "doh",
+        })
+    }
+}
+
+pub fn rcode_name(rcode: u8) -> String {
+    match rcode {
+        0 => "NOERROR".to_string(),
+        1 => "FORMERR".to_string(),
+        2 => "SERVFAIL".to_string(),
+        3 => "NXDOMAIN".to_string(),
+        4 => "NOTIMP".to_string(),
+        5 => "REFUSED".to_string(),
+        other => format!("RCODE{other}"),
+    }
+}
+
+pub fn parse_server(value: &str, default_transport: DnsTransport) -> Result<DnsServer> {
+    if value.starts_with("https://") || value.starts_with("http://") {
+        Url::parse(value).with_context(|| format!("invalid DNS-over-HTTPS URL {value}"))?;
+        return Ok(DnsServer::Https {
+            url: value.to_string(),
+        });
+    }
+
+    let (transport, address) = match value.split_once("://") {
+        Some(("udp", address)) => (DnsTransport::Udp, address),
+        Some(("tcp", address)) => (DnsTransport::Tcp, address),
+        Some(("tls", address)) => (DnsTransport::Dot, address),
+        Some((scheme, _)) => anyhow::bail!("unsupported DNS server scheme {scheme} in {value}"),
+        None => (default_transport, value),
+    };
+
+    match transport {
+        DnsTransport::Udp | DnsTransport::Tcp => Ok(DnsServer::Plain {
+            address: parse_socket_address(address, DEFAULT_DNS_PORT).ok_or_else(|| {
+                anyhow!("invalid DNS server {value}; expected an IP address with optional port")
+            })?,
+            transport,
+        }),
+        DnsTransport::Dot => {
+            let (host, port) = split_host_port(address, DEFAULT_DOT_PORT)
+                .ok_or_else(|| anyhow!("invalid DNS-over-TLS server {value}"))?;
+            Ok(DnsServer::Tls { host, port })
+        }
+        DnsTransport::Doh => {
+            let authority = match parse_socket_address(address, DEFAULT_DOH_PORT) {
+                Some(address) if address.port() == DEFAULT_DOH_PORT => match address.ip() {
+                    IpAddr::V4(ip) => ip.to_string(),
+                    IpAddr::V6(ip) => format!("[{ip}]"),
+                },
+                Some(address) => address.to_string(),
+                None => address.to_string(),
+            };
+            parse_server(&format!("https://{authority}/dns-query"), transport)
+        }
+    }
+}
+
+fn parse_socket_address(value: &str, default_port: u16) -> Option<SocketAddr> {
+    value.parse::<SocketAddr>().ok().or_else(|| {
+        value
+            .trim_start_matches('[')
+            .trim_end_matches(']')
+            .parse::<IpAddr>()
+            .ok()
+            .map(|address| SocketAddr::new(address, default_port))
+    })
+}
+
+fn split_host_port(value: &str, default_port: u16) -> Option<(String, u16)> {
+    if let Some(address) = parse_socket_address(value, default_port) {
+        return Some((address.ip().to_string(), address.port()));
+    }
+
+    let (host, port) = match value.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok()?),
+        None => (value, default_port),
+    };
+    (!host.is_empty() && !host.contains(['/', ':'])).then(|| (host.to_string(), port))
+}
+
+impl DnsServer {
+    pub fn transport(&self) -> DnsTransport {
+        match self {
+            Self::Plain { transport, .. } => *transport,
+            Self::Tls { .. } => DnsTransport::Dot,
+            Self::Https { .. } => DnsTransport::Doh,
+        }
+    }
+}
+
+impl fmt::Display for DnsServer {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Plain { address, .. } => write!(formatter, "{address}"),
+            Self::Tls { host, port } if host.contains(':') => {
+                write!(formatter, "tls://[{host}]:{port}")
+            }
+            Self::Tls { host, port } => write!(formatter, "tls://{host}:{port}"),
+            Self::Https { url } => formatter.write_str(url),
+        }
+    }
+}
+
+impl DnsSession {
+    pub async fn connect(
+        server: &DnsServer,
+        timeout: Duration,
+    ) -> Result<(Self, Option<DnsHandshake>)> {
+        let connected = async {
+            match server {
+                DnsServer::Plain { .. } => Ok::<_, anyhow::Error>((Channel::Plain, None)),
+                DnsServer::Tls { host, port } => {
+                    let (stream, remote_address, connect) = open_tcp(host, *port).await?;
+                    let tls_started = Instant::now();
+                    let stream = tls_connect(host, stream, b"dot").await?;
+                    let handshake = DnsHandshake {
+                        remote_address,
+                        connect,
+                        tls: Some(tls_started.elapsed()),
+                    };
+                    Ok((Channel::Tls(Box::new(stream)), Some(handshake)))
+                }
+                DnsServer::Https { url } => {
+                    let parsed = Url::parse(url).with_context(|| format!("invalid URL {url}"))?;
+                    let host = parsed
+                        .host_str()
+                        .ok_or_else(|| anyhow!("URL {url} has no host"))?
+                        .trim_start_matches('[')
+                        .trim_end_matches(']')
+                        .to_string();
+                    let port = parsed
+                        .port_or_known_default()
+                        .ok_or_else(|| anyhow!("URL {url} has no port"))?;
+
+                    // Queries reuse this HTTP/1.1 connection, so the handshake
+                    // timed here is the one they ride on.
+                    let (stream, remote_address, connect) = open_tcp(&host, port).await?;
+                    let (stream, tls): (Box<dyn HttpStream>, _) = if parsed.scheme() == "https" {
+                        let tls_started = Instant::now();
+                        let stream = tls_connect(&host, stream, b"http/1.1").await?;
+                        (Box::new(stream), Some(tls_started.elapsed()))
+                    } else {
+                        (Box::new(stream), None)
+                    };
+
+                    Ok((
+                        Channel::Https {
+                            stream,
+                            url: parsed,
+                        },
+                        Some(DnsHandshake {
+                            remote_address,
+                            connect,
+                            tls,
+                        }),
+                    ))
+                }
+            }
+        };
+
+        let (channel, handshake) =
+            tokio::time::timeout(timeout, connected)
+                .await
+                .map_err(|_| {
+                    anyhow!(
+                        "connecting to DNS server {server} timed out after {}ms",
+                        timeout.as_millis()
+                    )
+                })??;
+
+        Ok((
+            Self {
+                server: server.clone(),
+                channel,
+            },
+            handshake,
+        ))
+    }
+
+    pub async fn query(
+        &mut self,
+        name: &str,
+        record_type: DnsRecordType,
+        timeout: Duration,
+    ) -> Result<DnsExchange> {
+        let id = query_id();
+        let request = build_query(id, name, record_type)?;
+        let started = Instant::now();
+
+        let exchange = async {
+            match (&self.server, &mut self.channel) {
+                (DnsServer::Plain { address, transport }, _) => {
+                    if *transport == DnsTransport::Udp {
+                        let response = exchange_udp(*address, &request, id).await?;
+                        if !response.truncated {
+                            return Ok::<_, anyhow::Error>((response, DnsTransport::Udp));
+                        }
+                        // Truncated answers are retried over TCP, as stub resolvers do.
+                    }
+                    let mut stream = TcpStream::connect(address).await.with_context(|| {
+                        format!("failed to connect to DNS server {address} over TCP")
+                    })?;
+                    let response = exchange_framed(&mut stream, &request, id).await?;
+                    Ok((response, DnsTransport::Tcp))
+                }
+                (_, Channel::Tls(stream)) => {
+                    let response = exchange_framed(stream.as_mut(), &request, id).await?;
+                    Ok((response, DnsTransport::Dot))
+                }
+                (_, Channel::Https { stream, url }) => {
+                    let response = exchange_https(stream.as_mut(), url, &request, id).await?;
+                    Ok((response, DnsTransport::Doh))
+                }
+                (server, _) => Err(anyhow!("DNS session for {server} is not connected")),
+            }
+        };
+
+        let (response, transport) =
+            tokio::time::timeout(timeout, exchange)
+                .await
+                .map_err(|_| {
+                    anyhow!(
+                        "DNS query to {} timed out after {}ms",
+                        self.server,
+                        timeout.as_millis()
+                    )
+                })??;
+
+        Ok(DnsExchange {
+            response,
+            rtt: started.elapsed(),
+            transport,
+        })
+    }
+}
+
+async fn open_tcp(host: &str, port: u16) -> Result<(TcpStream, SocketAddr, Duration)> {
+    let remote_address = tokio::net::lookup_host((host, port))
+        .await
+        .with_context(|| format!("failed to resolve {host}"))?
+        .next()
+        .ok_or_else(|| anyhow!("no addresses resolved for {host}"))?;
+
+    let started = Instant::now();
+    let stream = TcpStream::connect(remote_address)
+        .await
+        .with_context(|| format!("failed to connect to {remote_address}"))?;
+
+    Ok((stream, remote_address, started.elapsed()))
+}
+
+async fn tls_connect(host: &str, stream: TcpStream, alpn: &[u8]) -> Result<TlsStream<TcpStream>> {
+    let mut config =
+        ClientConfig::with_platform_verifier().context("failed to load TLS trust roots")?;
+    config.alpn_protocols = vec![alpn.to_vec()];
+    let server_name = ServerName::try_from(host.to_string())
+        .with_context(|| format!("invalid TLS server name {host}"))?;
+
+    TlsConnector::from(Arc::new(config))
+        .connect(server_name, stream)
+        .await
+        .with_context(|| format!("TLS handshake with {host} failed"))
+}
+
+async fn exchange_udp(server: SocketAddr, request: &[u8], id: u16) -> Result<DnsResponse> {
+    let bind_address = if server.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(bind_address)
+        .await
+        .context("failed to bind DNS socket")?;
+    socket
+        .connect(server)
+        .await
+        .with_context(|| format!("failed to connect DNS socket to {server}"))?;
+    socket
+        .send(request)
+        .await
+        .with_context(|| format!("failed to send DNS query to {server}"))?;
+
+    let mut buffer = vec![0_u8; MAX_UDP_RESPONSE_LEN];
+    loop {
+        let length = socket
+            .recv(&mut buffer)
+            .await
+            .with_context(|| format!("failed to receive DNS response from {server}"))?;
+        // Ignore stray datagrams that do not answer this query.
+        if let Ok(response) = parse_response(&buffer[..length], id) {
+            return Ok(response);
+        }
+    }
+}
+
+async fn exchange_framed<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    request: &[u8],
+    id: u16,
+) -> Result<DnsResponse> {
+    let mut framed = Vec::with_capacity(request.len() + 2);
+    framed.extend_from_slice(&(request.len() as u16).to_be_bytes());
+    framed.extend_from_slice(request);
+    stream
+        .write_all(&framed)
+        .await
+        .context("failed to send DNS query")?;
+
+    // Skip late answers to earlier queries that timed out on a reused stream.
+    loop {
+        let length = stream
+            .read_u16()
+            .await
+            .context("failed to read DNS response length")?;
+        let mut buffer = vec![0_u8; usize::from(length)];
+        stream
+            .read_exact(&mut buffer)
+            .await
+            .context("failed to read DNS response")?;
+        if read_u16(&buffer, 0)? == id {
+            return parse_response(&buffer, id);
+        }
+    }
+}
+
+async fn exchange_https(
+    stream: &mut dyn HttpStream,
+    url: &Url,
+    request: &[u8],
+    id: u16,
+) -> Result<DnsResponse> {
+    let mut target = url.path().to_string();
+    if let Some(query) = url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    let host = match url.port() {
+        Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+    let mut message = format!(
+        "POST {target} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: pantheon-probe/{}\r\nContent-Type: {DOH_CONTENT_TYPE}\r\nAccept: {DOH_CONTENT_TYPE}\r\nContent-Length: {}\r\n\r\n",
+        env!("CARGO_PKG_VERSION"),
+        request.len()
+    )
+    .into_bytes();
+    message.extend_from_slice(request);
+    stream
+        .write_all(&message)
+        .await
+        .with_context(|| format!("failed to send DNS-over-HTTPS request to {url}"))?;
+
+    // Skip late answers to earlier queries that timed out on the reused connection.
+    loop {
+        let (status, body) = read_http_response(stream)
+            .await
+            .with_context(|| format!("failed to read DNS-over-HTTPS response from {url}"))?;
+        if !(200..300).contains(&status) {
+            anyhow::bail!("DNS-over-HTTPS server {url} returned HTTP {status}");
+        }
+        if read_u16(&body, 0)? == id {
+            return parse_response(&body, id);
+        }
+    }
+}
+
+async fn read_http_response(stream: &mut dyn HttpStream) -> Result<(u16, Vec<u8>)> {
+    let mut buffer = [0_u8; MAX_UDP_RESPONSE_LEN];
+    let mut head = Vec::new();
+    loop {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 {
+            anyhow::bail!("connection closed before the response was received");
+        }
+        head.extend_from_slice(&buffer[..read]);
+
+        if let Some((head_len, status, content_length)) = http_timing::parse_response_head(&head)? {
+            let length =
+                content_length.ok_or_else(|| anyhow!("response has no Content-Length"))? as usize;
+            let mut body = head.split_off(head_len);
+            let received = body.len();
+            body.resize(length, 0);
+            if received < length {
+                stream.read_exact(&mut body[received..]).await?;
+            }
+            return Ok((status, body));
+        }
+        if head.len() > MAX_HTTP_HEAD_LEN {
+            anyhow::bail!("response headers exceeded {MAX_HTTP_HEAD_LEN} bytes");
+        }
+    }
+}
+
+fn query_id() -> u16 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos())
+        .unwrap_or_default();
+    (nanos ^ (nanos >> 16)) as u16
+}
+
+pub fn build_query(id: u16, name: &str, record_type: DnsRecordType) -> Result<Vec<u8>> {
+    let mut message = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+    message.extend_from_slice(&id.to_be_bytes());
+    message.extend_from_slice(&FLAG_RECURSION_DESIRED.to_be_bytes());
+    message.extend_from_slice(&1_u16.to_be_bytes());
+    message.extend_from_slice(&[0; 6]);
+
+    let name = name.trim_end_matches('.');
+    for label in name.split('.').filter(|_| !name.is_empty()) {
+        if label.is_empty() || label.len() > 63 {
+            anyhow::bail!("invalid DNS name {name}");
+        }
+        message.push(label.len() as u8);
+        message.extend_from_slice(label.as_bytes());
+    }
+    message.push(0);
+    message.extend_from_slice(&record_type.code().to_be_bytes());
+    message.extend_from_slice(&CLASS_IN.to_be_bytes());
+
+    Ok(message)
+}
+
+pub fn parse_response(message: &[u8], id: u16) -> Result<DnsResponse> {
+    if message.len() < HEADER_LEN {
+        anyhow::bail!("DNS response is shorter than its header");
+    }
+    if read_u16(message, 0)? != id {
+        anyhow::bail!("DNS response id does not match the query");
+    }
+
+    let flags = read_u16(message, 2)?;
+    if flags & FLAG_RESPONSE == 0 {
+        anyhow::bail!("DNS message is not a response");
+    }
+    let question_count = read_u16(message, 4)?;
+    let answer_count = read_u16(message, 6)?;
+
+    let mut offset = HEADER_LEN;
+    for _ in 0..question_count {
+        offset = skip_name(message, offset)? + 4;
+    }
+
+    let mut answers = Vec::with_capacity(usize::from(answer_count));
+    for _ in 0..answer_count {
+        offset = skip_name(message, offset)?;
+        let record_type = read_u16(message, offset)?;
+        let ttl = read_u32(message, offset + 4)?;
+        let data_len = usize::from(read_u16(message, offset + 8)?);
+        let data_start = offset + 10;
+        let data = message
+            .get(data_start..data_start + data_len)
+            .ok_or_else(|| anyhow!("DNS answer data is truncated"))?;
+        answers.push(DnsAnswer {
+            record_type,
+            ttl,
+            data: format_record_data(message, record_type, data_start, data)?,
+        });
+        offset = data_start + data_len;
+    }
+
+    Ok(DnsResponse {
+        rcode: (flags & 0x000f) as u8,
+        truncated: flags & FLAG_TRUNCATED != 0,
+        answers,
+    })
+}
+
+fn format_record_data(
+    message: &[u8],
+    record_type: u16,
+    data_start: usize,
+    data: &[u8],
+) -> Result<String> {
+    Ok(match record_type {
+        1 if data.len() == 4 => Ipv4Addr::new(data[0], data[1], data[2], data[3]).to_string(),
+        28 if data.len() == 16 => {
+            let octets: [u8; 16] = data.try_into().expect("length checked");
+            Ipv6Addr::from(octets).to_string()
+        }
+        5 => read_name(message, data_start)?,
+        15 => format!(
+            "{} {}",
+            read_u16(message, data_start)?,
+            read_name(message, data_start + 2)?
+        ),
+        16 => {
+            let mut strings = Vec::new();
+            let mut position = 0;
+            while let Some(&length) = data.get(position) {
+                let end = position + 1 + usize::from(length);
+                let text = data
+                    .get(position + 1..end)
+                    .ok_or_else(|| anyhow!("DNS TXT record is truncated"))?;
+                strings.push(format!("\"{}\"", String::from_utf8_lossy(text)));
+                position = end;
+            }
+            strings.join(" ")
+        }
+        other => format!("type {other} ({} bytes)", data.len()),
+    })
+}
+
+fn read_name(message: &[u8], mut offset: usize) -> Result<String> {
+    let mut labels = Vec::new();
+    let mut jumps = 0;
+
+    loop {
+        let length = *message
+            .get(offset)
+            .ok_or_else(|| anyhow!("DNS name is truncated"))?;
+        match length {
+            0 => break,
+            length if length & 0xc0 == 0xc0 => {
+                jumps += 1;
+                if jumps > MAX_COMPRESSION_JUMPS {
+                    anyhow::bail!("DNS name has too many compression pointers");
+                }
+                offset = usize::from(read_u16(message, offset)? & 0x3fff);
+            }
+            length => {
+                let start = offset + 1;
+                let label = message
+                    .get(start..start + usize::from(length))
+                    .ok_or_else(|| anyhow!("DNS label is truncated"))?;
+                labels.push(String::from_utf8_lossy(label).into_owned());
+                offset = start + usize::from(length);
+            }
+        }
+    }
+
+    Ok(labels.join("."))
+}
+
+fn skip_name(message: &[u8], mut offset: usize) -> Result<usize> {
+    loop {
+        let length = *message
+            .get(offset)
+            .ok_or_else(|| anyhow!("DNS name is truncated"))?;
+        match length {
+            0 => return Ok(offset + 1),
+            length if length & 0xc0 == 0xc0 => return Ok(offset + 2),
+            length => offset += 1 + usize::from(length),
+        }
+    }
+}
+
+fn read_u16(message: &[u8], offset: usize) -> Result<u16> {
+    message
+        .get(offset..offset + 2)
+        .map(|bytes| u16::from_be_bytes([bytes[0], bytes[1]]))
+        .ok_or_else(|| anyhow!("DNS message is truncated"))
+}
+
+fn read_u32(message: &[u8], offset: usize) -> Result<u32> {
+    message
+        .get(offset..offset + 4)
+        .map(|bytes| u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+        .ok_or_else(|| anyhow!("DNS message is truncated"))
+}
+
+#[cfg(test)]
+pub mod stub {
+    use super::{build_query, read_u16, skip_name, HEADER_LEN};
+    use bytes::Bytes;
+    use http_body_util::{BodyExt, Full};
+    use hyper::server::conn::http1;
+    use hyper::service::service_fn;
+    use hyper::{Request, Response};
+    use hyper_util::rt::TokioIo;
+    use std::convert::Infallible;
+    use std::net::{Ipv4Addr, SocketAddr};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, UdpSocket};
+
+    // Answers every A query with a fixed address and a CNAME hop, and every
+    // other type with NXDOMAIN.
+    pub async fn spawn(answer: Ipv4Addr, ttl: u32) -> SocketAddr {
+        let udp = UdpSocket::bind("127.0.0.1:0")
+            .await
+            .expect("stub UDP socket should bind");
+        let address = udp.local_addr().expect("stub should have an address");
+        let tcp = TcpListener::bind(address)
+            .await
+            .expect("stub TCP listener should bind");
+
+        tokio::spawn(async move {
+            let mut buffer = [0_u8; 512];
+            while let Ok((length, peer)) = udp.recv_from(&mut buffer).await {
+                let response = respond(&buffer[..length], answer, ttl);
+                let _ = udp.send_to(&response, peer).await;
+            }
+        });
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = tcp.accept().await {
+                let Ok(length) = stream.read_u16().await else {
+                    continue;
+                };
+                let mut query = vec![0_u8; usize::from(length)];
+                if stream.read_exact(&mut query).await.is_err() {
+                    continue;
+                }
+                let response = respond(&query, answer, ttl);
+                let mut framed = (response.len() as u16).to_be_bytes().to_vec();
+                framed.extend_from_slice(&response);
+                let _ = stream.write_all(&framed).await;
+            }
+        });
+
+        address
+    }
+
+    // Plain-HTTP stand-in for a DNS-over-HTTPS server that takes RFC 8484
+    // POST bodies at /dns-query.
+    pub async fn spawn_doh(answer: Ipv4Addr, ttl: u32) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("stub DoH listener should bind");
+        let address = listener.local_addr().expect("stub should have an address");
+
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                tokio::spawn(async move {
+                    let service =
+                        service_fn(move |request: Request<hyper::body::Incoming>| async move {
+                            let query = request
+                                .into_body()
+                                .collect()
+                                .await
+                                .map(|body| body.to_bytes())
+                                .unwrap_or_default();
+                            let response = Response::builder()
+                                .header("content-type", "application/dns-message")
+                                .body(Full::new(Bytes::from(respond(&query, answer, ttl))))
+                                .expect("stub response should build");
+                            Ok::<_, Infallible>(response)
+                        });
+                    let _ = http1::Builder::new()
+                        .serve_connection(TokioIo::new(stream), service)
+                        .await;
+                });
+            }
+        });
+
+        format!("http://{address}/dns-query")
+    }
+
+    fn respond(query: &[u8], answer: Ipv4Addr, ttl: u32) -> Vec<u8> {
+        let question_end = skip_name(query, HEADER_LEN).expect("query has a name") + 4;
+        let record_type = read_u16(query, question_end - 4).expect("query has a type");
+        let mut response = query[..question_end].to_vec();
+        let is_a = record_type == 1;
+        response[2] = 0x81;
+        response[3] = if is_a { 0x80 } else { 0x83 };
+        response[6..8].copy_from_slice(&(if is_a { 2_u16 } else { 0 }).to_be_bytes());
+
+        if is_a {
+            // <query name> CNAME alias.test, then the alias A record via a pointer.
+            let target = build_query(0, "alias.test", super::DnsRecordType::A)
+                .expect("static name is valid");
+            let alias_name = &target[HEADER_LEN..target.len() - 4];
+            response.extend_from_slice(&[0xc0, 0x0c, 0, 5, 0, 1]);
+            response.extend_from_slice(&ttl.to_be_bytes());
+            response.extend_from_slice(&(alias_name.len() as u16).to_be_bytes());
+            let alias_offset = response.len() as u16;
+            response.extend_from_slice(alias_name);
+            response.extend_from_slice(&(0xc000 | alias_offset).to_be_bytes());
+            response.extend_from_slice(&[0, 1, 0, 1]);
+            response.extend_from_slice(&ttl.to_be_bytes());
+            response.extend_from_slice(&4_u16.to_be_bytes());
+            response.extend_from_slice(&answer.octets());
+        }
+
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_query, parse_response, parse_server, stub, DnsRecordType, DnsServer, DnsSession,
+        DnsTransport,
+    };
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+
+    #[test]
+    fn builds_queries_and_parses_server_addresses() {
+        let query = build_query(0xbeef, "example.com.", DnsRecordType::Mx).expect("valid name");
+
+        assert_eq!(&query[..4], &[0xbe, 0xef, 0x01, 0x00]);
+        assert_eq!(&query[12..25], b"\x07example\x03com\x00");
+        assert_eq!(&query[25..], &[0, 15, 0, 1]);
+        assert!(build_query(1, "bad..name", DnsRecordType::A).is_err());
+        assert_eq!(
+            &build_query(1, ".", DnsRecordType::A).expect("root is valid")[12..],
+            &[0, 0, 1, 0, 1]
+        );
+
+        let parse = |value| {
+            parse_server(value, DnsTransport::Udp)
+                .expect("valid server")
+                .to_string()
+        };
+        assert_eq!(parse("9.9.9.9"), "9.9.9.9:53");
+        assert_eq!(parse("tcp://[::1]:5353"), "[::1]:5353");
+        assert_eq!(parse("tls://1.1.1.1"), "tls://1.1.1.1:853");
+        assert_eq!(
+            parse("tls://dns.quad9.net:8853"),
+            "tls://dns.quad9.net:8853"
+        );
+        assert_eq!(
+            parse("https://dns.example/dns-query"),
+            "https://dns.example/dns-query"
+        );
+        assert_eq!(
+            parse_server("tcp://9.9.9.9", DnsTransport::Udp)
+                .expect("valid server")
+                .transport(),
+            DnsTransport::Tcp
+        );
+        assert_eq!(
+            parse_server("2606:4700::1111", DnsTransport::Doh).expect("valid server"),
+            DnsServer::Https {
+                url: "https://[2606:4700::1111]/dns-query".to_string()
+            }
+        );
+        assert!(parse_server("resolver.example", DnsTransport::Udp).is_err());
+        assert!(parse_server("quic://1.1.1.1", DnsTransport::Udp).is_err());
+    }
+
+    #[test]
+    fn rejects_responses_for_other_queries() {
+        let mut message = build_query(7, "example.com", DnsRecordType::A).expect("valid name");
+        assert!(parse_response(&message, 7).is_err());
+
+        message[2] |= 0x80;
+        assert!(parse_response(&message, 7).is_ok());
+        assert!(parse_response(&message, 8).is_err());
+    }
+
+    #[tokio::test]
+    async fn queries_local_stub_over_udp_tcp_and_https() {
+        let address = stub::spawn(Ipv4Addr::new(192, 0, 2, 10), 300).await;
+        let servers = [
+            DnsServer::Plain {
+                address,
+                transport: DnsTransport::Udp,
+            },
+            DnsServer::Plain {
+                address,
+                transport: DnsTransport::Tcp,
+            },
+            DnsServer::Https {
+                url: stub::spawn_doh(Ipv4Addr::new(192, 0, 2, 10), 300).await,
+            },
+        ];
+
+        for server in servers {
+            let (mut session, handshake) = DnsSession::connect(&server, Duration::from_secs(2))
+                .await
+                .expect("stub should accept connections");
+            assert_eq!(handshake.is_some(), server.transport() == DnsTransport::Doh);
+
+            let exchange = session
+                .query("www.example.test", DnsRecordType::A, Duration::from_secs(2))
+                .await
+                .expect("stub query should succeed");
+
+            assert_eq!(exchange.transport, server.transport());
+            assert_eq!(exchange.response.rcode, 0);
+            let answers = exchange
+                .response
+                .answers
+                .iter()
+                .map(|answer| answer.data.as_str())
+                .collect::<Vec<_>>();
+            assert_eq!(answers, vec!["alias.test", "192.0.2.10"]);
+            assert!(exchange
+                .response
+                .answers
+                .iter()
+                .all(|answer| answer.ttl == 300));
+
+            let missing = session
+                .query(
+                    "www.example.test",
+                    DnsRecordType::Txt,
+                    Duration::from_secs(2),
+                )
+                .await
+                .expect("stub query should succeed");
+            assert_eq!(missing.response.rcode, 3);
+            assert!(missing.response.answers.is_empty());
+        }
+    }
+}
@@ -0,0 +1,435 @@
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::collections::hash_map::RandomState;
+use std::fs;
+use std::hash::{BuildHasher, Hasher};
+use std::path::PathBuf;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use crate::dns::{self, DnsRecordType, DnsServer, DnsSession, DnsTransport};
+use crate::probe::{calculate_stats, duration_to_ms, MetricStats};
+use crate::version;
+
+const DEFAULT_RESOLVERS: [&str; 3] = ["1.1.1.1", "8.8.8.8", "9.9.9.9"];
+const DEFAULT_DOMAINS: [&str; 3] = ["example.com", "wikipedia.org", "github.com"];
+const DEFAULT_QUERIES: u32 = 5;
+const DEFAULT_TIMEOUT_MS: u64 = 2_000;
+
+#[derive(Debug, Clone)]
+pub struct DnsBenchOptions {
+    pub resolvers: Vec<DnsServer>,
+    pub domains: Vec<String>,
+    pub record_type: DnsRecordType,
+    pub queries: u32,
+    pub timeout: Duration,
+}
+
+#[derive(Debug, Clone)]
+pub struct DnsBenchOverrides {
+    pub config: Option<PathBuf>,
+    pub resolvers: Vec<String>,
+    pub domains: Vec<String>,
+    pub record_type: DnsRecordType,
+    pub transport: DnsTransport,
+    pub queries: Option<u32>,
+    pub timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct DnsBenchFile {
+    #[serde(default)]
+    resolvers: Vec<String>,
+    #[serde(default)]
+    domains: Vec<String>,
+    queries: Option<u32>,
+    timeout_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsBenchReport {
+    pub created_at_unix_ms: u128,
+    pub record_type: DnsRecordType,
+    pub queries_per_domain: u32,
+    pub domains: Vec<String>,
+    pub resolvers: Vec<DnsBenchResolver>,
+    pub recommended: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsBenchResolver {
+    pub rank: u32,
+    pub server: String,
+    pub transport: DnsTransport,
+    pub cold: DnsBenchSeries,
+    pub warm: DnsBenchSeries,
+    pub failure_pct: f64,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsBenchSeries {
+    pub queries: u32,
+    pub failures: u32,
+    pub failure_pct: f64,
+    pub latency: Option<MetricStats>,
+    pub samples_ms: Vec<f64>,
+}
+
+#[derive(Debug, Default)]
+struct ResolverSamples {
+    cold: Vec<Option<f64>>,
+    warm: Vec<Option<f64>>,
+    last_error: Option<String>,
+}
+
+pub fn resolve_dns_bench_options(overrides: DnsBenchOverrides) -> Result<DnsBenchOptions> {
+    let file = match &overrides.config {
+        Some(path) => {
+            let contents = fs::read_to_string(path)
+                .with_context(|| format!("failed to read {}", path.display()))?;
+            serde_json::from_str::<DnsBenchFile>(&contents)
+                .with_context(|| format!("failed to parse {}", path.display()))?
+        }
+        None => DnsBenchFile::default(),
+    };
+
+    let resolvers = if !overrides.resolvers.is_empty() {
+        overrides.resolvers
+    } else if !file.resolvers.is_empty() {
+        file.resolvers
+    } else {
+        DEFAULT_RESOLVERS.map(String::from).to_vec()
+    };
+    let domains = if !overrides.domains.is_empty() {
+        overrides.domains
+    } else if !file.domains.is_empty() {
+        file.domains
+    } else {
+        DEFAULT_DOMAINS.map(String::from).to_vec()
+    };
+
+    Ok(DnsBenchOptions {
+        resolvers: resolvers
+            .iter()
+            .map(|resolver| dns::parse_server(resolver, overrides.transport))
+            .collect::<Result<Vec<_>>>()?,
+        domains,
+        record_type: overrides.record_type,
+        queries: overrides
+            .queries
+            .or(file.queries)
+            .unwrap_or(DEFAULT_QUERIES)
+            .max(1),
+        timeout: Duration::from_millis(
+            overrides
+                .timeout_ms
+                .or(file.timeout_ms)
+                .unwrap_or(DEFAULT_TIMEOUT_MS)
+                .max(1),
+        ),
+    })
+}
+
+pub async fn run_dns_bench(options: &DnsBenchOptions) -> Result<DnsBenchReport> {
+    let mut samples = options
+        .resolvers
+        .iter()
+        .map(|_| ResolverSamples::default())
+        .collect::<Vec<_>>();
+    let mut sessions = Vec::with_capacity(options.resolvers.len());
+    for (server, samples) in options.resolvers.iter().zip(samples.iter_mut()) {
+        match DnsSession::connect(server, options.timeout).await {
+            Ok((session, _)) => sessions.push(Some(session)),
+            Err(error) => {
+                samples.last_error = Some(error.to_string());
+                sessions.push(None);
+            }
+        }
+    }
+
+    for domain in &options.domains {
+        // Prime every resolver's cache so the warm lookups below are cache hits.
+        for session in sessions.iter_mut().flatten() {
+            let _ = lookup(options, session, domain).await;
+        }
+
+        // Resolvers take turns within each round so a slow moment on the path
+        // does not land on a single resolver.
+        for _ in 0..options.queries {
+            for (session, samples) in sessions.iter_mut().zip(samples.iter_mut()) {
+                let Some(session) = session.as_mut() else {
+                    samples.cold.push(None);
+                    samples.warm.push(None);
+                    continue;
+                };
+                let cold_name = format!("{}.{domain}", cache_busting_label());
+                let cold = lookup(options, session, &cold_name).await;
+                let warm = lookup(options, session, domain).await;
+                for (result, series) in [(cold, &mut samples.cold), (warm, &mut samples.warm)] {
+                    match result {
+                        Ok(rtt_ms) => series.push(Some(rtt_ms)),
+                        Err(error) => {
+                            samples.last_error = Some(error.to_string());
+                            series.push(None);
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    let resolvers = rank_resolvers(
+        options
+            .resolvers
+            .iter()
+            .zip(samples)
+            .map(|(server, samples)| summarize_resolver(server, samples))
+            .collect(),
+    );
+
+    Ok(DnsBenchReport {
+        created_at_unix_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("system clock is before unix epoch")?
+            .as_millis(),
+        record_type: options.record_type,
+        queries_per_domain: options.queries,
+        domains: options.domains.clone(),
+        recommended: resolvers
+            .first()
+            .filter(|resolver| resolver.failure_pct < 100.0)
+            .map(|resolver| resolver.server.clone()),
+        resolvers,
+    })
+}
+
+async fn lookup(options: &DnsBenchOptions, session: &mut DnsSession, name: &str) -> Result<f64> {
+    let exchange = session
+        .query(name, options.record_type, options.timeout)
+        .await?;
+
+    // NXDOMAIN is the expected answer for cache-busting names, so only
+    // server-side failures count against the resolver.
+    match exchange.response.rcode {
+        0 | 3 => Ok(duration_to_ms(exchange.rtt)),
+        rcode => anyhow::bail!("answered {name} with {}", dns::rcode_name(rcode)),
+    }
+}
+
+fn cache_busting_label() -> String {
+    format!("pp-{:016x}", RandomState::new().build_hasher().finish())
+}
+
+fn summarize_resolver(server: &DnsServer, samples: ResolverSamples) -> DnsBenchResolver {
+    let cold = summarize_series(&samples.cold);
+    let warm = summarize_series(&samples.warm);
+    let queries = cold.queries + warm.queries;
+
+    DnsBenchResolver {
+        rank: 0,
+        server: server.to_string(),
+        transport: server.transport(),
+        failure_pct: (cold.failures + warm.failures) as f64 / queries.max(1) as f64 * 100.0,
+        cold,
+        warm,
+        last_error: samples.last_error,
+    }
+}
+
+fn summarize_series(samples: &[Option<f64>]) -> DnsBenchSeries {
+    let samples_ms = samples.iter().flatten().copied().collect::<Vec<_>>();
+    let queries = samples.len() as u32;
+    let failures = queries - samples_ms.len() as u32;
+
+    DnsBenchSeries {
+        queries,
+        failures,
+        failure_pct: failures as f64 / queries.max(1) as f64 * 100.0,
+        latency: calculate_stats(&samples_ms),
+        samples_ms,
+    }
+}
+
+fn rank_resolvers(mut resolvers: Vec<DnsBenchResolver>) -> Vec<DnsBenchResolver> {
+    let key = |series: &DnsBenchSeries, pick: fn(&MetricStats) -> f64| {
+        series.latency.as_ref().map(pick).unwrap_or(f64::INFINITY)
+    };
+
+    // Reliability first, then what clients see most (warm median and tail),
+    // with cold median as the final tie-breaker.
+    resolvers.sort_by(|left, right| {
+        left.failure_pct
+            .total_cmp(&right.failure_pct)
+            .then_with(|| {
+                key(&left.warm, |stats| stats.median)
+                    .total_cmp(&key(&right.warm, |stats| stats.median))
+            })
+            .then_with(|| {
+                key(&left.warm, |stats| stats.p95).total_cmp(&key(&right.warm, |stats| stats.p95))
+            })
+            .then_with(|| {
+                key(&left.cold, |stats| stats.median)
+                    .total_cmp(&key(&right.cold, |stats| stats.median))
+            })
+    });
+    for (index, resolver) in resolvers.iter_mut().enumerate() {
+        resolver.rank = index as u32 + 1;
+    }
+
+    resolvers
+}
+
+pub fn format_dns_bench(report: &DnsBenchReport) -> String {
+    let mut lines = vec![
+        format!(
+            "PantheonProbe v{} | DNS resolver benchmark",
+            version::VERSION
+        ),
+        format!(
+            "Domains: {} | Record: {} | Queries: {} cold + {} warm per domain",
+            report.domains.join(", "),
+            report.record_type,
+            report.queries_per_domain,
+            report.queries_per_domain
+        ),
+        String::new(),
+    ];
+
+    for resolver in &report.resolvers {
+        lines.push(format!(
+            "#{} {} ({}) | failures: {:.2}%",
+            resolver.rank, resolver.server, resolver.transport, resolver.failure_pct
+        ));
+        lines.push(format_series("warm", &resolver.warm));
+        lines.push(format_series("cold", &resolver.cold));
+        if let Some(error) = &resolver.last_error {
+            lines.push(format!("  last error: {error}"));
+        }
+    }
+
+    lines.push(String::new());
+    lines.push(format!(
+        "Recommended: {}",
+        report
+            .recommended
+            .as_deref()
+            .unwrap_or("none (no resolver answered)")
+    ));
+
+    lines.join("\n")
+}
+
+fn format_series(label: &str, series: &DnsBenchSeries) -> String {
+    let latency = series
+        .latency
+        .as_ref()
+        .map(|stats| format!("{:.2}/{:.2}/{:.2} ms", stats.min, stats.median, stats.p95))
+        .unwrap_or_else(|| "unavailable".to_string());
+
+    format!(
+        "  {label}: min/median/p95 {latency} | {}/{} answered",
+        series.queries - series.failures,
+        series.queries
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        format_dns_bench, resolve_dns_bench_options, run_dns_bench, DnsBenchOptions,
+        DnsBenchOverrides,
+    };
+    use crate::dns::{self, DnsRecordType, DnsServer, DnsTransport};
+    use std::net::Ipv4Addr;
+    use std::time::Duration;
+    use tokio::net::UdpSocket;
+
+    #[test]
+    fn flags_override_config_file_resolvers_and_domains() {
+        let path = std::env::temp_dir().join(format!(
+            "pantheon-probe-dns-bench-{}.json",
+            std::process::id()
+        ));
+        std::fs::write(
+            &path,
+            r#"{"resolvers": ["10.0.0.53", "[2001:db8::53]:5353"], "domains": ["corp.example"], "queries": 3}"#,
+        )
+        .expect("config should be written");
+
+        let overrides = DnsBenchOverrides {
+            config: Some(path.clone()),
+            resolvers: Vec::new(),
+            domains: Vec::new(),
+            record_type: DnsRecordType::A,
+            transport: DnsTransport::Udp,
+            queries: None,
+            timeout_ms: None,
+        };
+        let from_file =
+            resolve_dns_bench_options(overrides.clone()).expect("config file should resolve");
+        let from_flags = resolve_dns_bench_options(DnsBenchOverrides {
+            resolvers: vec!["1.1.1.1".to_string(), "tls://dns.quad9.net".to_string()],
+            queries: Some(7),
+            ..overrides
+        })
+        .expect("flags should resolve");
+        std::fs::remove_file(&path).expect("config should be removed");
+
+        assert_eq!(
+            from_file
+                .resolvers
+                .iter()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>(),
+            vec!["10.0.0.53:53", "[2001:db8::53]:5353"]
+        );
+        assert_eq!(from_file.domains, vec!["corp.example"]);
+        assert_eq!(from_file.queries, 3);
+        assert_eq!(from_flags.resolvers[0].to_string(), "1.1.1.1:53");
+        assert_eq!(from_flags.resolvers[1].transport(), DnsTransport::Dot);
+        assert_eq!(from_flags.domains, vec!["corp.example"]);
+        assert_eq!(from_flags.queries, 7);
+    }
+
+    #[tokio::test]
+    async fn ranks_answering_resolvers_above_silent_ones() {
+        let answering = dns::stub::spawn(Ipv4Addr::new(192, 0, 2, 10), 60).await;
+        let silent = UdpSocket::bind("127.0.0.1:0")
+            .await
+            .expect("silent socket should bind");
+
+        let plain = |address| DnsServer::Plain {
+            address,
+            transport: DnsTransport::Udp,
+        };
+        let report = run_dns_bench(&DnsBenchOptions {
+            resolvers: vec![
+                plain(silent.local_addr().expect("silent address")),
+                plain(answering),
+            ],
+            domains: vec!["example.test".to_string()],
+            record_type: DnsRecordType::A,
+            queries: 2,
+            timeout: Duration::from_millis(100),
+        })
+        .await
+        .expect("benchmark should run");
+
+        let best = &report.resolvers[0];
+        assert_eq!(best.rank, 1);
+        assert_eq!(best.server, answering.to_string());
+        assert_eq!(best.failure_pct, 0.0);
+        assert_eq!(best.cold.queries, 2);
+        assert_eq!(best.warm.samples_ms.len(), 2);
+        assert!(best.warm.latency.is_some());
+
+        let worst = &report.resolvers[1];
+        assert_eq!(worst.rank, 2);
+        assert_eq!(worst.failure_pct, 100.0);
+        assert!(worst.warm.latency.is_none());
+        assert!(worst.last_error.is_some());
+
+        assert_eq!(report.recommended, Some(answering.to_string()));
+        assert!(format_dns_bench(&report).contains(&format!("Recommended: {answering}")));
+    }
+}
@@ -0,0 +1,326 @@
+use anyhow::{anyhow, Context, Result};
+use reqwest::Url;
+use rustls_platform_verifier::ConfigVerifierExt;
+use std::net::SocketAddr;
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::ClientConfig;
+use tokio_rustls::TlsConnector;
+
+use crate::probe::AddressFamily;
+
+const READ_BUFFER_LEN: usize = 64 * 1024;
+const MAX_HEADER_BYTES: usize = 64 * 1024;
+
+#[derive(Debug, Clone)]
+pub struct PhaseTimings {
+    pub remote_address: SocketAddr,
+    pub status: u16,
+    pub dns: Duration,
+    pub connect: Duration,
+    pub tls: Option<Duration>,
+    pub time_to_first_byte: Duration,
+    pub transfer: Duration,
+    pub total: Duration,
+    pub body_bytes: u64,
+}
+
+// With a body limit, reading stops and the connection is dropped once that
+// many body bytes have arrived, whatever the server meant to send.
+pub async fn timed_get(
+    url: &str,
+    headers: &[(&str, &str)],
+    family: Option<AddressFamily>,
+    body_limit: Option<u64>,
+    timeout: Duration,
+) -> Result<PhaseTimings> {
+    tokio::time::timeout(timeout, run_timed_get(url, headers, family, body_limit))
+        .await
+        .map_err(|_| anyhow!("request to {url} timed out after {}s", timeout.as_secs()))?
+}
+
+async fn run_timed_get(
+    url: &str,
+    headers: &[(&str, &str)],
+    family: Option<AddressFamily>,
+    body_limit: Option<u64>,
+) -> Result<PhaseTimings> {
+    let parsed = Url::parse(url).with_context(|| format!("invalid URL {url}"))?;
+    let secure = match parsed.scheme() {
+        "https" => true,
+        "http" => false,
+        scheme => anyhow::bail!("unsupported URL scheme {scheme} in {url}"),
+    };
+    let host = parsed
+        .host_str()
+        .ok_or_else(|| anyhow!("URL {url} has no host"))?
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .to_string();
+    let port = parsed
+        .port_or_known_default()
+        .ok_or_else(|| anyhow!("URL {url} has no port"))?;
+
+    let started = Instant::now();
+    let remote_address = tokio::net::lookup_host((host.as_str(), port))
+        .await
+        .with_context(|| format!("failed to resolve {host}"))?
+        .find(|address| family.is_none_or(|family| family.matches(&address.ip())))
+        .ok_or_else(|| match family {
+            Some(family) => anyhow!("no {family} addresses resolved for {host}"),
+            None => anyhow!("no addresses resolved for {host}"),
+        })?;
+    let dns = started.elapsed();
+
+    let connect_started = Instant::now();
+    let stream = TcpStream::connect(remote_address)
+        .await
+        .with_context(|| format!("failed to connect to {remote_address}"))?;
+    let connect = connect_started.elapsed();
+
+    let mut phases = ConnectPhases {
+        started,
+        remote_address,
+        dns,
+        connect,
+        tls: None,
+        body_limit,
+    };
+    let request = build_request(&parsed, headers);
+    if secure {
+        let tls_started = Instant::now();
+        let server_name = ServerName::try_from(host.clone())
+            .with_context(|| format!("invalid TLS server name {host}"))?;
+        let stream = TlsConnector::from(tls_config()?)
+            .connect(server_name, stream)
+            .await
+            .with_context(|| format!("TLS handshake with {host} failed"))?;
+        phases.tls = Some(tls_started.elapsed());
+
+        exchange(stream, &request, phases).await
+    } else {
+        exchange(stream, &request, phases).await
+    }
+}
+
+struct ConnectPhases {
+    started: Instant,
+    remote_address: SocketAddr,
+    dns: Duration,
+    connect: Duration,
+    tls: Option<Duration>,
+    body_limit: Option<u64>,
+}
+
+async fn exchange<S: AsyncRead + AsyncWrite + Unpin>(
+    mut stream: S,
+    request: &str,
+    phases: ConnectPhases,
+) -> Result<PhaseTimings> {
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .context("failed to send HTTP request")?;
+    let request_sent = Instant::now();
+
+    let mut buffer = vec![0_u8; READ_BUFFER_LEN];
+    let mut head = Vec::new();
+    let mut first_byte_at = None;
+    let (head_len, status, content_length) = loop {
+        let read = read_allowing_unclean_close(&mut stream, &mut buffer).await?;
+        if read == 0 {
+            anyhow::bail!("connection closed before response headers were received");
+        }
+        first_byte_at.get_or_insert_with(Instant::now);
+        head.extend_from_slice(&buffer[..read]);
+
+        if let Some(parsed) = parse_response_head(&head)? {
+            break parsed;
+        }
+        if head.len() > MAX_HEADER_BYTES {
+            anyhow::bail!("response headers exceeded {MAX_HEADER_BYTES} bytes");
+        }
+    };
+    let first_byte_at = first_byte_at.expect("first byte was received");
+
+    let mut body_bytes = (head.len() - head_len) as u64;
+    let wanted = match (content_length, phases.body_limit) {
+        (Some(length), Some(limit)) => Some(length.min(limit)),
+        (length, limit) => length.or(limit),
+    };
+    while wanted.is_none_or(|wanted| body_bytes < wanted) {
+        let read = read_allowing_unclean_close(&mut stream, &mut buffer).await?;
+        if read == 0 {
+            break;
+        }
+        body_bytes += read as u64;
+    }
+    let finished = Instant::now();
+
+    Ok(PhaseTimings {
+        remote_address: phases.remote_address,
+        status,
+        dns: phases.dns,
+        connect: phases.connect,
+        tls: phases.tls,
+        time_to_first_byte: first_byte_at.duration_since(request_sent),
+        transfer: finished.duration_since(first_byte_at),
+        total: finished.duration_since(phases.started),
+        body_bytes,
+    })
+}
+
+async fn read_allowing_unclean_close<S: AsyncRead + Unpin>(
+    stream: &mut S,
+    buffer: &mut [u8],
+) -> Result<usize> {
+    match stream.read(buffer).await {
+        Ok(read) => Ok(read),
+        // Plenty of servers close TLS connections without a close_notify alert.
+        Err(error) if error.kind() == std::io::ErrorKind::UnexpectedEof => Ok(0),
+        Err(error) => Err(error).context("failed to read HTTP response"),
+    }
+}
+
+fn tls_config() -> Result<Arc<ClientConfig>> {
+    static CONFIG: OnceLock<Arc<ClientConfig>> = OnceLock::new();
+    if let Some(config) = CONFIG.get() {
+        return Ok(config.clone());
+    }
+
+    let mut config =
+        ClientConfig::with_platform_verifier().context("failed to load TLS trust roots")?;
+    config.alpn_protocols = vec![b"http/1.1".to_vec()];
+    Ok(CONFIG.get_or_init(|| Arc::new(config)).clone())
+}
+
+fn build_request(url: &Url, headers: &[(&str, &str)]) -> String {
+    let mut target = url.path().to_string();
+    if let Some(query) = url.query() {
+        target.push('?');
+        target.push_str(query);
+    }
+    let host = match url.port() {
+        Some(port) => format!("{}:{port}", url.host_str().unwrap_or_default()),
+        None => url.host_str().unwrap_or_default().to_string(),
+    };
+
+    let mut request = format!(
+        "GET {target} HTTP/1.1\r\nHost: {host}\r\nUser-Agent: pantheon-probe/{}\r\nAccept: */*\r\nConnection: close\r\n",
+        env!("CARGO_PKG_VERSION")
+    );
+    for (name, value) in headers {
+        request.push_str(&format!("{name}: {value}\r\n"));
+    }
+    request.push_str("\r\n");
+    request
+}
+
+pub fn parse_response_head(bytes: &[u8]) -> Result<Option<(usize, u16, Option<u64>)>> {
+    let Some(end) = bytes.windows(4).position(|window| window == b"\r\n\r\n") else {
+        return Ok(None);
+    };
+    let head = String::from_utf8_lossy(&bytes[..end]);
+    let mut lines = head.lines();
+    let status_line = lines.next().unwrap_or_default();
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|code| code.parse::<u16>().ok())
+        .ok_or_else(|| anyhow!("malformed HTTP status line: {status_line}"))?;
+    let content_length = lines.find_map(|line| {
+        let (name, value) = line.split_once(':')?;
+        name.trim()
+            .eq_ignore_ascii_case("content-length")
+            .then(|| value.trim().parse::<u64>().ok())
+            .flatten()
+    });
+
+    Ok(Some((end + 4, status, content_length)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{build_request, parse_response_head, timed_get, AddressFamily};
+    use crate::server;
+    use reqwest::Url;
+    use std::time::Duration;
+
+    #[test]
+    fn builds_request_with_query_port_and_extra_headers() {
+        let url = Url::parse("http://example.test:8080/__down?bytes=10").expect("valid url");
+        let request = build_request(&url, &[("Range", "bytes=0-9")]);
+
+        assert!(request.starts_with("GET /__down?bytes=10 HTTP/1.1\r\n"));
+        assert!(request.contains("Host: example.test:8080\r\n"));
+        assert!(request.contains("Range: bytes=0-9\r\n"));
+        assert!(request.ends_with("\r\n\r\n"));
+    }
+
+    #[test]
+    fn parses_status_and_content_length_once_headers_complete() {
+        assert!(parse_response_head(b"HTTP/1.1 200 OK\r\nContent-")
+            .expect("partial head should not error")
+            .is_none());
+
+        let head = b"HTTP/1.1 206 Partial Content\r\ncontent-length: 1024\r\n\r\nabc";
+        let parsed = parse_response_head(head)
+            .expect("head should parse")
+            .expect("head should be complete");
+
+        assert_eq!(parsed, (head.len() - 3, 206, Some(1_024)));
+    }
+
+    #[tokio::test]
+    async fn times_each_phase_against_local_server() {
+        let listener = server::bind_server("127.0.0.1:0")
+            .await
+            .expect("server should bind");
+        let address = listener
+            .local_addr()
+            .expect("server should have an address");
+        tokio::spawn(server::serve(listener));
+
+        let timings = timed_get(
+            &format!("http://{address}/__down?bytes=300000"),
+            &[],
+            Some(AddressFamily::V4),
+            None,
+            Duration::from_secs(10),
+        )
+        .await
+        .expect("timed request should succeed");
+
+        assert_eq!(timings.status, 200);
+        assert_eq!(timings.body_bytes, 300_000);
+        assert_eq!(timings.remote_address, address);
+        assert!(timings.tls.is_none());
+        assert!(timings.total >= timings.connect + timings.time_to_first_byte);
+
+        let limited = timed_get(
+            &format!("http://{address}/__down?bytes=40000000"),
+            &[],
+            None,
+            Some(1_024),
+            Duration::from_secs(10),
+        )
+        .await
+        .expect("limited request should succeed");
+        assert_eq!(limited.status, 200);
+        assert!(limited.body_bytes >= 1_024 && limited.body_bytes < 40_000_000);
+
+        let error = timed_get(
+            &format!("http://{address}/health"),
+            &[],
+            Some(AddressFamily::V6),
+            None,
+            Duration::from_secs(10),
+        )
+        .await
+        .expect_err("an IPv4 literal has no IPv6 address");
+        assert!(error.to_string().contains("no IPv6 addresses resolved"));
+    }
+}
@@ -0,0 +1,718 @@
+use anyhow::{Context, Result};
+use socket2::{Domain, Protocol, SockAddr, SockRef, Socket, Type};
+use std::io::{self, Read};
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::time::{Duration, Instant};
+
+const ICMPV4_ECHO_REQUEST: u8 = 8;
+const ICMPV4_ECHO_REPLY: u8 = 0;
+const ICMPV4_DESTINATION_UNREACHABLE: u8 = 3;
+const ICMPV4_TIME_EXCEEDED: u8 = 11;
+const ICMPV6_ECHO_REQUEST: u8 = 128;
+const ICMPV6_ECHO_REPLY: u8 = 129;
+const ICMPV6_DESTINATION_UNREACHABLE: u8 = 1;
+const ICMPV6_TIME_EXCEEDED: u8 = 3;
+const ICMP_HEADER_LEN: usize = 8;
+const IPV6_HEADER_LEN: usize = 40;
+const RECEIVE_BUFFER_LEN: usize = 2048;
+
+// Only Linux hands routers' Fragmentation Needed / Packet Too Big messages to
+// an ICMP socket; elsewhere an unanswered probe cannot be told apart from one
+// a router rejected.
+pub const REPORTS_FRAGMENTATION_NEEDED: bool =
+    cfg!(any(target_os = "linux", target_os = "android"));
+
+// Linux and Android rewrite the identifier on datagram ICMP sockets, strip the
+// IPv4 header, and only deliver replies to the socket that sent the echo. On
+// macOS and the BSDs a datagram socket sees every echo reply on the host,
+// IPv4 header included.
+const KERNEL_OWNS_DATAGRAM_ECHO: bool = cfg!(any(target_os = "linux", target_os = "android"));
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EchoSocketKind {
+    Datagram,
+    Raw,
+}
+
+pub struct EchoSocket {
+    socket: Socket,
+    kind: EchoSocketKind,
+    address: IpAddr,
+    identifier: u16,
+}
+
+impl EchoSocket {
+    pub fn open(address: IpAddr) -> Result<Self> {
+        let (domain, protocol) = match address {
+            IpAddr::V4(_) => (Domain::IPV4, Protocol::ICMPV4),
+            IpAddr::V6(_) => (Domain::IPV6, Protocol::ICMPV6),
+        };
+
+        let (socket, kind) = match Socket::new(domain, Type::DGRAM, Some(protocol)) {
+            Ok(socket) => (socket, EchoSocketKind::Datagram),
+            Err(datagram_error) => {
+                let socket = Socket::new(domain, Type::RAW, Some(protocol)).with_context(|| {
+                    format!("failed to open ICMP socket (datagram: {datagram_error})")
+                })?;
+                (socket, EchoSocketKind::Raw)
+            }
+        };
+
+        socket
+            .connect(&SockAddr::from(SocketAddr::new(address, 0)))
+            .with_context(|| format!("failed to connect ICMP socket to {address}"))?;
+
+        Ok(Self {
+            socket,
+            kind,
+            address,
+            identifier: std::process::id() as u16,
+        })
+    }
+
+    pub fn kind(&self) -> EchoSocketKind {
+        self.kind
+    }
+
+    pub fn echo(
+        &self,
+        sequence: u16,
+        payload_size: usize,
+        timeout: Duration,
+    ) -> Result<Option<Duration>> {
+        let started = self
+            .send_request(sequence, payload_size)
+            .with_context(|| format!("failed to send ICMP echo to {}", self.address))?;
+
+        self.wait_for_reply(sequence, started, timeout)
+    }
+
+    pub fn set_ttl(&self, ttl: u8) -> Result<()> {
+        match self.address {
+            IpAddr::V4(_) => self.socket.set_ttl_v4(u32::from(ttl)),
+            IpAddr::V6(_) => self.socket.set_unicast_hops_v6(u32::from(ttl)),
+        }
+        .with_context(|| format!("failed to set TTL {ttl} on ICMP socket to {}", self.address))
+    }
+
+    pub fn send(&self, sequence: u16, payload_size: usize) -> Result<Instant> {
+        self.send_request(sequence, payload_size)
+            .with_context(|| format!("failed to send ICMP echo to {}", self.address))
+    }
+
+    // Returns the sequence number of the next echo reply for this socket, so
+    // callers can see late, duplicated, and reordered replies.
+    pub fn receive(&self, deadline: Instant) -> Result<Option<u16>> {
+        let mut buffer = [0_u8; RECEIVE_BUFFER_LEN];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+
+            self.socket
+                .set_read_timeout(Some(remaining))
+                .context("failed to set ICMP read timeout")?;
+
+            let length = match (&self.socket).read(&mut buffer) {
+                Ok(length) => length,
+                Err(error)
+                    if matches!(
+                        error.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None)
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => {
+                    return Err(error).with_context(|| {
+                        format!("failed to receive ICMP echo from {}", self.address)
+                    })
+                }
+            };
+
+            if let Some(sequence) = parse_socket_reply(
+                &buffer[..length],
+                self.address,
+                self.kind,
+                self.identifier,
+                KERNEL_OWNS_DATAGRAM_ECHO,
+            ) {
+                return Ok(Some(sequence));
+            }
+        }
+    }
+
+    pub fn set_dont_fragment(&self) -> Result<()> {
+        set_dont_fragment(&self.socket, self.address).with_context(|| {
+            format!(
+                "failed to set the don't-fragment flag on the ICMP socket to {}",
+                self.address
+            )
+        })
+    }
+
+    pub fn echo_unfragmented(
+        &self,
+        sequence: u16,
+        payload_size: usize,
+        timeout: Duration,
+    ) -> Result<UnfragmentedEcho> {
+        // A rejection that arrived after an earlier probe timed out would
+        // otherwise fail this send with a stale EMSGSIZE.
+        self.drain_error_queue();
+        let started = match self.send_request(sequence, payload_size) {
+            Ok(started) => started,
+            Err(error) if is_message_too_big(&error) => return Ok(UnfragmentedEcho::TooBig),
+            Err(error) => {
+                return Err(error)
+                    .with_context(|| format!("failed to send ICMP echo to {}", self.address))
+            }
+        };
+
+        match self.wait_for_reply(sequence, started, timeout) {
+            Ok(Some(rtt)) => Ok(UnfragmentedEcho::Reply(rtt)),
+            Ok(None) => Ok(UnfragmentedEcho::Lost),
+            // With IP_RECVERR a router's rejection surfaces as EMSGSIZE on receive.
+            Err(error)
+                if error
+                    .root_cause()
+                    .downcast_ref::<io::Error>()
+                    .is_some_and(is_message_too_big) =>
+            {
+                self.drain_error_queue();
+                Ok(UnfragmentedEcho::FragmentationNeeded)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    fn drain_error_queue(&self) {
+        let mut buffer = [std::mem::MaybeUninit::<u8>::uninit(); RECEIVE_BUFFER_LEN];
+        while self
+            .socket
+            .recv_with_flags(&mut buffer, libc::MSG_ERRQUEUE | libc::MSG_DONTWAIT)
+            .is_ok()
+        {}
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    fn drain_error_queue(&self) {}
+
+    fn send_request(&self, sequence: u16, payload_size: usize) -> io::Result<Instant> {
+        let request = build_echo_request(self.address, self.identifier, sequence, payload_size);
+        let started = Instant::now();
+        self.socket.send(&request)?;
+        Ok(started)
+    }
+
+    fn wait_for_reply(
+        &self,
+        sequence: u16,
+        started: Instant,
+        timeout: Duration,
+    ) -> Result<Option<Duration>> {
+        let deadline = started + timeout;
+        loop {
+            match self.receive(deadline)? {
+                Some(reply) if reply == sequence => return Ok(Some(started.elapsed())),
+                Some(_) => continue,
+                None => return Ok(None),
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UnfragmentedEcho {
+    Reply(Duration),
+    // The local interface MTU refused the send.
+    TooBig,
+    // A router on the path answered with Fragmentation Needed / Packet Too Big.
+    FragmentationNeeded,
+    Lost,
+}
+
+// Linux's probe mode sets DF but ignores the cached path MTU, so sizes above a
+// previously learned PMTU are still sent and can be measured. Probe mode also
+// stops routers' rejections from reaching the socket, so RECVERR queues them.
+#[cfg(any(target_os = "linux", target_os = "android"))]
+fn set_dont_fragment(socket: &Socket, address: IpAddr) -> io::Result<()> {
+    match address {
+        IpAddr::V4(_) => {
+            set_int_option(
+                socket,
+                libc::IPPROTO_IP,
+                libc::IP_MTU_DISCOVER,
+                libc::IP_PMTUDISC_PROBE,
+            )?;
+            set_int_option(socket, libc::IPPROTO_IP, libc::IP_RECVERR, 1)
+        }
+        IpAddr::V6(_) => {
+            set_int_option(
+                socket,
+                libc::IPPROTO_IPV6,
+                libc::IPV6_MTU_DISCOVER,
+                libc::IPV6_PMTUDISC_PROBE,
+            )?;
+            set_int_option(socket, libc::IPPROTO_IPV6, libc::IPV6_RECVERR, 1)
+        }
+    }
+}
+
+#[cfg(any(target_os = "macos", target_os = "ios", target_os = "freebsd"))]
+fn set_dont_fragment(socket: &Socket, address: IpAddr) -> io::Result<()> {
+    match address {
+        IpAddr::V4(_) => set_int_option(socket, libc::IPPROTO_IP, libc::IP_DONTFRAG, 1),
+        IpAddr::V6(_) => set_int_option(socket, libc::IPPROTO_IPV6, libc::IPV6_DONTFRAG, 1),
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+)))]
+fn set_dont_fragment(_socket: &Socket, _address: IpAddr) -> io::Result<()> {
+    Err(io::Error::new(
+        io::ErrorKind::Unsupported,
+        "path MTU probing is not supported on this platform",
+    ))
+}
+
+#[cfg(any(
+    target_os = "linux",
+    target_os = "android",
+    target_os = "macos",
+    target_os = "ios",
+    target_os = "freebsd"
+))]
+fn set_int_option(
+    socket: &Socket,
+    level: libc::c_int,
+    name: libc::c_int,
+    value: libc::c_int,
+) -> io::Result<()> {
+    use std::os::fd::AsRawFd;
+
+    // SAFETY: the descriptor is owned by `socket` and the option value is a
+    // correctly sized c_int that outlives the call.
+    let result = unsafe {
+        libc::setsockopt(
+            socket.as_raw_fd(),
+            level,
+            name,
+            (&value as *const libc::c_int).cast(),
+            std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+        )
+    };
+    if result == 0 {
+        Ok(())
+    } else {
+        Err(io::Error::last_os_error())
+    }
+}
+
+#[cfg(unix)]
+fn is_message_too_big(error: &io::Error) -> bool {
+    error.raw_os_error() == Some(libc::EMSGSIZE)
+}
+
+#[cfg(not(unix))]
+fn is_message_too_big(_error: &io::Error) -> bool {
+    false
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TraceReply {
+    pub responder: IpAddr,
+    pub sequence: u16,
+    pub reached: bool,
+}
+
+pub struct TraceSocket {
+    // Raw sockets are message oriented, so std's UDP wrapper gives us recv_from
+    // with a parsed source address.
+    socket: UdpSocket,
+    address: IpAddr,
+    identifier: u16,
+}
+
+impl TraceSocket {
+    pub fn open(address: IpAddr) -> Result<Self> {
+        let (domain, protocol) = match address {
+            IpAddr::V4(_) => (Domain::IPV4, Protocol::ICMPV4),
+            IpAddr::V6(_) => (Domain::IPV6, Protocol::ICMPV6),
+        };
+
+        // Datagram ICMP sockets only see errors through the error queue, so hop
+        // replies need a raw socket.
+        let socket = Socket::new(domain, Type::RAW, Some(protocol)).context(
+            "failed to open raw ICMP socket for path probe (requires elevated privileges)",
+        )?;
+
+        Ok(Self {
+            socket: socket.into(),
+            address,
+            identifier: std::process::id() as u16,
+        })
+    }
+
+    pub fn send(&self, ttl: u8, sequence: u16, payload_size: usize) -> Result<()> {
+        let socket = SockRef::from(&self.socket);
+        match self.address {
+            IpAddr::V4(_) => socket.set_ttl_v4(u32::from(ttl)),
+            IpAddr::V6(_) => socket.set_unicast_hops_v6(u32::from(ttl)),
+        }
+        .with_context(|| format!("failed to set TTL {ttl} on path probe socket"))?;
+
+        let request = build_echo_request(self.address, self.identifier, sequence, payload_size);
+        self.socket
+            .send_to(&request, SocketAddr::new(self.address, 0))
+            .with_context(|| format!("failed to send path probe to {}", self.address))?;
+
+        Ok(())
+    }
+
+    pub fn receive(&self, deadline: Instant) -> Result<Option<TraceReply>> {
+        let mut buffer = [0_u8; RECEIVE_BUFFER_LEN];
+        loop {
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Ok(None);
+            }
+
+            self.socket
+                .set_read_timeout(Some(remaining))
+                .context("failed to set ICMP read timeout")?;
+
+            let (length, responder) = match self.socket.recv_from(&mut buffer) {
+                Ok(received) => received,
+                Err(error)
+                    if matches!(
+                        error.kind(),
+                        io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+                    ) =>
+                {
+                    return Ok(None)
+                }
+                Err(error) if error.kind() == io::ErrorKind::Interrupted => continue,
+                Err(error) => return Err(error).context("failed to receive path probe reply"),
+            };
+
+            let Some((kind, reply)) =
+                parse_trace_reply(&buffer[..length], self.address, self.address.is_ipv4())
+            else {
+                continue;
+            };
+            if reply.identifier != self.identifier {
+                continue;
+            }
+
+            let responder = responder.ip();
+            return Ok(Some(TraceReply {
+                responder,
+                sequence: reply.sequence,
+                reached: kind == TraceReplyKind::EchoReply || responder == self.address,
+            }));
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TraceReplyKind {
+    EchoReply,
+    TimeExceeded,
+    Unreachable,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct EchoReply {
+    identifier: u16,
+    sequence: u16,
+}
+
+fn build_echo_request(
+    address: IpAddr,
+    identifier: u16,
+    sequence: u16,
+    payload_size: usize,
+) -> Vec<u8> {
+    let message_type = match address {
+        IpAddr::V4(_) => ICMPV4_ECHO_REQUEST,
+        IpAddr::V6(_) => ICMPV6_ECHO_REQUEST,
+    };
+    let mut packet = Vec::with_capacity(ICMP_HEADER_LEN + payload_size);
+    packet.extend_from_slice(&[message_type, 0, 0, 0]);
+    packet.extend_from_slice(&identifier.to_be_bytes());
+    packet.extend_from_slice(&sequence.to_be_bytes());
+    packet.extend((0..payload_size).map(|index| (index % 256) as u8));
+
+    // The kernel fills in ICMPv6 checksums because they cover a pseudo-header.
+    if address.is_ipv4() {
+        let checksum = icmp_checksum(&packet);
+        packet[2..4].copy_from_slice(&checksum.to_be_bytes());
+    }
+
+    packet
+}
+
+// Returns the sequence number of an echo reply meant for this socket.
+fn parse_socket_reply(
+    packet: &[u8],
+    address: IpAddr,
+    kind: EchoSocketKind,
+    identifier: u16,
+    kernel_owns_datagram_echo: bool,
+) -> Option<u16> {
+    let kernel_owned = kind == EchoSocketKind::Datagram && kernel_owns_datagram_echo;
+    // An IPv4 header starts with version nibble 4, which no echo reply type does.
+    let has_ip_header = address.is_ipv4()
+        && match kind {
+            EchoSocketKind::Raw => true,
+            EchoSocketKind::Datagram => !kernel_owned && packet.first()? >> 4 == 4,
+        };
+    let reply = parse_echo_reply(packet, address, has_ip_header)?;
+
+    (kernel_owned || reply.identifier == identifier).then_some(reply.sequence)
+}
+
+fn parse_echo_reply(packet: &[u8], address: IpAddr, has_ip_header: bool) -> Option<EchoReply> {
+    let message = if has_ip_header {
+        strip_ipv4_header(packet)?
+    } else {
+        packet
+    };
+
+    if message.len() < ICMP_HEADER_LEN {
+        return None;
+    }
+
+    let expected_type = match address {
+        IpAddr::V4(_) => ICMPV4_ECHO_REPLY,
+        IpAddr::V6(_) => ICMPV6_ECHO_REPLY,
+    };
+    if message[0] != expected_type || message[1] != 0 {
+        return None;
+    }
+
+    Some(EchoReply {
+        identifier: u16::from_be_bytes([message[4], message[5]]),
+        sequence: u16::from_be_bytes([message[6], message[7]]),
+    })
+}
+
+fn parse_trace_reply(
+    packet: &[u8],
+    address: IpAddr,
+    has_ip_header: bool,
+) -> Option<(TraceReplyKind, EchoReply)> {
+    let message = if has_ip_header {
+        strip_ipv4_header(packet)?
+    } else {
+        packet
+    };
+    if message.len() < ICMP_HEADER_LEN {
+        return None;
+    }
+
+    let (kind, echo_request_type) = match (address, message[0]) {
+        (IpAddr::V4(_), ICMPV4_ECHO_REPLY) | (IpAddr::V6(_), ICMPV6_ECHO_REPLY) => {
+            return parse_echo_reply(packet, address, has_ip_header)
+                .map(|reply| (TraceReplyKind::EchoReply, reply));
+        }
+        (IpAddr::V4(_), ICMPV4_TIME_EXCEEDED) => {
+            (TraceReplyKind::TimeExceeded, ICMPV4_ECHO_REQUEST)
+        }
+        (IpAddr::V4(_), ICMPV4_DESTINATION_UNREACHABLE) => {
+            (TraceReplyKind::Unreachable, ICMPV4_ECHO_REQUEST)
+        }
+        (IpAddr::V6(_), ICMPV6_TIME_EXCEEDED) => {
+            (TraceReplyKind::TimeExceeded, ICMPV6_ECHO_REQUEST)
+        }
+        (IpAddr::V6(_), ICMPV6_DESTINATION_UNREACHABLE) => {
+            (TraceReplyKind::Unreachable, ICMPV6_ECHO_REQUEST)
+        }
+        _ => return None,
+    };
+
+    // Error messages quote the original IP header and the first eight bytes of our echo.
+    let quoted = &message[ICMP_HEADER_LEN..];
+    let quoted_echo = match address {
+        IpAddr::V4(_) => strip_ipv4_header(quoted)?,
+        IpAddr::V6(_) => quoted.get(IPV6_HEADER_LEN..)?,
+    };
+    if quoted_echo.len() < ICMP_HEADER_LEN || quoted_echo[0] != echo_request_type {
+        return None;
+    }
+
+    Some((
+        kind,
+        EchoReply {
+            identifier: u16::from_be_bytes([quoted_echo[4], quoted_echo[5]]),
+            sequence: u16::from_be_bytes([quoted_echo[6], quoted_echo[7]]),
+        },
+    ))
+}
+
+fn strip_ipv4_header(packet: &[u8]) -> Option<&[u8]> {
+    let header_len = usize::from(*packet.first()? & 0x0f) * 4;
+    packet.get(header_len..)
+}
+
+fn icmp_checksum(bytes: &[u8]) -> u16 {
+    let mut sum = bytes
+        .chunks(2)
+        .map(|pair| match pair {
+            [high, low] => u32::from(u16::from_be_bytes([*high, *low])),
+            [high] => u32::from(*high) << 8,
+            _ => 0,
+        })
+        .sum::<u32>();
+
+    while sum > 0xffff {
+        sum = (sum & 0xffff) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        build_echo_request, icmp_checksum, parse_echo_reply, parse_socket_reply, parse_trace_reply,
+        EchoReply, EchoSocketKind, TraceReplyKind,
+    };
+    use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+    #[test]
+    fn builds_ipv4_echo_request_with_valid_checksum() {
+        let address = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let packet = build_echo_request(address, 0x1234, 7, 56);
+
+        assert_eq!(packet.len(), 64);
+        assert_eq!(packet[0], 8);
+        assert_eq!(&packet[4..8], &[0x12, 0x34, 0x00, 0x07]);
+        assert_eq!(icmp_checksum(&packet), 0);
+    }
+
+    #[test]
+    fn leaves_ipv6_checksum_to_the_kernel() {
+        let address = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        let packet = build_echo_request(address, 1, 2, 8);
+
+        assert_eq!(packet[0], 128);
+        assert_eq!(&packet[2..4], &[0, 0]);
+    }
+
+    #[test]
+    fn parses_echo_replies_with_and_without_ip_header() {
+        let address = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let reply = [0_u8, 0, 0, 0, 0x12, 0x34, 0x00, 0x05];
+        let mut raw = vec![0x45_u8];
+        raw.extend_from_slice(&[0; 19]);
+        raw.extend_from_slice(&reply);
+
+        let expected = EchoReply {
+            identifier: 0x1234,
+            sequence: 5,
+        };
+        assert_eq!(parse_echo_reply(&reply, address, false), Some(expected));
+        assert_eq!(parse_echo_reply(&raw, address, true), Some(expected));
+    }
+
+    #[test]
+    fn filters_datagram_replies_where_the_kernel_does_not() {
+        let address = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let ours = [0_u8, 0, 0, 0, 0x12, 0x34, 0x00, 0x05];
+        let theirs = [0_u8, 0, 0, 0, 0x56, 0x78, 0x00, 0x06];
+        let with_header = |reply: &[u8]| {
+            let mut packet = vec![0x45_u8];
+            packet.extend_from_slice(&[0; 19]);
+            packet.extend_from_slice(reply);
+            packet
+        };
+        let datagram = EchoSocketKind::Datagram;
+
+        // Linux strips the header and rewrites the identifier for us.
+        assert_eq!(
+            parse_socket_reply(&theirs, address, datagram, 0x1234, true),
+            Some(6)
+        );
+        // macOS and the BSDs hand over the header and everyone's replies.
+        assert_eq!(
+            parse_socket_reply(&with_header(&ours), address, datagram, 0x1234, false),
+            Some(5)
+        );
+        assert_eq!(
+            parse_socket_reply(&with_header(&theirs), address, datagram, 0x1234, false),
+            None
+        );
+        assert_eq!(
+            parse_socket_reply(&ours, address, datagram, 0x1234, false),
+            Some(5)
+        );
+        assert_eq!(
+            parse_socket_reply(
+                &with_header(&theirs),
+                address,
+                EchoSocketKind::Raw,
+                0x5678,
+                true
+            ),
+            Some(6)
+        );
+    }
+
+    #[test]
+    fn ignores_non_reply_messages() {
+        let address = IpAddr::V4(Ipv4Addr::LOCALHOST);
+        let request = build_echo_request(address, 1, 1, 0);
+
+        assert_eq!(parse_echo_reply(&request, address, false), None);
+        assert_eq!(parse_echo_reply(&[0, 0, 0], address, false), None);
+    }
+
+    #[test]
+    fn matches_hop_errors_to_the_quoted_echo_request() {
+        let target = IpAddr::V4(Ipv4Addr::new(192, 0, 2, 1));
+        let request = build_echo_request(target, 0x1234, 9, 16);
+        let mut quoted_ip = vec![0x45_u8];
+        quoted_ip.extend_from_slice(&[0; 19]);
+        let mut time_exceeded = vec![0x45_u8];
+        time_exceeded.extend_from_slice(&[0; 19]);
+        time_exceeded.extend_from_slice(&[11, 0, 0, 0, 0, 0, 0, 0]);
+        time_exceeded.extend_from_slice(&quoted_ip);
+        time_exceeded.extend_from_slice(&request[..8]);
+
+        let expected = EchoReply {
+            identifier: 0x1234,
+            sequence: 9,
+        };
+        assert_eq!(
+            parse_trace_reply(&time_exceeded, target, true),
+            Some((TraceReplyKind::TimeExceeded, expected))
+        );
+
+        let mut echo_reply = vec![0x45_u8];
+        echo_reply.extend_from_slice(&[0; 19]);
+        echo_reply.extend_from_slice(&[0, 0, 0, 0, 0x12, 0x34, 0x00, 0x09]);
+        assert_eq!(
+            parse_trace_reply(&echo_reply, target, true),
+            Some((TraceReplyKind::EchoReply, expected))
+        );
+
+        let v6_target = IpAddr::V6(Ipv6Addr::LOCALHOST);
+        let mut unreachable = vec![1_u8, 4, 0, 0, 0, 0, 0, 0];
+        unreachable.extend_from_slice(&[0; 40]);
+        unreachable.extend_from_slice(&[128, 0, 0, 0, 0x12, 0x34, 0x00, 0x09]);
+        assert_eq!(
+            parse_trace_reply(&unreachable, v6_target, false),
+            Some((TraceReplyKind::Unreachable, expected))
+        );
+    }
+}
@@ -0,0 +1,142 @@
+use std::future::Future;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+
+use crate::icmp;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+const ICMP_TIMEOUT: Duration = Duration::from_secs(1);
+const TCP_TIMEOUT: Duration = Duration::from_secs(2);
+const ICMP_PAYLOAD_BYTES: usize = 56;
+
+#[derive(Debug, Clone, Copy)]
+pub struct LatencySampler {
+    pub target: Option<IpAddr>,
+    pub endpoint: Option<SocketAddr>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PhaseSamples {
+    pub target: Option<Vec<Option<Duration>>>,
+    pub endpoint: Option<Vec<Option<Duration>>>,
+}
+
+impl PhaseSamples {
+    pub fn extend(&mut self, other: PhaseSamples) {
+        extend_series(&mut self.target, other.target);
+        extend_series(&mut self.endpoint, other.endpoint);
+    }
+}
+
+impl LatencySampler {
+    pub async fn sample_while<F: Future>(&self, future: F) -> (F::Output, PhaseSamples) {
+        let stop = Arc::new(AtomicBool::new(false));
+        let target_task = self.target.map(|address| {
+            let stop = stop.clone();
+            tokio::task::spawn_blocking(move || sample_icmp(address, &stop))
+        });
+        let endpoint_task = self.endpoint.map(|address| {
+            let stop = stop.clone();
+            tokio::spawn(async move { sample_tcp(address, &stop).await })
+        });
+
+        let output = future.await;
+        stop.store(true, Ordering::Relaxed);
+
+        let mut samples = PhaseSamples::default();
+        if let Some(task) = target_task {
+            samples.target = task.await.ok().flatten();
+        }
+        if let Some(task) = endpoint_task {
+            samples.endpoint = task.await.ok();
+        }
+
+        (output, samples)
+    }
+}
+
+fn sample_icmp(address: IpAddr, stop: &AtomicBool) -> Option<Vec<Option<Duration>>> {
+    let socket = icmp::EchoSocket::open(address).ok()?;
+    let mut samples = Vec::new();
+    let mut sequence = 0_u16;
+
+    // Always take at least one sample so short phases are still represented.
+    loop {
+        let sent_at = Instant::now();
+        samples.push(
+            socket
+                .echo(sequence, ICMP_PAYLOAD_BYTES, ICMP_TIMEOUT)
+                .ok()
+                .flatten(),
+        );
+        sequence = sequence.wrapping_add(1);
+        std::thread::sleep(SAMPLE_INTERVAL.saturating_sub(sent_at.elapsed()));
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+
+    Some(samples)
+}
+
+async fn sample_tcp(address: SocketAddr, stop: &AtomicBool) -> Vec<Option<Duration>> {
+    let mut samples = Vec::new();
+
+    loop {
+        let started = Instant::now();
+        let connected = tokio::time::timeout(TCP_TIMEOUT, TcpStream::connect(address)).await;
+        samples.push(matches!(connected, Ok(Ok(_))).then(|| started.elapsed()));
+        tokio::time::sleep(SAMPLE_INTERVAL.saturating_sub(started.elapsed())).await;
+        if stop.load(Ordering::Relaxed) {
+            break;
+        }
+    }
+
+    samples
+}
+
+fn extend_series(series: &mut Option<Vec<Option<Duration>>>, other: Option<Vec<Option<Duration>>>) {
+    match (series.as_mut(), other) {
+        (Some(series), Some(other)) => series.extend(other),
+        (None, Some(other)) => *series = Some(other),
+        (_, None) => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::LatencySampler;
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    #[tokio::test]
+    async fn samples_endpoint_latency_while_work_is_in_flight() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("listener should bind");
+        let address = listener
+            .local_addr()
+            .expect("listener should have an address");
+        tokio::spawn(async move { while listener.accept().await.is_ok() {} });
+
+        let sampler = LatencySampler {
+            target: None,
+            endpoint: Some(address),
+        };
+        let (output, samples) = sampler
+            .sample_while(async {
+                tokio::time::sleep(Duration::from_millis(350)).await;
+                42
+            })
+            .await;
+
+        assert_eq!(output, 42);
+        assert!(samples.target.is_none());
+        let endpoint = samples.endpoint.expect("endpoint samples should exist");
+        assert!(endpoint.len() >= 3);
+        assert!(endpoint.iter().all(Option::is_some));
+    }
+}
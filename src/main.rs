@@ -1,12 +1,23 @@
 mod cli;
+mod dns;
+mod dns_bench;
+mod http_timing;
+mod icmp;
+mod latency;
 mod probe;
+mod quality;
+mod server;
 mod storage;
+mod tls;
 mod tui;
+mod udp;
 mod version;
+mod x509;
 
 use anyhow::Result;
 use clap::Parser;
 use cli::{Cli, Commands, CompareArgs, ExportFormat};
+use dns_bench::{format_dns_bench, run_dns_bench};
 use probe::{format_provider_catalog, format_report, provider_catalog, run_probe_suite};
 use serde::Serialize;
 use storage::{
@@ -50,6 +61,14 @@ async fn main() -> Result<()> {
                 println!("{}", format_provider_catalog(&providers));
             }
         }
+        Commands::DnsBench(args) => {
+            let report = run_dns_bench(&args.to_bench_options()?).await?;
+            if args.json {
+                println!("{}", serde_json::to_string_pretty(&report)?);
+            } else {
+                println!("{}", format_dns_bench(&report));
+            }
+        }
         Commands::History(args) => {
             let runs = list_runs(args.target.as_deref(), args.limit)?;
             if args.json {
@@ -74,6 +93,30 @@ async fn main() -> Result<()> {
                 println!("{}", format_compared_runs(&output.compared_runs));
             }
         }
+        Commands::Serve(args) => {
+            let listener = server::bind_server(&args.bind).await?;
+            let address = listener.local_addr()?;
+            println!(
+                "PantheonProbe v{} | serving throughput endpoints on {address}",
+                version::VERSION
+            );
+            println!(
+                "  download: http://{address}{}?bytes=N",
+                server::DOWNLOAD_PATH
+            );
+            println!("  upload: http://{address}{}", server::UPLOAD_PATH);
+            println!("  health: http://{address}{}", server::HEALTH_PATH);
+            server::serve(listener).await?;
+        }
+        Commands::Reflect(args) => {
+            let socket = udp::bind_reflector(&args.bind).await?;
+            println!(
+                "PantheonProbe v{} | reflecting UDP echo on {}",
+                version::VERSION,
+                socket.local_addr()?
+            );
+            udp::reflect(socket).await?;
+        }
     }
 
     Ok(())
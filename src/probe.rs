@@ -1,24 +1,155 @@
 use anyhow::{anyhow, Context, Result};
+use bytes::Bytes;
 use clap::ValueEnum;
-use reqwest::{header::RANGE, Client};
+use futures_util::{future, stream, StreamExt};
+use http_body_util::StreamBody;
+use hyper::body::Frame;
+use reqwest::{Body, Client};
 use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::Infallible;
 use std::fmt;
-use std::net::{IpAddr, ToSocketAddrs};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, ToSocketAddrs};
 use std::process::Command;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, OnceLock};
 use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::net::TcpStream;
 use tokio::task::JoinSet;
 
+use crate::dns::{self, DnsRecordType, DnsServer, DnsSession, DnsTransport};
+use crate::latency::{LatencySampler, PhaseSamples};
+use crate::quality::{self, QualityEstimate};
+use crate::x509::Certificate;
+use crate::{http_timing, icmp, tls, udp};
+
 const CLOUDFLARE_UPLOAD_URL: &str = "https://speed.cloudflare.com/__up";
 const DEFAULT_ENDPOINT_NAME: &str = "global";
+const DEFAULT_PING_PAYLOAD_BYTES: usize = 56;
+const DEFAULT_PING_INTERVAL_MS: u64 = 1_000;
+const DEFAULT_PING_TIMEOUT_MS: u64 = 1_000;
+const TCP_CONNECT_INTERVAL: Duration = Duration::from_millis(200);
+const TCP_CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+const DNS_QUERY_INTERVAL: Duration = Duration::from_millis(200);
+const DNS_QUERY_TIMEOUT: Duration = Duration::from_secs(2);
+const TLS_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+const UPLOAD_CHUNK_BYTES: usize = 64 * 1024;
+// Larger than the history window of the compressors middleboxes run inline, so
+// cycling through it never hands them a repeat they can exploit.
+const UPLOAD_BLOCK_BYTES: usize = 4 * 1024 * 1024;
+const THROUGHPUT_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+const DEFAULT_RAMP_UP_EXCLUSION_MS: u64 = 300;
+// Below this much of the transfer, or this many samples, after the ramp-up
+// cutoff, a steady-state rate is mostly noise.
+const STEADY_STATE_MIN_SHARE: f64 = 0.5;
+const STEADY_STATE_MIN_POINTS: usize = 3;
+const DEFAULT_MIN_SUCCESSFUL_RUNS: u32 = 1;
+const DEFAULT_UDP_PACKETS: u32 = 50;
+const DEFAULT_UDP_INTERVAL_MS: u64 = 20;
+const DEFAULT_UDP_PAYLOAD_BYTES: usize = 160;
+const UDP_ECHO_TIMEOUT: Duration = Duration::from_secs(1);
+const RFC3550_JITTER_GAIN: f64 = 16.0;
+const DEFAULT_TRACE_MAX_HOPS: u8 = 30;
+const DEFAULT_TRACE_CYCLES: u32 = 5;
+const TRACE_TIMEOUT: Duration = Duration::from_secs(1);
+const DEFAULT_PMTU_MAX_BYTES: u32 = 1_500;
+const PMTU_MIN_BYTES_V4: u32 = 576;
+const PMTU_MIN_BYTES_V6: u32 = 1_280;
+const PMTU_ATTEMPTS: u32 = 3;
+const PMTU_TIMEOUT: Duration = Duration::from_secs(1);
+const IDLE_LATENCY_WINDOW: Duration = Duration::from_secs(2);
+const RESPONSIVENESS_RAMP_UP: Duration = Duration::from_secs(2);
+const RESPONSIVENESS_DURATION: Duration = Duration::from_secs(8);
+const RESPONSIVENESS_PROBE_INTERVAL: Duration = Duration::from_millis(100);
+const RESPONSIVENESS_TRIM_FRACTION: f64 = 0.95;
 
 #[derive(Debug, Clone)]
 pub struct ProbeOptions {
     pub target: String,
     pub profile: MeasurementProfile,
     pub samples: u32,
+    pub family: Option<FamilyMode>,
+    pub ping: PingConfig,
+    pub ping_all_addresses: bool,
+    pub tcp_port: Option<u16>,
+    pub tls_port: Option<u16>,
+    pub dns_query: Option<DnsQueryConfig>,
+    pub udp_echo: Option<UdpEchoConfig>,
+    pub trace: Option<TraceConfig>,
+    pub pmtu: Option<PmtuConfig>,
+    pub http_urls: Vec<String>,
+    pub responsiveness: bool,
     pub bandwidth: BandwidthConfig,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PingConfig {
+    pub payload_bytes: usize,
+    pub interval_ms: u64,
+    pub ttl: Option<u8>,
+    pub timeout_ms: u64,
+}
+
+impl PingConfig {
+    fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms)
+    }
+
+    fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+impl Default for PingConfig {
+    fn default() -> Self {
+        Self {
+            payload_bytes: DEFAULT_PING_PAYLOAD_BYTES,
+            interval_ms: DEFAULT_PING_INTERVAL_MS,
+            ttl: None,
+            timeout_ms: DEFAULT_PING_TIMEOUT_MS,
+        }
+    }
+}
+
+impl fmt::Display for PingConfig {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{}-byte payload, {} ms interval, {} ms timeout, ",
+            self.payload_bytes, self.interval_ms, self.timeout_ms
+        )?;
+        match self.ttl {
+            Some(ttl) => write!(formatter, "TTL {ttl}"),
+            None => formatter.write_str("default TTL"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DnsQueryConfig {
+    pub servers: Vec<DnsServer>,
+    pub record_type: DnsRecordType,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct UdpEchoConfig {
+    pub port: u16,
+    pub packets: u32,
+    pub interval_ms: u64,
+    pub payload_bytes: usize,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TraceConfig {
+    pub max_hops: u8,
+    pub cycles: u32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PmtuConfig {
+    pub max_bytes: u32,
+}
+
 #[derive(Debug, Clone)]
 pub struct BandwidthConfig {
     pub provider: BandwidthProviderPreset,
@@ -33,8 +164,13 @@ pub struct BandwidthConfig {
     pub download_streams: u32,
     pub upload_streams: u32,
     pub target_transfer_duration_ms: u64,
+    pub transfer_duration_ms: Option<u64>,
+    pub ramp_up_exclusion_ms: u64,
+    pub min_successful_runs: u32,
     pub max_download_size_bytes: usize,
     pub max_upload_size_bytes: usize,
+    pub loaded_latency: bool,
+    pub family: Option<AddressFamily>,
 }
 
 #[derive(Debug, Clone)]
@@ -44,6 +180,27 @@ pub struct ProbeOverrides {
     pub provider: BandwidthProviderPreset,
     pub endpoint: Option<String>,
     pub samples: Option<u32>,
+    pub family: Option<FamilyMode>,
+    pub ping_payload_bytes: Option<usize>,
+    pub ping_interval_ms: Option<u64>,
+    pub ping_ttl: Option<u8>,
+    pub ping_timeout_ms: Option<u64>,
+    pub ping_all_addresses: bool,
+    pub tcp_port: Option<u16>,
+    pub tls_port: Option<u16>,
+    pub dns_servers: Vec<String>,
+    pub dns_record: Option<DnsRecordType>,
+    pub dns_transport: Option<DnsTransport>,
+    pub udp_port: Option<u16>,
+    pub udp_packets: Option<u32>,
+    pub udp_interval_ms: Option<u64>,
+    pub udp_payload_bytes: Option<usize>,
+    pub trace: bool,
+    pub trace_max_hops: Option<u8>,
+    pub trace_cycles: Option<u32>,
+    pub pmtu: bool,
+    pub pmtu_max_bytes: Option<u32>,
+    pub http_urls: Vec<String>,
     pub download_urls: Vec<String>,
     pub upload_urls: Vec<String>,
     pub download_size_bytes: Option<usize>,
@@ -55,8 +212,13 @@ pub struct ProbeOverrides {
     pub download_streams: Option<u32>,
     pub upload_streams: Option<u32>,
     pub target_transfer_duration_ms: Option<u64>,
+    pub transfer_duration_ms: Option<u64>,
+    pub ramp_up_exclusion_ms: Option<u64>,
+    pub min_successful_runs: Option<u32>,
     pub max_download_size_bytes: Option<usize>,
     pub max_upload_size_bytes: Option<usize>,
+    pub loaded_latency: bool,
+    pub responsiveness: bool,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -88,6 +250,58 @@ pub enum BandwidthProviderPreset {
     Custom,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "kebab-case")]
+pub enum FamilyMode {
+    V4,
+    V6,
+    Both,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum AddressFamily {
+    V4,
+    V6,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum PingEngine {
+    IcmpDatagram,
+    IcmpRaw,
+    Subprocess,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum DownloadSizing {
+    QueryParameter,
+    RangeRequest,
+    // The server ignored `Range`, so each stream stops reading at the target.
+    StreamLimit,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum UploadPayload {
+    // Runs stored before random payloads uploaded a single repeated byte.
+    #[default]
+    RepeatedByte,
+    Random,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum BufferbloatGrade {
+    #[serde(rename = "A+")]
+    APlus,
+    A,
+    B,
+    C,
+    D,
+    F,
+}
+
 #[derive(Debug, Clone, Copy)]
 struct ProfileDefaults {
     samples: u32,
@@ -172,6 +386,119 @@ impl fmt::Display for BandwidthProviderPreset {
     }
 }
 
+impl FamilyMode {
+    fn pinned(self) -> Option<AddressFamily> {
+        match self {
+            Self::V4 => Some(AddressFamily::V4),
+            Self::V6 => Some(AddressFamily::V6),
+            Self::Both => None,
+        }
+    }
+}
+
+impl AddressFamily {
+    fn of(address: &IpAddr) -> Self {
+        if address.is_ipv4() {
+            Self::V4
+        } else {
+            Self::V6
+        }
+    }
+
+    pub fn matches(self, address: &IpAddr) -> bool {
+        match self {
+            Self::V4 => address.is_ipv4(),
+            Self::V6 => address.is_ipv6(),
+        }
+    }
+
+    fn unspecified(self) -> IpAddr {
+        match self {
+            Self::V4 => IpAddr::V4(Ipv4Addr::UNSPECIFIED),
+            Self::V6 => IpAddr::V6(Ipv6Addr::UNSPECIFIED),
+        }
+    }
+}
+
+impl fmt::Display for FamilyMode {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::V4 => "v4",
+            Self::V6 => "v6",
+            Self::Both => "both",
+        })
+    }
+}
+
+impl fmt::Display for AddressFamily {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::V4 => "IPv4",
+            Self::V6 => "IPv6",
+        })
+    }
+}
+
+impl fmt::Display for PingEngine {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::IcmpDatagram => "icmp-datagram",
+            Self::IcmpRaw => "icmp-raw",
+            Self::Subprocess => "subprocess",
+        })
+    }
+}
+
+impl fmt::Display for DownloadSizing {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::QueryParameter => "query-parameter",
+            Self::RangeRequest => "range-request",
+            Self::StreamLimit => "stream-limit",
+        })
+    }
+}
+
+impl fmt::Display for UploadPayload {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::RepeatedByte => "repeated-byte",
+            Self::Random => "random",
+        })
+    }
+}
+
+impl fmt::Display for TransferPhase {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::Warmup => "warmup",
+            Self::Measured => "measured",
+        })
+    }
+}
+
+impl fmt::Display for TransferDirection {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::Download => "download",
+            Self::Upload => "upload",
+        })
+    }
+}
+
+impl fmt::Display for BufferbloatGrade {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::APlus => "A+",
+            Self::A => "A",
+            Self::B => "B",
+            Self::C => "C",
+            Self::D => "D",
+            Self::F => "F",
+        })
+    }
+}
+
 pub fn provider_catalog() -> Vec<ProviderCatalogEntry> {
     vec![
         ProviderCatalogEntry {
@@ -280,6 +607,34 @@ pub fn resolve_probe_options(overrides: ProbeOverrides) -> Result<ProbeOptions>
         anyhow::bail!("provide the same number of --download-url and --upload-url values");
     }
 
+    let trace = overrides.trace.then(|| TraceConfig {
+        max_hops: overrides
+            .trace_max_hops
+            .unwrap_or(DEFAULT_TRACE_MAX_HOPS)
+            .max(1),
+        cycles: overrides
+            .trace_cycles
+            .unwrap_or(DEFAULT_TRACE_CYCLES)
+            .max(1),
+    });
+    // Every trace probe gets its own 16-bit ICMP sequence number.
+    if let Some(trace) = trace {
+        if u64::from(trace.cycles) * u64::from(trace.max_hops) > u64::from(u16::MAX) {
+            anyhow::bail!(
+                "--trace-cycles times --trace-max-hops must not exceed {} probes",
+                u16::MAX
+            );
+        }
+    }
+
+    let dns_servers = overrides
+        .dns_servers
+        .iter()
+        .map(|server| {
+            dns::parse_server(server, overrides.dns_transport.unwrap_or(DnsTransport::Udp))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
     let provider = if has_download_overrides {
         BandwidthProviderPreset::Custom
     } else {
@@ -297,6 +652,45 @@ pub fn resolve_probe_options(overrides: ProbeOverrides) -> Result<ProbeOptions>
         target: overrides.target,
         profile: overrides.profile,
         samples,
+        family: overrides.family,
+        ping: PingConfig {
+            payload_bytes: overrides
+                .ping_payload_bytes
+                .unwrap_or(DEFAULT_PING_PAYLOAD_BYTES),
+            interval_ms: overrides
+                .ping_interval_ms
+                .unwrap_or(DEFAULT_PING_INTERVAL_MS)
+                .max(1),
+            ttl: overrides.ping_ttl.map(|ttl| ttl.max(1)),
+            timeout_ms: overrides
+                .ping_timeout_ms
+                .unwrap_or(DEFAULT_PING_TIMEOUT_MS)
+                .max(1),
+        },
+        ping_all_addresses: overrides.ping_all_addresses,
+        tcp_port: overrides.tcp_port,
+        tls_port: overrides.tls_port,
+        dns_query: (!dns_servers.is_empty()).then(|| DnsQueryConfig {
+            servers: dns_servers,
+            record_type: overrides.dns_record.unwrap_or(DnsRecordType::A),
+        }),
+        udp_echo: overrides.udp_port.map(|port| UdpEchoConfig {
+            port,
+            packets: overrides.udp_packets.unwrap_or(DEFAULT_UDP_PACKETS).max(1),
+            interval_ms: overrides
+                .udp_interval_ms
+                .unwrap_or(DEFAULT_UDP_INTERVAL_MS)
+                .max(1),
+            payload_bytes: overrides
+                .udp_payload_bytes
+                .unwrap_or(DEFAULT_UDP_PAYLOAD_BYTES),
+        }),
+        trace,
+        pmtu: overrides.pmtu.then(|| PmtuConfig {
+            max_bytes: overrides.pmtu_max_bytes.unwrap_or(DEFAULT_PMTU_MAX_BYTES),
+        }),
+        http_urls: overrides.http_urls,
+        responsiveness: overrides.responsiveness,
         bandwidth: BandwidthConfig {
             provider,
             endpoint: overrides.endpoint,
@@ -310,8 +704,18 @@ pub fn resolve_probe_options(overrides: ProbeOverrides) -> Result<ProbeOptions>
             download_streams,
             upload_streams,
             target_transfer_duration_ms,
+            transfer_duration_ms: overrides.transfer_duration_ms.map(|ms| ms.max(1)),
+            ramp_up_exclusion_ms: overrides
+                .ramp_up_exclusion_ms
+                .unwrap_or(DEFAULT_RAMP_UP_EXCLUSION_MS),
+            min_successful_runs: overrides
+                .min_successful_runs
+                .unwrap_or(DEFAULT_MIN_SUCCESSFUL_RUNS)
+                .clamp(1, runs),
             max_download_size_bytes,
             max_upload_size_bytes,
+            loaded_latency: overrides.loaded_latency,
+            family: overrides.family.and_then(FamilyMode::pinned),
         },
     })
 }
@@ -360,6 +764,10 @@ fn default_endpoint_name() -> String {
     DEFAULT_ENDPOINT_NAME.to_string()
 }
 
+fn default_ping_engine() -> PingEngine {
+    PingEngine::Subprocess
+}
+
 fn default_download_size_bytes() -> usize {
     MeasurementProfile::Standard.defaults().download_size_bytes
 }
@@ -378,6 +786,10 @@ fn default_transfer_attempts() -> u32 {
     1
 }
 
+fn default_min_successful_runs() -> u32 {
+    DEFAULT_MIN_SUCCESSFUL_RUNS
+}
+
 fn default_transfer_timeout_seconds() -> u64 {
     30
 }
@@ -391,8 +803,47 @@ pub struct ProbeReport {
     pub bandwidth_provider: String,
     pub samples: u32,
     pub created_at_unix_ms: u128,
+    #[serde(default)]
+    pub family: Option<FamilyMode>,
+    #[serde(default)]
+    pub ping_config: PingConfig,
     pub ping: ProbeOutcome<PingSummary>,
+    #[serde(default)]
+    pub quality: Option<QualityEstimate>,
     pub dns: ProbeOutcome<DnsSummary>,
+    #[serde(default)]
+    pub address_pings: Vec<AddressPing>,
+    #[serde(default)]
+    pub tcp: Option<ProbeOutcome<TcpSummary>>,
+    #[serde(default)]
+    pub tls: Option<ProbeOutcome<TlsSummary>>,
+    #[serde(default)]
+    pub udp: Option<ProbeOutcome<UdpSummary>>,
+    #[serde(default)]
+    pub trace: Option<ProbeOutcome<TraceSummary>>,
+    #[serde(default)]
+    pub pmtu: Option<ProbeOutcome<PmtuSummary>>,
+    #[serde(default)]
+    pub http: Vec<ProbeOutcome<HttpTiming>>,
+    pub bandwidth: ProbeOutcome<BandwidthSummary>,
+    #[serde(default)]
+    pub responsiveness: Option<ProbeOutcome<ResponsivenessSummary>>,
+    #[serde(default)]
+    pub families: Vec<FamilyReport>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AddressPing {
+    pub address: String,
+    pub ping: ProbeOutcome<PingSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FamilyReport {
+    pub family: AddressFamily,
+    pub address: Option<String>,
+    pub ping: ProbeOutcome<PingSummary>,
+    pub tcp: Option<ProbeOutcome<TcpSummary>>,
     pub bandwidth: ProbeOutcome<BandwidthSummary>,
 }
 
@@ -420,6 +871,8 @@ impl<T> ProbeOutcome<T> {
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PingSummary {
+    #[serde(default = "default_ping_engine")]
+    pub engine: PingEngine,
     pub sent: u32,
     pub received: u32,
     pub packet_loss_pct: f64,
@@ -431,12 +884,169 @@ pub struct PingSummary {
     pub stddev_ms: Option<f64>,
     pub jitter_ms: Option<f64>,
     pub samples_ms: Vec<f64>,
+    #[serde(default)]
+    pub sequence: Option<PingSequenceSummary>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PingSequenceSummary {
+    pub duplicates: u32,
+    pub out_of_order: u32,
+    pub loss_bursts: Vec<u32>,
+    pub max_loss_burst: u32,
+    pub longest_gap_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PingReply {
+    sequence: u32,
+    rtt_ms: f64,
+    arrival_ms: f64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DnsSummary {
     pub resolution_time_ms: f64,
     pub addresses: Vec<String>,
+    #[serde(default)]
+    pub resolvers: Vec<DnsResolverSummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsResolverSummary {
+    pub server: String,
+    pub record_type: DnsRecordType,
+    pub transport: DnsTransport,
+    pub queries: u32,
+    pub responses: u32,
+    pub loss_pct: f64,
+    pub rcodes: BTreeMap<String, u32>,
+    pub min_ttl_s: Option<u32>,
+    pub answers: Vec<String>,
+    pub tcp_fallbacks: u32,
+    #[serde(default)]
+    pub handshake: Option<DnsHandshakeSummary>,
+    pub rtt: Option<MetricStats>,
+    pub samples_ms: Vec<f64>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DnsHandshakeSummary {
+    pub remote_address: String,
+    pub connect_ms: f64,
+    pub tls_ms: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TcpSummary {
+    pub address: String,
+    pub port: u16,
+    pub sent: u32,
+    pub connected: u32,
+    pub loss_pct: f64,
+    pub connect: Option<MetricStats>,
+    pub jitter_ms: Option<f64>,
+    pub samples_ms: Vec<f64>,
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TlsSummary {
+    pub address: String,
+    pub port: u16,
+    pub server_name: String,
+    pub connect_ms: f64,
+    pub handshake_ms: f64,
+    pub protocol: String,
+    pub cipher: String,
+    pub alpn: Option<String>,
+    pub days_until_expiry: i64,
+    pub validation_error: Option<String>,
+    pub chain: Vec<Certificate>,
+    #[serde(default)]
+    pub chain_errors: Vec<String>,
+}
+
+impl BandwidthSummary {
+    pub fn download_success_ratio(&self) -> f64 {
+        self.download_runs.len() as f64 / f64::from(self.runs.max(1))
+    }
+
+    pub fn upload_success_ratio(&self) -> f64 {
+        self.upload_runs.len() as f64 / f64::from(self.runs.max(1))
+    }
+}
+
+impl TlsSummary {
+    pub fn leaf(&self) -> Option<&Certificate> {
+        self.chain.first()
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UdpSummary {
+    pub address: String,
+    pub port: u16,
+    pub interval_ms: u64,
+    pub payload_bytes: usize,
+    pub sent: u32,
+    pub received: u32,
+    pub loss_pct: f64,
+    pub duplicates: u32,
+    pub out_of_order: u32,
+    pub rtt: Option<MetricStats>,
+    pub jitter_ms: Option<f64>,
+    pub samples_ms: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceSummary {
+    pub address: String,
+    pub max_hops: u8,
+    pub cycles: u32,
+    pub reached: bool,
+    pub hops: Vec<TraceHop>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraceHop {
+    pub ttl: u8,
+    pub addresses: Vec<String>,
+    pub sent: u32,
+    pub received: u32,
+    pub loss_pct: f64,
+    pub rtt: Option<MetricStats>,
+    pub samples_ms: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PmtuSummary {
+    pub address: String,
+    pub mtu_bytes: u32,
+    pub min_probed_bytes: u32,
+    pub max_probed_bytes: u32,
+    pub blackhole_from_bytes: Option<u32>,
+    #[serde(default)]
+    pub signalled_limit_bytes: Option<u32>,
+    #[serde(default)]
+    pub no_reply_from_bytes: Option<u32>,
+    pub local_limit_bytes: Option<u32>,
+    pub probes: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HttpTiming {
+    pub url: String,
+    pub remote_address: String,
+    pub status: u16,
+    pub dns_ms: f64,
+    pub connect_ms: f64,
+    pub tls_ms: Option<f64>,
+    pub ttfb_ms: f64,
+    pub transfer_ms: f64,
+    pub total_ms: f64,
+    pub bytes: u64,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -472,10 +1082,22 @@ pub struct BandwidthSummary {
     #[serde(default = "default_target_transfer_duration_ms")]
     pub target_transfer_duration_ms: u64,
     #[serde(default)]
+    pub transfer_duration_ms: Option<u64>,
+    #[serde(default)]
+    pub ramp_up_exclusion_ms: u64,
+    #[serde(default)]
+    pub download_average_mbps: Option<f64>,
+    #[serde(default)]
+    pub upload_average_mbps: Option<f64>,
+    #[serde(default)]
     pub bandwidth_elapsed_ms: f64,
     pub runs: u32,
     #[serde(default)]
     pub warmup_runs: u32,
+    #[serde(default = "default_min_successful_runs")]
+    pub min_successful_runs: u32,
+    #[serde(default)]
+    pub failed_attempts: Vec<TransferFailure>,
     #[serde(default = "default_transfer_attempts")]
     pub transfer_attempts: u32,
     #[serde(default = "default_transfer_timeout_seconds")]
@@ -484,6 +1106,56 @@ pub struct BandwidthSummary {
     pub upload_streams: u32,
     pub download_url: String,
     pub upload_url: String,
+    #[serde(default)]
+    pub download_sizing: Option<DownloadSizing>,
+    #[serde(default)]
+    pub upload_payload: UploadPayload,
+    #[serde(default)]
+    pub loaded_latency: Option<LoadedLatencySummary>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LoadedLatencySummary {
+    pub target_address: Option<String>,
+    pub endpoint_address: Option<String>,
+    pub idle: LoadedLatencyPhase,
+    pub download: LoadedLatencyPhase,
+    pub upload: LoadedLatencyPhase,
+    pub download_increase_ms: Option<f64>,
+    pub upload_increase_ms: Option<f64>,
+    pub grade: Option<BufferbloatGrade>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LoadedLatencyPhase {
+    pub target: Option<LatencySeries>,
+    pub endpoint: Option<LatencySeries>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LatencySeries {
+    pub sent: u32,
+    pub received: u32,
+    pub loss_pct: f64,
+    pub rtt: Option<MetricStats>,
+    pub samples_ms: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ResponsivenessSummary {
+    pub rpm: f64,
+    pub endpoint: String,
+    pub probe_url: String,
+    pub duration_ms: f64,
+    pub download_streams: u32,
+    pub upload_streams: u32,
+    pub foreign_probes: u32,
+    pub reused_probes: u32,
+    pub failed_probes: u32,
+    pub tcp_connect: Option<MetricStats>,
+    pub tls_handshake: Option<MetricStats>,
+    pub foreign_http: Option<MetricStats>,
+    pub reused_http: Option<MetricStats>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -493,6 +1165,16 @@ pub struct EndpointHealth {
     pub upload_url: String,
     pub latency_ms: Option<f64>,
     pub error: Option<String>,
+    #[serde(default)]
+    pub status: Option<u16>,
+    // The peer the health-check request connected to (the proxy when one is
+    // configured).
+    #[serde(default)]
+    pub remote_address: Option<String>,
+    // Phases from a separate, direct request; it may reach a different address
+    // than the health check itself.
+    #[serde(default)]
+    pub timing: Option<HttpTiming>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -510,37 +1192,245 @@ pub struct TransferSample {
     #[serde(default)]
     pub target_bytes: usize,
     pub mbps: f64,
+    #[serde(default)]
+    pub average_mbps: f64,
     pub bytes: u64,
     pub elapsed_ms: f64,
     pub streams: u32,
+    #[serde(default)]
+    pub window_ms: Option<f64>,
+    #[serde(default)]
+    pub series: Vec<ThroughputPoint>,
+    #[serde(default)]
+    pub ramp_up_fallback: bool,
 }
 
-pub async fn run_probe_suite(options: &ProbeOptions) -> Result<ProbeReport> {
-    let ping_result = measure_ping(&options.target, options.samples);
-    let dns_result = measure_dns(&options.target);
-    let bandwidth_result = measure_bandwidth(&options.bandwidth).await;
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ThroughputPoint {
+    pub elapsed_ms: f64,
+    pub bytes: u64,
+}
 
-    Ok(ProbeReport {
-        target: options.target.clone(),
-        profile: options.profile,
-        bandwidth_provider: options.bandwidth.provider.to_string(),
-        samples: options.samples,
-        created_at_unix_ms: SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .context("system clock is before unix epoch")?
-            .as_millis(),
-        ping: ping_result
-            .map(ProbeOutcome::success)
-            .unwrap_or_else(ProbeOutcome::failure),
-        dns: dns_result
-            .map(ProbeOutcome::success)
-            .unwrap_or_else(ProbeOutcome::failure),
-        bandwidth: bandwidth_result
-            .map(ProbeOutcome::success)
-            .unwrap_or_else(ProbeOutcome::failure),
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferFailure {
+    pub phase: TransferPhase,
+    pub direction: TransferDirection,
+    pub run: u32,
+    pub attempt: u32,
+    pub error: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransferPhase {
+    Warmup,
+    Measured,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum TransferDirection {
+    Download,
+    Upload,
+}
+
+pub async fn run_probe_suite(options: &ProbeOptions) -> Result<ProbeReport> {
+    let family = options.family.and_then(FamilyMode::pinned);
+    let families = match options.family {
+        Some(FamilyMode::Both) => measure_families(options).await,
+        _ => Vec::new(),
+    };
+    // In dual-stack mode the top-level sections mirror the family of the first
+    // address getaddrinfo returns (RFC 6724 order), so history, exports, and
+    // comparisons keep working.
+    let preferred = preferred_family(&options.target);
+    let default_family = families
+        .iter()
+        .filter(|family| family.address.is_some())
+        .find(|family| Some(family.family) == preferred)
+        .or_else(|| families.iter().find(|family| family.address.is_some()))
+        .or(families.first());
+
+    let ping = match default_family {
+        Some(report) => report.ping.clone(),
+        None => measure_ping(&options.target, family, options.samples, options.ping)
+            .await
+            .map(ProbeOutcome::success)
+            .unwrap_or_else(ProbeOutcome::failure),
+    };
+    let dns_result =
+        measure_dns(&options.target, options.dns_query.as_ref(), options.samples).await;
+    let address_pings = match (&dns_result, options.ping_all_addresses) {
+        (Ok(dns), true) => {
+            measure_address_pings(&dns.addresses, family, options.samples, options.ping).await
+        }
+        _ => Vec::new(),
+    };
+    let tcp = match (default_family, options.tcp_port) {
+        (Some(report), _) => report.tcp.clone(),
+        (None, Some(port)) => Some(
+            measure_tcp_connect(&options.target, family, port, options.samples)
+                .await
+                .map(ProbeOutcome::success)
+                .unwrap_or_else(ProbeOutcome::failure),
+        ),
+        (None, None) => None,
+    };
+    let tls_result = match options.tls_port {
+        Some(port) => Some(measure_tls(&options.target, family, port).await),
+        None => None,
+    };
+    let udp_result = match options.udp_echo {
+        Some(config) => Some(measure_udp_echo(&options.target, family, config).await),
+        None => None,
+    };
+    let trace_result = match options.trace {
+        Some(config) => Some(measure_trace(&options.target, family, config).await),
+        None => None,
+    };
+    let pmtu_result = match options.pmtu {
+        Some(config) => Some(measure_pmtu(&options.target, family, config).await),
+        None => None,
+    };
+    let mut http_results = Vec::with_capacity(options.http_urls.len());
+    for url in &options.http_urls {
+        http_results.push(
+            measure_http_timing(
+                url,
+                family,
+                Duration::from_secs(options.bandwidth.transfer_timeout_seconds),
+            )
+            .await,
+        );
+    }
+    let bandwidth = match default_family {
+        Some(report) => report.bandwidth.clone(),
+        None => measure_bandwidth(&options.target, &options.bandwidth)
+            .await
+            .map(ProbeOutcome::success)
+            .unwrap_or_else(ProbeOutcome::failure),
+    };
+    let responsiveness_result = match (&bandwidth.value, options.responsiveness) {
+        (_, false) => None,
+        (Some(summary), true) => Some(
+            measure_responsiveness(
+                summary,
+                options
+                    .bandwidth
+                    .family
+                    .or(default_family.map(|report| report.family)),
+                Duration::from_secs(options.bandwidth.transfer_timeout_seconds),
+                RESPONSIVENESS_RAMP_UP,
+                RESPONSIVENESS_DURATION,
+            )
+            .await,
+        ),
+        (None, true) => Some(Err(anyhow!(
+            "no bandwidth endpoint to load: {}",
+            bandwidth
+                .error
+                .as_deref()
+                .unwrap_or("bandwidth probe failed")
+        ))),
+    };
+
+    Ok(ProbeReport {
+        target: options.target.clone(),
+        profile: options.profile,
+        bandwidth_provider: options.bandwidth.provider.to_string(),
+        samples: options.samples,
+        created_at_unix_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .context("system clock is before unix epoch")?
+            .as_millis(),
+        family: options.family,
+        ping_config: options.ping,
+        quality: ping.value.as_ref().and_then(quality::estimate),
+        ping,
+        dns: dns_result
+            .map(ProbeOutcome::success)
+            .unwrap_or_else(ProbeOutcome::failure),
+        address_pings,
+        tcp,
+        tls: tls_result.map(|result| {
+            result
+                .map(ProbeOutcome::success)
+                .unwrap_or_else(ProbeOutcome::failure)
+        }),
+        udp: udp_result.map(|result| {
+            result
+                .map(ProbeOutcome::success)
+                .unwrap_or_else(ProbeOutcome::failure)
+        }),
+        trace: trace_result.map(|result| {
+            result
+                .map(ProbeOutcome::success)
+                .unwrap_or_else(ProbeOutcome::failure)
+        }),
+        pmtu: pmtu_result.map(|result| {
+            result
+                .map(ProbeOutcome::success)
+                .unwrap_or_else(ProbeOutcome::failure)
+        }),
+        http: http_results
+            .into_iter()
+            .map(|result| {
+                result
+                    .map(ProbeOutcome::success)
+                    .unwrap_or_else(ProbeOutcome::failure)
+            })
+            .collect(),
+        bandwidth,
+        responsiveness: responsiveness_result.map(|result| {
+            result
+                .map(ProbeOutcome::success)
+                .unwrap_or_else(ProbeOutcome::failure)
+        }),
+        families,
     })
 }
 
+async fn measure_families(options: &ProbeOptions) -> Vec<FamilyReport> {
+    let mut families = Vec::with_capacity(2);
+
+    for family in [AddressFamily::V4, AddressFamily::V6] {
+        let address = resolve_target_address(&options.target, Some(family))
+            .ok()
+            .map(|address| address.to_string());
+        let ping = measure_ping(&options.target, Some(family), options.samples, options.ping)
+            .await
+            .map(ProbeOutcome::success)
+            .unwrap_or_else(ProbeOutcome::failure);
+        let tcp = match options.tcp_port {
+            Some(port) => Some(
+                measure_tcp_connect(&options.target, Some(family), port, options.samples)
+                    .await
+                    .map(ProbeOutcome::success)
+                    .unwrap_or_else(ProbeOutcome::failure),
+            ),
+            None => None,
+        };
+        let bandwidth_config = BandwidthConfig {
+            family: Some(family),
+            ..options.bandwidth.clone()
+        };
+        let bandwidth = measure_bandwidth(&options.target, &bandwidth_config)
+            .await
+            .map(ProbeOutcome::success)
+            .unwrap_or_else(ProbeOutcome::failure);
+
+        families.push(FamilyReport {
+            family,
+            address,
+            ping,
+            tcp,
+            bandwidth,
+        });
+    }
+
+    families
+}
+
 pub fn format_report(report: &ProbeReport) -> String {
     let mut output = String::new();
     output.push_str(&format!(
@@ -548,13 +1438,17 @@ pub fn format_report(report: &ProbeReport) -> String {
         env!("CARGO_PKG_VERSION"),
         report.target
     ));
+    let family = report
+        .family
+        .map(|family| format!(" | Family: {family}"))
+        .unwrap_or_default();
     output.push_str(&format!(
-        "Profile: {} | Samples: {} | Bandwidth provider: {}\n\n",
+        "Profile: {} | Samples: {} | Bandwidth provider: {}{family}\n\n",
         report.profile, report.samples, report.bandwidth_provider
     ));
 
     output.push_str("Ping\n");
-    output.push_str(&format_outcome(&report.ping, |ping| {
+    output.push_str(&format_outcome(&report.ping, "  ", |ping| {
         let latency = match (ping.min_ms, ping.avg_ms, ping.max_ms) {
             (Some(min), Some(avg), Some(max)) => {
                 format!("min/avg/max: {:.2}/{:.2}/{:.2} ms", min, avg, max)
@@ -567,6 +1461,8 @@ pub fn format_report(report: &ProbeReport) -> String {
             .unwrap_or_else(|| "unavailable".to_string());
 
         [
+            format!("  engine: {}", ping.engine),
+            format!("  settings: {}", report.ping_config),
             format!("  sent/received: {}/{}", ping.sent, ping.received),
             format!("  packet loss: {:.2}%", ping.packet_loss_pct),
             format!("  {latency}"),
@@ -576,21 +1472,164 @@ pub fn format_report(report: &ProbeReport) -> String {
             ),
             format!("  jitter: {jitter}"),
         ]
+        .into_iter()
+        .chain(ping.sequence.as_ref().map(format_ping_sequence))
+        .collect::<Vec<_>>()
         .join("\n")
     }));
     output.push('\n');
+    output.push_str("\nQuality\n");
+    output.push_str(
+        &report
+            .quality
+            .as_ref()
+            .map(format_quality)
+            .unwrap_or_else(|| "  unavailable: no ping round trips to estimate from".to_string()),
+    );
+    output.push('\n');
     output.push_str("\nDNS\n");
-    output.push_str(&format_outcome(&report.dns, |dns| {
+    output.push_str(&format_outcome(&report.dns, "  ", |dns| {
         [
             format!("  resolution time: {:.2} ms", dns.resolution_time_ms),
             format!("  addresses: {}", dns.addresses.join(", ")),
         ]
+        .into_iter()
+        .chain(dns.resolvers.iter().map(format_dns_resolver))
+        .collect::<Vec<_>>()
         .join("\n")
     }));
     output.push('\n');
+    if !report.address_pings.is_empty() {
+        output.push_str("\nPer-address ping\n");
+        output.push_str(&format_address_pings(&report.address_pings));
+        output.push('\n');
+    }
+    if !report.families.is_empty() {
+        output.push_str("\nAddress families\n");
+        output.push_str(&format_families(&report.families));
+        output.push('\n');
+    }
+    if let Some(tcp) = &report.tcp {
+        output.push_str("\nTCP connect\n");
+        output.push_str(&format_outcome(tcp, "  ", |tcp| {
+            let connect = tcp
+                .connect
+                .as_ref()
+                .map(|stats| {
+                    format!(
+                        "min/avg/max: {:.2}/{:.2}/{:.2} ms",
+                        stats.min, stats.mean, stats.max
+                    )
+                })
+                .unwrap_or_else(|| "min/avg/max: unavailable".to_string());
+            let mut lines = vec![
+                format!("  address: {}:{}", tcp.address, tcp.port),
+                format!("  sent/connected: {}/{}", tcp.sent, tcp.connected),
+                format!("  connect loss: {:.2}%", tcp.loss_pct),
+                format!("  {connect}"),
+                format!(
+                    "  median/p95/stddev: {}",
+                    format_optional_triplet(
+                        tcp.connect.as_ref().map(|stats| stats.median),
+                        tcp.connect.as_ref().map(|stats| stats.p95),
+                        tcp.connect.as_ref().map(|stats| stats.stddev),
+                        "ms"
+                    )
+                ),
+                format!("  jitter: {}", format_optional_value(tcp.jitter_ms, "ms")),
+            ];
+            if let Some(error) = &tcp.last_error {
+                lines.push(format!("  last error: {error}"));
+            }
+            lines.join("\n")
+        }));
+        output.push('\n');
+    }
+    if let Some(tls) = &report.tls {
+        output.push_str("\nTLS\n");
+        output.push_str(&format_outcome(tls, "  ", format_tls));
+        output.push('\n');
+    }
+    if let Some(udp) = &report.udp {
+        output.push_str("\nUDP echo\n");
+        output.push_str(&format_outcome(udp, "  ", |udp| {
+            [
+                format!("  address: {}:{}", udp.address, udp.port),
+                format!(
+                    "  stream: {} packets, {} ms interval, {} payload bytes",
+                    udp.sent, udp.interval_ms, udp.payload_bytes
+                ),
+                format!("  sent/received: {}/{}", udp.sent, udp.received),
+                format!("  packet loss: {:.2}%", udp.loss_pct),
+                format!(
+                    "  duplicates/out of order: {}/{}",
+                    udp.duplicates, udp.out_of_order
+                ),
+                format!(
+                    "  rtt median/p95/stddev: {}",
+                    format_optional_triplet(
+                        udp.rtt.as_ref().map(|stats| stats.median),
+                        udp.rtt.as_ref().map(|stats| stats.p95),
+                        udp.rtt.as_ref().map(|stats| stats.stddev),
+                        "ms"
+                    )
+                ),
+                format!(
+                    "  jitter (RFC 3550): {}",
+                    format_optional_value(udp.jitter_ms, "ms")
+                ),
+            ]
+            .join("\n")
+        }));
+        output.push('\n');
+    }
+    if let Some(trace) = &report.trace {
+        output.push_str("\nPath\n");
+        output.push_str(&format_outcome(trace, "  ", format_trace));
+        output.push('\n');
+    }
+    if let Some(pmtu) = &report.pmtu {
+        output.push_str("\nPath MTU\n");
+        output.push_str(&format_outcome(pmtu, "  ", |pmtu| {
+            let limit = if let Some(size) = pmtu.signalled_limit_bytes {
+                format!("a router rejected packets of {size} bytes and larger as too big")
+            } else if let Some(size) = pmtu.blackhole_from_bytes {
+                format!(
+                    "packets of {size} bytes and larger are blackholed (no reply, no ICMP error)"
+                )
+            } else if let Some(size) = pmtu.no_reply_from_bytes {
+                format!("no reply above {} bytes", size - 1)
+            } else if let Some(size) = pmtu.local_limit_bytes {
+                format!("packets of {size} bytes and larger exceed the local interface MTU")
+            } else {
+                format!("no limit found up to {} bytes", pmtu.max_probed_bytes)
+            };
+            [
+                format!("  address: {}", pmtu.address),
+                format!("  path MTU: {} bytes", pmtu.mtu_bytes),
+                format!("  limit: {limit}"),
+                format!(
+                    "  probes: {} between {} and {} bytes",
+                    pmtu.probes, pmtu.min_probed_bytes, pmtu.max_probed_bytes
+                ),
+            ]
+            .join("\n")
+        }));
+        output.push('\n');
+    }
+    if !report.http.is_empty() {
+        output.push_str("\nHTTP timing\n");
+        let sections = report
+            .http
+            .iter()
+            .map(|outcome| format_outcome(outcome, "  ", format_http_timing))
+            .collect::<Vec<_>>();
+        output.push_str(&sections.join("\n"));
+        output.push('\n');
+    }
     output.push_str("\nBandwidth\n");
-    output.push_str(&format_outcome(&report.bandwidth, |bandwidth| {
-        [
+    output.push_str(&format_outcome(&report.bandwidth, "  ", |bandwidth| {
+        let mut lines = vec![
             format!(
                 "  download: {:.2} Mbps median, {:.2} Mbps p95, {:.2} Mbps stddev",
                 bandwidth.download.median, bandwidth.download.p95, bandwidth.download.stddev
@@ -599,6 +1638,13 @@ pub fn format_report(report: &ProbeReport) -> String {
                 "  upload: {:.2} Mbps median, {:.2} Mbps p95, {:.2} Mbps stddev",
                 bandwidth.upload.median, bandwidth.upload.p95, bandwidth.upload.stddev
             ),
+            format!(
+                "  ramp-up excluded: {} ms{} | whole-transfer average: {} down, {} up",
+                bandwidth.ramp_up_exclusion_ms,
+                format_ramp_up_fallbacks(&bandwidth.download_runs, &bandwidth.upload_runs),
+                format_optional_value(bandwidth.download_average_mbps, "Mbps"),
+                format_optional_value(bandwidth.upload_average_mbps, "Mbps")
+            ),
             format!(
                 "  runs/streams: {} warmup, {} measured, {} attempts, {}s timeout, {} down streams, {} up streams",
                 bandwidth.warmup_runs,
@@ -609,15 +1655,31 @@ pub fn format_report(report: &ProbeReport) -> String {
                 bandwidth.upload_streams
             ),
             format!(
-                "  configured sizing: {} down bytes, {} up bytes",
-                bandwidth.download_size_bytes, bandwidth.upload_size_bytes
+                "  successful runs: {}/{} down ({:.0}%), {}/{} up ({:.0}%), minimum {}",
+                bandwidth.download_runs.len(),
+                bandwidth.runs,
+                bandwidth.download_success_ratio() * 100.0,
+                bandwidth.upload_runs.len(),
+                bandwidth.runs,
+                bandwidth.upload_success_ratio() * 100.0,
+                bandwidth.min_successful_runs
             ),
             format!(
-                "  calibrated sizing: {} down bytes, {} up bytes, target {} ms",
-                bandwidth.calibrated_download_size_bytes,
-                bandwidth.calibrated_upload_size_bytes,
-                bandwidth.target_transfer_duration_ms
+                "  configured sizing: {} down bytes, {} up bytes",
+                bandwidth.download_size_bytes, bandwidth.upload_size_bytes
             ),
+            match bandwidth.transfer_duration_ms {
+                Some(window_ms) => format!(
+                    "  duration mode: {window_ms} ms window per run, measured windows {}",
+                    format_transfer_windows(&bandwidth.download_runs, &bandwidth.upload_runs)
+                ),
+                None => format!(
+                    "  calibrated sizing: {} down bytes, {} up bytes, target {} ms",
+                    bandwidth.calibrated_download_size_bytes,
+                    bandwidth.calibrated_upload_size_bytes,
+                    bandwidth.target_transfer_duration_ms
+                ),
+            },
             format!(
                 "  bandwidth elapsed: {}",
                 format_optional_value(Some(bandwidth.bandwidth_elapsed_ms), "ms")
@@ -630,22 +1692,383 @@ pub fn format_report(report: &ProbeReport) -> String {
                 "  endpoint latency: {}",
                 format_optional_value(bandwidth.endpoint_latency_ms, "ms")
             ),
-            format!("  download source: {}", bandwidth.download_url),
-            format!("  upload source: {}", bandwidth.upload_url),
-        ]
-        .join("\n")
+            format!(
+                "  endpoint timing (separate direct probe): {}",
+                bandwidth
+                    .endpoint_candidates
+                    .iter()
+                    .find(|candidate| candidate.name == bandwidth.endpoint)
+                    .and_then(|candidate| candidate.timing.as_ref())
+                    .map(format_http_phases)
+                    .unwrap_or_else(|| "unavailable".to_string())
+            ),
+            format!(
+                "  download source: {}{}",
+                bandwidth.download_url,
+                bandwidth
+                    .download_sizing
+                    .map(|sizing| format!(" ({sizing} sizing)"))
+                    .unwrap_or_default()
+            ),
+            format!(
+                "  upload source: {} ({} payload)",
+                bandwidth.upload_url, bandwidth.upload_payload
+            ),
+        ];
+        lines.extend(bandwidth.failed_attempts.iter().map(|failure| {
+            format!(
+                "  failed {} {} run {}, attempt {}: {}",
+                failure.phase, failure.direction, failure.run, failure.attempt, failure.error
+            )
+        }));
+        if let Some(loaded) = &bandwidth.loaded_latency {
+            lines.extend(format_loaded_latency(loaded));
+        }
+        lines.join("\n")
     }));
     output.push('\n');
+    if let Some(responsiveness) = &report.responsiveness {
+        output.push_str("\nResponsiveness\n");
+        output.push_str(&format_outcome(responsiveness, "  ", |responsiveness| {
+            let median = |stats: &Option<MetricStats>| {
+                format_optional_value(stats.as_ref().map(|stats| stats.median), "ms")
+            };
+            [
+                format!("  RPM: {:.0}", responsiveness.rpm),
+                format!(
+                    "  load: {} down streams, {} up streams against {} for {}",
+                    responsiveness.download_streams,
+                    responsiveness.upload_streams,
+                    responsiveness.endpoint,
+                    format_optional_value(Some(responsiveness.duration_ms), "ms")
+                ),
+                format!(
+                    "  probes: {} new connection, {} reused connection, {} failed",
+                    responsiveness.foreign_probes,
+                    responsiveness.reused_probes,
+                    responsiveness.failed_probes
+                ),
+                format!(
+                    "  median tcp/tls/http: {} | {} | {}",
+                    median(&responsiveness.tcp_connect),
+                    median(&responsiveness.tls_handshake),
+                    median(&responsiveness.foreign_http)
+                ),
+                format!(
+                    "  median reused http: {}",
+                    median(&responsiveness.reused_http)
+                ),
+            ]
+            .join("\n")
+        }));
+        output.push('\n');
+    }
 
     output
 }
 
-fn format_outcome<T>(outcome: &ProbeOutcome<T>, formatter: impl FnOnce(&T) -> String) -> String {
+fn format_loaded_latency(loaded: &LoadedLatencySummary) -> Vec<String> {
+    let grade = loaded
+        .grade
+        .map(|grade| grade.to_string())
+        .unwrap_or_else(|| "unavailable".to_string());
+    let increase = |value: Option<f64>| {
+        value
+            .map(|value| format!("+{value:.2} ms"))
+            .unwrap_or_else(|| "unavailable".to_string())
+    };
+
+    vec![
+        format!(
+            "  loaded latency: grade {grade} | download {} | upload {}",
+            increase(loaded.download_increase_ms),
+            increase(loaded.upload_increase_ms)
+        ),
+        format!("    idle: {}", format_latency_phase(&loaded.idle)),
+        format!("    download: {}", format_latency_phase(&loaded.download)),
+        format!("    upload: {}", format_latency_phase(&loaded.upload)),
+    ]
+}
+
+fn format_latency_phase(phase: &LoadedLatencyPhase) -> String {
+    let format_series = |label: &str, series: &Option<LatencySeries>| {
+        series.as_ref().map(|series| {
+            format!(
+                "{label} median/p95 {} ({:.2}% loss)",
+                match &series.rtt {
+                    Some(stats) => format!("{:.2}/{:.2} ms", stats.median, stats.p95),
+                    None => "unavailable".to_string(),
+                },
+                series.loss_pct
+            )
+        })
+    };
+
+    let parts = [
+        format_series("target", &phase.target),
+        format_series("endpoint", &phase.endpoint),
+    ]
+    .into_iter()
+    .flatten()
+    .collect::<Vec<_>>();
+    if parts.is_empty() {
+        "unavailable".to_string()
+    } else {
+        parts.join(" | ")
+    }
+}
+
+fn format_trace(trace: &TraceSummary) -> String {
+    let status = if trace.reached {
+        format!("reached at hop {}", trace.hops.len())
+    } else {
+        format!("not reached within {} hops", trace.max_hops)
+    };
+    let mut lines = vec![format!(
+        "  destination: {} ({status}, {} cycles)",
+        trace.address, trace.cycles
+    )];
+    lines.extend(trace.hops.iter().map(|hop| {
+        let addresses = if hop.addresses.is_empty() {
+            "*".to_string()
+        } else {
+            hop.addresses.join(", ")
+        };
+        format!(
+            "  {:>2}. {addresses} | loss {:.2}% | min/median/max {}",
+            hop.ttl,
+            hop.loss_pct,
+            format_optional_triplet(
+                hop.rtt.as_ref().map(|stats| stats.min),
+                hop.rtt.as_ref().map(|stats| stats.median),
+                hop.rtt.as_ref().map(|stats| stats.max),
+                "ms"
+            )
+        )
+    }));
+    lines.join("\n")
+}
+
+fn format_http_timing(timing: &HttpTiming) -> String {
+    [
+        format!("  {} ({})", timing.url, timing.remote_address),
+        format!("    status: {} | bytes: {}", timing.status, timing.bytes),
+        format!("    {}", format_http_phases(timing)),
+    ]
+    .join("\n")
+}
+
+fn format_http_phases(timing: &HttpTiming) -> String {
+    format!(
+        "dns {:.2} ms | connect {:.2} ms | tls {} | ttfb {:.2} ms | transfer {:.2} ms | total {:.2} ms",
+        timing.dns_ms,
+        timing.connect_ms,
+        format_optional_value(timing.tls_ms, "ms"),
+        timing.ttfb_ms,
+        timing.transfer_ms,
+        timing.total_ms
+    )
+}
+
+fn format_outcome<T>(
+    outcome: &ProbeOutcome<T>,
+    indent: &str,
+    formatter: impl FnOnce(&T) -> String,
+) -> String {
     match (&outcome.value, &outcome.error) {
         (Some(value), _) => formatter(value),
-        (None, Some(error)) => format!("  error: {error}"),
-        (None, None) => "  unavailable".to_string(),
+        (None, Some(error)) => format!("{indent}error: {error}"),
+        (None, None) => format!("{indent}unavailable"),
+    }
+}
+
+fn format_ping_sequence(sequence: &PingSequenceSummary) -> String {
+    let bursts = match sequence.loss_bursts.len() {
+        0 => "none".to_string(),
+        count => format!("{count} (longest {} in a row)", sequence.max_loss_burst),
+    };
+    [
+        format!(
+            "  duplicates/out of order: {}/{}",
+            sequence.duplicates, sequence.out_of_order
+        ),
+        format!("  loss bursts: {bursts}"),
+        format!(
+            "  longest reply gap: {}",
+            format_optional_value(sequence.longest_gap_ms, "ms")
+        ),
+    ]
+    .join("\n")
+}
+
+fn format_transfer_windows(download: &[TransferSample], upload: &[TransferSample]) -> String {
+    let windows = download
+        .iter()
+        .chain(upload)
+        .filter_map(|sample| sample.window_ms)
+        .collect::<Vec<_>>();
+    match calculate_stats(&windows) {
+        Some(stats) => format!("{:.0}-{:.0} ms", stats.min, stats.max),
+        None => "unavailable".to_string(),
+    }
+}
+
+fn format_quality(quality: &QualityEstimate) -> String {
+    let mut lines = vec![format!(
+        "  R-factor: {:.1} | MOS: {:.2} (G.711 E-model, {:.1} ms mouth-to-ear)",
+        quality.r_factor, quality.mos, quality.one_way_delay_ms
+    )];
+    lines.extend(quality.verdicts.iter().map(|verdict| {
+        let (good, fair) = verdict.use_case.limits();
+        format!(
+            "  {}: {} (good within {good}; fair within {fair})",
+            verdict.use_case, verdict.verdict
+        )
+    }));
+    lines.join("\n")
+}
+
+fn format_tls(tls: &TlsSummary) -> String {
+    let mut lines = vec![
+        format!(
+            "  address: {}:{} (SNI {})",
+            tls.address, tls.port, tls.server_name
+        ),
+        format!(
+            "  connect/handshake: {:.2}/{:.2} ms",
+            tls.connect_ms, tls.handshake_ms
+        ),
+        format!(
+            "  protocol: {} | cipher: {} | ALPN: {}",
+            tls.protocol,
+            tls.cipher,
+            tls.alpn.as_deref().unwrap_or("none")
+        ),
+    ];
+    if let Some(leaf) = tls.leaf() {
+        lines.push(format!("  subject: {}", leaf.subject));
+        lines.push(format!("  issuer: {}", leaf.issuer));
+        if !leaf.subject_alt_names.is_empty() {
+            lines.push(format!("  SANs: {}", leaf.subject_alt_names.join(", ")));
+        }
+        lines.push(format!("  serial: {}", leaf.serial));
+    }
+    lines.push(match tls.days_until_expiry {
+        days if days < 0 => format!("  expiry: expired {} days ago", -days),
+        days => format!("  expiry: in {days} days"),
+    });
+    lines.push(format!(
+        "  chain length: {}",
+        tls.chain.len() + tls.chain_errors.len()
+    ));
+    for error in &tls.chain_errors {
+        lines.push(format!("  unparsed {error}"));
+    }
+    lines.push(format!(
+        "  validation: {}",
+        tls.validation_error.as_deref().unwrap_or("ok")
+    ));
+    lines.join("\n")
+}
+
+fn format_address_pings(address_pings: &[AddressPing]) -> String {
+    let width = address_pings
+        .iter()
+        .map(|ping| ping.address.len())
+        .chain(["address".len()])
+        .max()
+        .unwrap_or_default();
+    let mut lines = vec![format!(
+        "  {:<width$}  {:>9}  {:>7}  {:>10}  {:>10}  {:>10}",
+        "address", "received", "loss", "median", "p95", "jitter"
+    )];
+
+    let cell = |value: Option<f64>| {
+        value
+            .map(|value| format!("{value:.2} ms"))
+            .unwrap_or_else(|| "-".to_string())
+    };
+    for address_ping in address_pings {
+        let row = match (&address_ping.ping.value, &address_ping.ping.error) {
+            (Some(ping), _) => format!(
+                "{:>9}  {:>7}  {:>10}  {:>10}  {:>10}",
+                format!("{}/{}", ping.received, ping.sent),
+                format!("{:.1}%", ping.packet_loss_pct),
+                cell(ping.median_ms),
+                cell(ping.p95_ms),
+                cell(ping.jitter_ms),
+            ),
+            (None, error) => format!("error: {}", error.as_deref().unwrap_or("unavailable")),
+        };
+        lines.push(format!("  {:<width$}  {row}", address_ping.address));
+    }
+
+    lines.join("\n")
+}
+
+fn format_families(families: &[FamilyReport]) -> String {
+    let mut lines = Vec::new();
+    for report in families {
+        lines.push(format!(
+            "  {} ({})",
+            report.family,
+            report.address.as_deref().unwrap_or("unresolved")
+        ));
+        lines.push(format!(
+            "    ping: {}",
+            format_outcome(&report.ping, "", |ping| format!(
+                "{} median, {:.2}% loss",
+                format_optional_value(ping.median_ms, "ms"),
+                ping.packet_loss_pct
+            ))
+        ));
+        if let Some(tcp) = &report.tcp {
+            lines.push(format!(
+                "    tcp connect: {}",
+                format_outcome(tcp, "", |tcp| format!(
+                    "{} median, {:.2}% loss",
+                    format_optional_value(tcp.connect.as_ref().map(|stats| stats.median), "ms"),
+                    tcp.loss_pct
+                ))
+            ));
+        }
+        lines.push(format!(
+            "    bandwidth: {}",
+            format_outcome(&report.bandwidth, "", |bandwidth| format!(
+                "{:.2} Mbps down, {:.2} Mbps up",
+                bandwidth.download_mbps, bandwidth.upload_mbps
+            ))
+        ));
+    }
+
+    let find = |family| families.iter().find(|report| report.family == family);
+    if let (Some(v4), Some(v6)) = (find(AddressFamily::V4), find(AddressFamily::V6)) {
+        let gap = |value: fn(&FamilyReport) -> Option<f64>, unit| match (value(v4), value(v6)) {
+            (Some(v4), Some(v6)) => format!("{:+.2} {unit}", v6 - v4),
+            _ => "unavailable".to_string(),
+        };
+        lines.push(format!(
+            "  IPv6 vs IPv4: ping median {} | tcp connect median {} | download {} | upload {}",
+            gap(|report| report.ping.value.as_ref()?.median_ms, "ms"),
+            gap(
+                |report| {
+                    let tcp = report.tcp.as_ref()?.value.as_ref()?;
+                    Some(tcp.connect.as_ref()?.median)
+                },
+                "ms"
+            ),
+            gap(
+                |report| Some(report.bandwidth.value.as_ref()?.download_mbps),
+                "Mbps"
+            ),
+            gap(
+                |report| Some(report.bandwidth.value.as_ref()?.upload_mbps),
+                "Mbps"
+            ),
+        ));
     }
+
+    lines.join("\n")
 }
 
 fn format_optional_triplet(
@@ -662,56 +2085,696 @@ fn format_optional_triplet(
     }
 }
 
+fn format_dns_resolver(resolver: &DnsResolverSummary) -> String {
+    let rcodes = if resolver.rcodes.is_empty() {
+        "no responses".to_string()
+    } else {
+        resolver
+            .rcodes
+            .iter()
+            .map(|(rcode, count)| format!("{rcode} x{count}"))
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    let ttl = resolver
+        .min_ttl_s
+        .map(|ttl| format!("ttl {ttl} s"))
+        .unwrap_or_else(|| "ttl unavailable".to_string());
+    let mut lines = vec![
+        format!(
+            "  resolver {} ({} over {}): {}/{} answered | {} | {}",
+            resolver.server,
+            resolver.record_type,
+            resolver.transport,
+            resolver.responses,
+            resolver.queries,
+            rcodes,
+            ttl
+        ),
+        format!(
+            "    query median/p95/stddev: {}",
+            format_optional_triplet(
+                resolver.rtt.as_ref().map(|stats| stats.median),
+                resolver.rtt.as_ref().map(|stats| stats.p95),
+                resolver.rtt.as_ref().map(|stats| stats.stddev),
+                "ms"
+            )
+        ),
+        format!(
+            "    answers: {}",
+            if resolver.answers.is_empty() {
+                "none".to_string()
+            } else {
+                resolver.answers.join(", ")
+            }
+        ),
+    ];
+    if let Some(handshake) = &resolver.handshake {
+        lines.insert(
+            1,
+            format!(
+                "    handshake: connect {:.2} ms | tls {} ({})",
+                handshake.connect_ms,
+                format_optional_value(handshake.tls_ms, "ms"),
+                handshake.remote_address
+            ),
+        );
+    }
+    if resolver.tcp_fallbacks > 0 {
+        lines.push(format!(
+            "    truncated, retried over tcp: {}",
+            resolver.tcp_fallbacks
+        ));
+    }
+    if let Some(error) = &resolver.last_error {
+        lines.push(format!("    last error: {error}"));
+    }
+
+    lines.join("\n")
+}
+
 fn format_optional_value(value: Option<f64>, unit: &str) -> String {
     value
         .map(|value| format!("{value:.2} {unit}"))
         .unwrap_or_else(|| "unavailable".to_string())
 }
 
-fn measure_ping(target: &str, samples: u32) -> Result<PingSummary> {
-    let sample_count = samples.max(1);
-    let mut command = Command::new("ping");
+async fn measure_ping(
+    target: &str,
+    family: Option<AddressFamily>,
+    samples: u32,
+    config: PingConfig,
+) -> Result<PingSummary> {
+    // Native ping blocks on socket reads and sleeps between echoes, so keep it
+    // off the runtime threads the HTTP and TCP probes share.
+    let target = target.to_string();
+    tokio::task::spawn_blocking(move || measure_ping_blocking(&target, family, samples, config))
+        .await
+        .context("ping task failed")?
+}
 
-    if cfg!(target_os = "windows") {
-        command.args(["-n", &sample_count.to_string(), target]);
-    } else {
-        command.args(["-c", &sample_count.to_string(), target]);
+fn measure_ping_blocking(
+    target: &str,
+    family: Option<AddressFamily>,
+    samples: u32,
+    config: PingConfig,
+) -> Result<PingSummary> {
+    let sample_count = samples.max(1);
+    let address = resolve_target_address(target, family)?;
+
+    match icmp::EchoSocket::open(address) {
+        Ok(socket) => measure_native_ping(&socket, sample_count, config),
+        // A pinned family hands ping the resolved literal so it cannot fall
+        // back to the other family on its own.
+        Err(_) if family.is_some() => {
+            measure_subprocess_ping(&address.to_string(), sample_count, config)
+        }
+        Err(_) => measure_subprocess_ping(target, sample_count, config),
     }
+}
 
-    let output = command
-        .output()
-        .with_context(|| format!("failed to execute ping against {target}"))?;
+async fn measure_tls(target: &str, family: Option<AddressFamily>, port: u16) -> Result<TlsSummary> {
+    let inspection = tls::inspect(target, port, family, TLS_PROBE_TIMEOUT).await?;
+    let now_unix_s = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .context("system clock is before unix epoch")?
+        .as_secs() as i64;
+    let not_after_unix_s = inspection
+        .chain
+        .first()
+        .map(|leaf| leaf.not_after_unix_s)
+        .unwrap_or(now_unix_s);
+
+    Ok(TlsSummary {
+        address: inspection.remote_address.ip().to_string(),
+        port,
+        server_name: target.to_string(),
+        connect_ms: duration_to_ms(inspection.connect),
+        handshake_ms: duration_to_ms(inspection.handshake),
+        protocol: inspection.protocol,
+        cipher: inspection.cipher,
+        alpn: inspection.alpn,
+        days_until_expiry: (not_after_unix_s - now_unix_s).div_euclid(86_400),
+        validation_error: inspection.validation_error,
+        chain: inspection.chain,
+        chain_errors: inspection.chain_errors,
+    })
+}
 
-    let stdout = String::from_utf8_lossy(&output.stdout);
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    let combined_output = format!("{stdout}\n{stderr}");
+async fn measure_address_pings(
+    addresses: &[String],
+    family: Option<AddressFamily>,
+    samples: u32,
+    config: PingConfig,
+) -> Vec<AddressPing> {
+    let mut measured: Vec<AddressPing> = Vec::with_capacity(addresses.len());
+
+    for address in addresses {
+        let matches_family = match (family, address.parse::<IpAddr>()) {
+            (Some(family), Ok(ip)) => family.matches(&ip),
+            _ => true,
+        };
+        if !matches_family || measured.iter().any(|ping| &ping.address == address) {
+            continue;
+        }
+        measured.push(AddressPing {
+            address: address.clone(),
+            ping: measure_ping(address, None, samples, config)
+                .await
+                .map(ProbeOutcome::success)
+                .unwrap_or_else(ProbeOutcome::failure),
+        });
+    }
 
-    parse_ping_output(&combined_output, sample_count)
+    measured
 }
 
-fn parse_ping_output(output: &str, sent: u32) -> Result<PingSummary> {
-    let samples_ms: Vec<f64> = output.lines().filter_map(extract_time_ms).collect();
-    let received = samples_ms.len() as u32;
-    let packet_loss_pct = if sent == 0 {
-        0.0
-    } else {
-        ((sent - received) as f64 / sent as f64) * 100.0
-    };
-
-    if sent == 0 {
-        return Err(anyhow!("ping sample count must be greater than zero"));
+fn preferred_family(target: &str) -> Option<AddressFamily> {
+    if let Ok(address) = target.parse::<IpAddr>() {
+        return Some(AddressFamily::of(&address));
     }
+    let address = (target, 0).to_socket_addrs().ok()?.next()?;
+    Some(AddressFamily::of(&address.ip()))
+}
 
-    if received == 0 && !output.to_ascii_lowercase().contains("ttl") && output.trim().is_empty() {
-        return Err(anyhow!("ping produced no parseable output"));
+fn resolve_target_address(target: &str, family: Option<AddressFamily>) -> Result<IpAddr> {
+    if let Ok(address) = target.parse::<IpAddr>() {
+        if let Some(family) = family.filter(|family| !family.matches(&address)) {
+            anyhow::bail!("{target} is not an {family} address");
+        }
+        return Ok(address);
     }
 
-    let (min_ms, avg_ms, median_ms, p95_ms, max_ms, stddev_ms, jitter_ms) = if samples_ms.is_empty()
-    {
-        (None, None, None, None, None, None, None)
-    } else {
-        let stats = calculate_stats(&samples_ms).context("failed to derive ping stats")?;
-        let jitter_ms = calculate_jitter_ms(&samples_ms);
+    let addresses: Vec<IpAddr> = (target, 0)
+        .to_socket_addrs()
+        .with_context(|| format!("failed to resolve {target}"))?
+        .map(|addr| addr.ip())
+        .collect();
+
+    match family {
+        Some(family) => addresses
+            .iter()
+            .find(|address| family.matches(address))
+            .copied()
+            .ok_or_else(|| anyhow!("no {family} addresses resolved for {target}")),
+        None => addresses
+            .iter()
+            .find(|address| address.is_ipv4())
+            .or_else(|| addresses.first())
+            .copied()
+            .ok_or_else(|| anyhow!("no IP addresses resolved for {target}")),
+    }
+}
+
+fn measure_native_ping(
+    socket: &icmp::EchoSocket,
+    sample_count: u32,
+    config: PingConfig,
+) -> Result<PingSummary> {
+    let engine = match socket.kind() {
+        icmp::EchoSocketKind::Datagram => PingEngine::IcmpDatagram,
+        icmp::EchoSocketKind::Raw => PingEngine::IcmpRaw,
+    };
+    if let Some(ttl) = config.ttl {
+        socket.set_ttl(ttl)?;
+    }
+    let started = Instant::now();
+    let mut sent_at = Vec::with_capacity(sample_count as usize);
+    let mut replies = Vec::with_capacity(sample_count as usize);
+
+    // Echoes go out on a fixed schedule while replies are collected in between,
+    // so a late reply is seen as reordered instead of silently dropped.
+    for sequence in 0..sample_count {
+        let sent = socket.send(sequence as u16, config.payload_bytes)?;
+        sent_at.push(sent);
+        let deadline = if sequence + 1 < sample_count {
+            started + config.interval() * (sequence + 1)
+        } else {
+            sent + config.timeout()
+        };
+
+        while let Some(reply_sequence) = socket.receive(deadline)? {
+            let received_at = Instant::now();
+            let Some(sent) = sent_at.get(usize::from(reply_sequence)) else {
+                continue;
+            };
+            let rtt = received_at.duration_since(*sent);
+            if rtt <= config.timeout() {
+                replies.push(PingReply {
+                    sequence: u32::from(reply_sequence),
+                    rtt_ms: duration_to_ms(rtt),
+                    arrival_ms: duration_to_ms(received_at.duration_since(started)),
+                });
+            }
+        }
+    }
+
+    summarize_ping_replies(engine, sample_count, &replies)
+}
+
+fn measure_subprocess_ping(
+    target: &str,
+    sample_count: u32,
+    config: PingConfig,
+) -> Result<PingSummary> {
+    let output = Command::new("ping")
+        .args(subprocess_ping_args(sample_count, config))
+        .arg(target)
+        .output()
+        .with_context(|| format!("failed to execute ping against {target}"))?;
+
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    let stderr = String::from_utf8_lossy(&output.stderr);
+    let combined_output = format!("{stdout}\n{stderr}");
+
+    parse_ping_output(&combined_output, sample_count, config.interval())
+}
+
+// Only settings the user changed become flags: BusyBox ping lacks several of
+// them, and iputils refuses sub-200 ms intervals without root, so defaults are
+// left to the system ping.
+fn subprocess_ping_args(sample_count: u32, config: PingConfig) -> Vec<String> {
+    let defaults = PingConfig::default();
+    let bsd = cfg!(any(
+        target_os = "macos",
+        target_os = "ios",
+        target_os = "freebsd"
+    ));
+    let mut args = Vec::new();
+    let mut flag = |name: &str, value: String| {
+        args.push(name.to_string());
+        args.push(value);
+    };
+
+    // Windows ping has no send interval option and always waits one second.
+    if cfg!(target_os = "windows") {
+        flag("-n", sample_count.to_string());
+        if config.payload_bytes != defaults.payload_bytes {
+            flag("-l", config.payload_bytes.to_string());
+        }
+        if config.timeout_ms != defaults.timeout_ms {
+            flag("-w", config.timeout_ms.to_string());
+        }
+        if let Some(ttl) = config.ttl {
+            flag("-i", ttl.to_string());
+        }
+        return args;
+    }
+
+    flag("-c", sample_count.to_string());
+    if config.payload_bytes != defaults.payload_bytes {
+        flag("-s", config.payload_bytes.to_string());
+    }
+    if config.interval_ms != defaults.interval_ms {
+        flag("-i", format!("{}", config.interval_ms as f64 / 1_000.0));
+    }
+    // BSD-derived pings take -W in milliseconds; iputils and BusyBox in seconds.
+    if config.timeout_ms != defaults.timeout_ms {
+        let timeout = if bsd {
+            config.timeout_ms
+        } else {
+            config.timeout_ms.div_ceil(1_000)
+        };
+        flag("-W", timeout.to_string());
+    }
+    if let Some(ttl) = config.ttl {
+        flag(if bsd { "-m" } else { "-t" }, ttl.to_string());
+    }
+    args
+}
+
+fn parse_ping_output(output: &str, sent: u32, interval: Duration) -> Result<PingSummary> {
+    if sent == 0 {
+        return Err(anyhow!("ping sample count must be greater than zero"));
+    }
+
+    let transcript = parse_ping_transcript(output);
+    let sent = transcript
+        .transmitted
+        .filter(|transmitted| *transmitted > 0)
+        .unwrap_or(sent);
+
+    if transcript.replies.is_empty() {
+        return match (transcript.received, transcript.rtt) {
+            (Some(0), _) => summarize_ping(PingEngine::Subprocess, sent, Vec::new()),
+            (Some(received), Some(rtt)) => Ok(summarize_ping_totals(sent, received, rtt)),
+            (Some(received), None) => Err(anyhow!(
+                "ping reported {received} replies but printed no round-trip times"
+            )),
+            (None, _) => Err(anyhow!(
+                "unrecognized ping output: {}",
+                output
+                    .lines()
+                    .map(str::trim)
+                    .find(|line| !line.is_empty())
+                    .unwrap_or("no output")
+            )),
+        };
+    }
+
+    let summary = summarize_ping_transcript_replies(
+        &transcript.replies,
+        transcript.first_sequence,
+        sent,
+        interval,
+    )?;
+    cross_check_ping_summary(&summary, &transcript)?;
+    Ok(summary)
+}
+
+fn summarize_ping_transcript_replies(
+    replies: &[(Option<u32>, f64)],
+    first_sequence: Option<u32>,
+    sent: u32,
+    interval: Duration,
+) -> Result<PingSummary> {
+    let samples_ms: Vec<f64> = replies.iter().map(|(_, rtt_ms)| *rtt_ms).collect();
+
+    // Windows ping prints no sequence numbers, so only loss totals are known.
+    let Some(sequences) = replies
+        .iter()
+        .map(|(sequence, _)| *sequence)
+        .collect::<Option<Vec<_>>>()
+    else {
+        return summarize_ping(PingEngine::Subprocess, sent, samples_ms);
+    };
+
+    // Without a header to go by, only a reply numbered 0 rules out iputils.
+    let first_sequence = first_sequence.unwrap_or(if sequences.contains(&0) { 0 } else { 1 });
+    let interval_ms = duration_to_ms(interval);
+    let replies = sequences
+        .iter()
+        .zip(samples_ms)
+        .map(|(sequence, rtt_ms)| {
+            let sequence = sequence.saturating_sub(first_sequence);
+            PingReply {
+                sequence,
+                rtt_ms,
+                arrival_ms: f64::from(sequence) * interval_ms + rtt_ms,
+            }
+        })
+        .collect::<Vec<_>>();
+
+    summarize_ping_replies(PingEngine::Subprocess, sent, &replies)
+}
+
+// The totals ping prints itself catch reply lines the parser skipped or
+// misread, which would otherwise show up as phantom loss or latency.
+fn cross_check_ping_summary(summary: &PingSummary, transcript: &PingTranscript) -> Result<()> {
+    if let Some(received) = transcript.received {
+        if received != summary.received {
+            return Err(anyhow!(
+                "ping reported {received} replies but {} reply lines were recognized",
+                summary.received
+            ));
+        }
+    }
+
+    if let (Some(rtt), Some(min_ms), Some(max_ms)) =
+        (transcript.rtt, summary.min_ms, summary.max_ms)
+    {
+        let agrees =
+            |parsed: f64, reported: f64| (parsed - reported).abs() <= (reported * 0.05).max(1.0);
+        if !agrees(min_ms, rtt.min_ms) || !agrees(max_ms, rtt.max_ms) {
+            return Err(anyhow!(
+                "ping reported min/max {:.3}/{:.3} ms but parsed replies give {min_ms:.3}/{max_ms:.3} ms",
+                rtt.min_ms,
+                rtt.max_ms
+            ));
+        }
+    }
+
+    Ok(())
+}
+
+// `ping -q` and some wrappers print only the totals, so the distribution is
+// limited to what the summary lines carry.
+fn summarize_ping_totals(sent: u32, received: u32, rtt: PingTranscriptRtt) -> PingSummary {
+    PingSummary {
+        engine: PingEngine::Subprocess,
+        sent,
+        received,
+        packet_loss_pct: packet_loss_pct(sent, received),
+        min_ms: Some(rtt.min_ms),
+        avg_ms: Some(rtt.avg_ms),
+        median_ms: None,
+        p95_ms: None,
+        max_ms: Some(rtt.max_ms),
+        stddev_ms: rtt.stddev_ms,
+        jitter_ms: None,
+        samples_ms: Vec::new(),
+        sequence: None,
+    }
+}
+
+#[derive(Debug, Default)]
+struct PingTranscript {
+    replies: Vec<(Option<u32>, f64)>,
+    first_sequence: Option<u32>,
+    transmitted: Option<u32>,
+    received: Option<u32>,
+    rtt: Option<PingTranscriptRtt>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct PingTranscriptRtt {
+    min_ms: f64,
+    avg_ms: f64,
+    max_ms: f64,
+    stddev_ms: Option<f64>,
+}
+
+// Dialects differ in wording and language (iputils, BSD/macOS, BusyBox,
+// Windows, and their translations) but share a shape: replies carry a
+// `<word>=<number> ms` or `<word><<number>ms` token, the totals line has two
+// leading counts and a percentage, and the RTT line lists min/avg/max.
+fn parse_ping_transcript(output: &str) -> PingTranscript {
+    let mut transcript = PingTranscript::default();
+
+    let mut error_replies = 0;
+    let mut counts_error_replies = false;
+    for line in output.lines() {
+        if let Some(rtt_ms) = extract_reply_time_ms(line) {
+            transcript.replies.push((extract_sequence(line), rtt_ms));
+            // BusyBox prints `seq=` and numbers echoes from 0.
+            if !line.contains("icmp_seq=") && line.contains("seq=") {
+                transcript.first_sequence.get_or_insert(0);
+            }
+        } else if let Some((transmitted, received)) = extract_packet_counts(line) {
+            transcript.transmitted = Some(transmitted);
+            transcript.received = Some(received);
+            // Only Windows writes its totals as `Sent = 4, Received = 4`.
+            counts_error_replies = line.contains('=');
+        } else if let Some(rtt) = extract_rtt_summary(line) {
+            transcript.rtt = Some(rtt);
+        } else if let Some(first_sequence) = extract_first_sequence(line) {
+            transcript.first_sequence.get_or_insert(first_sequence);
+        } else if is_error_reply(line) {
+            error_replies += 1;
+        }
+    }
+
+    // Windows counts "Destination host unreachable" and similar answers from
+    // routers as received, but no echo came back.
+    if counts_error_replies {
+        transcript.received = transcript
+            .received
+            .map(|received| received.saturating_sub(error_replies));
+    }
+
+    transcript
+}
+
+// iputils headers read `PING host (address) 56(84) bytes of data.` and number
+// echoes from 1; BSD, macOS, and BusyBox read `PING host (address): 56 data
+// bytes` and number them from 0.
+fn extract_first_sequence(line: &str) -> Option<u32> {
+    let header = line.trim_start().strip_prefix("PING ")?;
+    Some(if header.contains("): ") { 0 } else { 1 })
+}
+
+// `Reply from 192.0.2.1: Destination host unreachable.` and its translations.
+fn is_error_reply(line: &str) -> bool {
+    let Some((source, message)) = line.trim().split_once(": ") else {
+        return false;
+    };
+    source
+        .rsplit(' ')
+        .next()
+        .is_some_and(|address| address.parse::<IpAddr>().is_ok())
+        && !message.trim().is_empty()
+        && !message.contains('=')
+}
+
+fn extract_sequence(line: &str) -> Option<u32> {
+    let start = line.find("seq=")? + "seq=".len();
+    line[start..]
+        .split(|character: char| !character.is_ascii_digit())
+        .next()?
+        .parse()
+        .ok()
+}
+
+fn extract_reply_time_ms(line: &str) -> Option<f64> {
+    line.char_indices().find_map(|(index, character)| {
+        if !matches!(character, '=' | '<') {
+            return None;
+        }
+        let follows_word = line[..index]
+            .chars()
+            .next_back()
+            .is_some_and(char::is_alphabetic);
+        if !follows_word {
+            return None;
+        }
+
+        let rest = &line[index + 1..];
+        let number_end = rest
+            .find(|character: char| !is_decimal_character(character))
+            .unwrap_or(rest.len());
+        if !rest[number_end..].trim_start().starts_with("ms") {
+            return None;
+        }
+
+        parse_decimal(&rest[..number_end])
+    })
+}
+
+fn extract_packet_counts(line: &str) -> Option<(u32, u32)> {
+    if !line.contains('%') {
+        return None;
+    }
+
+    let mut counts = line.split(',').map(|field| {
+        field
+            .split(|character: char| !character.is_ascii_digit())
+            .find(|digits| !digits.is_empty())?
+            .parse::<u32>()
+            .ok()
+    });
+    Some((counts.next()??, counts.next()??))
+}
+
+fn extract_rtt_summary(line: &str) -> Option<PingTranscriptRtt> {
+    // iputils, BSD, and BusyBox: `rtt min/avg/max/mdev = 9.8/10.1/10.4/0.2 ms`.
+    if let Some((_, values)) = line.rsplit_once('=') {
+        let values = values
+            .trim()
+            .trim_end_matches("ms")
+            .trim()
+            .split('/')
+            .map(parse_decimal)
+            .collect::<Option<Vec<_>>>()
+            .filter(|values| matches!(values.len(), 3 | 4));
+        if let Some(values) = values {
+            return Some(PingTranscriptRtt {
+                min_ms: values[0],
+                avg_ms: values[1],
+                max_ms: values[2],
+                stddev_ms: values.get(3).copied(),
+            });
+        }
+    }
+
+    // Windows: `Minimum = 9ms, Maximum = 11ms, Average = 10ms`.
+    let values = line
+        .split(',')
+        .map(|field| {
+            let (_, value) = field.split_once('=')?;
+            parse_decimal(value.trim().strip_suffix("ms")?.trim())
+        })
+        .collect::<Option<Vec<_>>>()
+        .filter(|values| values.len() == 3)?;
+    Some(PingTranscriptRtt {
+        min_ms: values[0],
+        avg_ms: values[2],
+        max_ms: values[1],
+        stddev_ms: None,
+    })
+}
+
+fn is_decimal_character(character: char) -> bool {
+    character.is_ascii_digit() || character == '.' || character == ','
+}
+
+// Localized iputils builds may print a decimal comma.
+fn parse_decimal(text: &str) -> Option<f64> {
+    let text = text.trim_end_matches(['.', ',']);
+    if text.is_empty() || !text.chars().all(is_decimal_character) {
+        return None;
+    }
+    text.replace(',', ".").parse().ok()
+}
+
+fn summarize_ping_replies(
+    engine: PingEngine,
+    sent: u32,
+    replies: &[PingReply],
+) -> Result<PingSummary> {
+    let mut seen = HashSet::with_capacity(replies.len());
+    let mut highest_sequence = None;
+    let mut duplicates = 0_u32;
+    let mut out_of_order = 0_u32;
+    let mut samples_ms = Vec::with_capacity(replies.len());
+    let mut arrivals_ms = Vec::with_capacity(replies.len());
+
+    for reply in replies.iter().filter(|reply| reply.sequence < sent) {
+        if !seen.insert(reply.sequence) {
+            duplicates += 1;
+            continue;
+        }
+
+        if highest_sequence.is_some_and(|highest| reply.sequence < highest) {
+            out_of_order += 1;
+        }
+        highest_sequence = highest_sequence.max(Some(reply.sequence));
+        samples_ms.push(reply.rtt_ms);
+        arrivals_ms.push(reply.arrival_ms);
+    }
+
+    let mut loss_bursts = Vec::new();
+    let mut burst = 0_u32;
+    for sequence in 0..sent {
+        if seen.contains(&sequence) {
+            if burst > 0 {
+                loss_bursts.push(burst);
+            }
+            burst = 0;
+        } else {
+            burst += 1;
+        }
+    }
+    if burst > 0 {
+        loss_bursts.push(burst);
+    }
+
+    arrivals_ms.sort_by(f64::total_cmp);
+    let longest_gap_ms = arrivals_ms
+        .windows(2)
+        .map(|pair| pair[1] - pair[0])
+        .max_by(f64::total_cmp);
+
+    let mut summary = summarize_ping(engine, sent, samples_ms)?;
+    summary.sequence = Some(PingSequenceSummary {
+        duplicates,
+        out_of_order,
+        max_loss_burst: loss_bursts.iter().copied().max().unwrap_or(0),
+        loss_bursts,
+        longest_gap_ms,
+    });
+    Ok(summary)
+}
+
+fn summarize_ping(engine: PingEngine, sent: u32, samples_ms: Vec<f64>) -> Result<PingSummary> {
+    let received = samples_ms.len() as u32;
+    let packet_loss_pct = packet_loss_pct(sent, received);
+
+    let (min_ms, avg_ms, median_ms, p95_ms, max_ms, stddev_ms, jitter_ms) = if samples_ms.is_empty()
+    {
+        (None, None, None, None, None, None, None)
+    } else {
+        let stats = calculate_stats(&samples_ms).context("failed to derive ping stats")?;
+        let jitter_ms = calculate_jitter_ms(&samples_ms);
 
         (
             Some(stats.min),
@@ -725,6 +2788,7 @@ fn parse_ping_output(output: &str, sent: u32) -> Result<PingSummary> {
     };
 
     Ok(PingSummary {
+        engine,
         sent,
         received,
         packet_loss_pct,
@@ -736,9 +2800,18 @@ fn parse_ping_output(output: &str, sent: u32) -> Result<PingSummary> {
         stddev_ms,
         jitter_ms,
         samples_ms,
+        sequence: None,
     })
 }
 
+fn packet_loss_pct(sent: u32, received: u32) -> f64 {
+    if sent == 0 {
+        0.0
+    } else {
+        (sent.saturating_sub(received) as f64 / sent as f64) * 100.0
+    }
+}
+
 fn calculate_jitter_ms(samples_ms: &[f64]) -> Option<f64> {
     if samples_ms.len() < 2 {
         return None;
@@ -752,34 +2825,11 @@ fn calculate_jitter_ms(samples_ms: &[f64]) -> Option<f64> {
     Some(jitter_total / (samples_ms.len() - 1) as f64)
 }
 
-fn extract_time_ms(line: &str) -> Option<f64> {
-    extract_time_value(line, "time=").or_else(|| extract_time_value(line, "time<"))
-}
-
-fn extract_time_value(line: &str, marker: &str) -> Option<f64> {
-    let start = line.find(marker)?;
-    let mut digits = String::new();
-
-    for character in line[start + marker.len()..].chars() {
-        if character.is_ascii_digit() || character == '.' {
-            digits.push(character);
-        } else if !digits.is_empty() {
-            break;
-        } else if character == ' ' {
-            continue;
-        } else {
-            return None;
-        }
-    }
-
-    if digits.is_empty() {
-        return None;
-    }
-
-    digits.parse::<f64>().ok()
-}
-
-fn measure_dns(target: &str) -> Result<DnsSummary> {
+async fn measure_dns(
+    target: &str,
+    query: Option<&DnsQueryConfig>,
+    samples: u32,
+) -> Result<DnsSummary> {
     let start = Instant::now();
     let addresses: Vec<IpAddr> = (target, 0)
         .to_socket_addrs()
@@ -792,23 +2842,514 @@ fn measure_dns(target: &str) -> Result<DnsSummary> {
         return Err(anyhow!("no IP addresses resolved for {target}"));
     }
 
+    let mut resolvers = Vec::new();
+    if let Some(query) = query {
+        for server in &query.servers {
+            resolvers.push(measure_dns_resolver(target, server, query.record_type, samples).await);
+        }
+    }
+
     Ok(DnsSummary {
         resolution_time_ms: duration_to_ms(elapsed),
         addresses: addresses
             .into_iter()
             .map(|address| address.to_string())
             .collect(),
+        resolvers,
+    })
+}
+
+async fn measure_dns_resolver(
+    name: &str,
+    server: &DnsServer,
+    record_type: DnsRecordType,
+    samples: u32,
+) -> DnsResolverSummary {
+    let query_count = samples.max(1);
+    let transport = server.transport();
+    let mut samples_ms = Vec::with_capacity(query_count as usize);
+    let mut rcodes = BTreeMap::new();
+    let mut answers = Vec::new();
+    let mut min_ttl_s: Option<u32> = None;
+    let mut tcp_fallbacks = 0;
+    let mut last_error = None;
+
+    let (mut session, handshake) = match DnsSession::connect(server, DNS_QUERY_TIMEOUT).await {
+        Ok((session, handshake)) => (Some(session), handshake),
+        Err(error) => {
+            last_error = Some(error.to_string());
+            (None, None)
+        }
+    };
+
+    for query in 0..query_count {
+        let Some(session) = session.as_mut() else {
+            break;
+        };
+        let started = Instant::now();
+        match session.query(name, record_type, DNS_QUERY_TIMEOUT).await {
+            Ok(exchange) => {
+                samples_ms.push(duration_to_ms(exchange.rtt));
+                *rcodes
+                    .entry(dns::rcode_name(exchange.response.rcode))
+                    .or_insert(0) += 1;
+                if exchange.transport != transport {
+                    tcp_fallbacks += 1;
+                }
+                for answer in exchange.response.answers {
+                    min_ttl_s = Some(min_ttl_s.map_or(answer.ttl, |ttl| ttl.min(answer.ttl)));
+                    if !answers.contains(&answer.data) {
+                        answers.push(answer.data);
+                    }
+                }
+            }
+            Err(error) => last_error = Some(error.to_string()),
+        }
+
+        if query + 1 < query_count {
+            tokio::time::sleep(DNS_QUERY_INTERVAL.saturating_sub(started.elapsed())).await;
+        }
+    }
+
+    let responses = samples_ms.len() as u32;
+
+    DnsResolverSummary {
+        server: server.to_string(),
+        record_type,
+        transport,
+        queries: query_count,
+        responses,
+        loss_pct: query_count.saturating_sub(responses) as f64 / query_count as f64 * 100.0,
+        rcodes,
+        min_ttl_s,
+        answers,
+        tcp_fallbacks,
+        handshake: handshake.map(|handshake| DnsHandshakeSummary {
+            remote_address: handshake.remote_address.to_string(),
+            connect_ms: duration_to_ms(handshake.connect),
+            tls_ms: handshake.tls.map(duration_to_ms),
+        }),
+        rtt: calculate_stats(&samples_ms),
+        samples_ms,
+        last_error,
+    }
+}
+
+async fn measure_tcp_connect(
+    target: &str,
+    family: Option<AddressFamily>,
+    port: u16,
+    samples: u32,
+) -> Result<TcpSummary> {
+    let sample_count = samples.max(1);
+    let address = SocketAddr::new(resolve_target_address(target, family)?, port);
+    let mut samples_ms = Vec::with_capacity(sample_count as usize);
+    let mut last_error = None;
+
+    for sample in 0..sample_count {
+        let started = Instant::now();
+        match tokio::time::timeout(TCP_CONNECT_TIMEOUT, TcpStream::connect(address)).await {
+            Ok(Ok(_stream)) => samples_ms.push(duration_to_ms(started.elapsed())),
+            Ok(Err(error)) => last_error = Some(format!("failed to connect to {address}: {error}")),
+            Err(_) => last_error = Some(format!("connect to {address} timed out")),
+        }
+
+        if sample + 1 < sample_count {
+            tokio::time::sleep(TCP_CONNECT_INTERVAL.saturating_sub(started.elapsed())).await;
+        }
+    }
+
+    let connected = samples_ms.len() as u32;
+
+    Ok(TcpSummary {
+        address: address.ip().to_string(),
+        port,
+        sent: sample_count,
+        connected,
+        loss_pct: (sample_count - connected) as f64 / sample_count as f64 * 100.0,
+        connect: calculate_stats(&samples_ms),
+        jitter_ms: calculate_jitter_ms(&samples_ms),
+        samples_ms,
+        last_error,
+    })
+}
+
+async fn measure_udp_echo(
+    target: &str,
+    family: Option<AddressFamily>,
+    config: UdpEchoConfig,
+) -> Result<UdpSummary> {
+    let address = SocketAddr::new(resolve_target_address(target, family)?, config.port);
+    let arrivals = udp::run_echo_session(
+        address,
+        udp::EchoSessionConfig {
+            packets: config.packets,
+            interval: Duration::from_millis(config.interval_ms),
+            payload_bytes: config.payload_bytes,
+            timeout: UDP_ECHO_TIMEOUT,
+        },
+    )
+    .await?;
+
+    Ok(summarize_udp_echo(address, config, &arrivals))
+}
+
+fn summarize_udp_echo(
+    address: SocketAddr,
+    config: UdpEchoConfig,
+    arrivals: &[udp::EchoArrival],
+) -> UdpSummary {
+    let mut seen = HashSet::with_capacity(arrivals.len());
+    let mut highest_sequence = None;
+    let mut duplicates = 0_u32;
+    let mut out_of_order = 0_u32;
+    let mut samples_ms = Vec::with_capacity(arrivals.len());
+
+    for arrival in arrivals {
+        if !seen.insert(arrival.sequence) {
+            duplicates += 1;
+            continue;
+        }
+
+        if highest_sequence.is_some_and(|highest| arrival.sequence < highest) {
+            out_of_order += 1;
+        }
+        highest_sequence = highest_sequence.max(Some(arrival.sequence));
+        samples_ms.push(duration_to_ms(
+            arrival.received_offset.saturating_sub(arrival.sent_offset),
+        ));
+    }
+
+    let sent = config.packets.max(1);
+    let received = samples_ms.len() as u32;
+
+    UdpSummary {
+        address: address.ip().to_string(),
+        port: address.port(),
+        interval_ms: config.interval_ms,
+        payload_bytes: config.payload_bytes,
+        sent,
+        received,
+        loss_pct: sent.saturating_sub(received) as f64 / sent as f64 * 100.0,
+        duplicates,
+        out_of_order,
+        rtt: calculate_stats(&samples_ms),
+        jitter_ms: calculate_interarrival_jitter_ms(&samples_ms),
+        samples_ms,
+    }
+}
+
+// RFC 3550 section 6.4.1: J += (|D(i-1, i)| - J) / 16, where D is the change
+// in transit time between consecutive packets in arrival order.
+fn calculate_interarrival_jitter_ms(transit_ms: &[f64]) -> Option<f64> {
+    if transit_ms.len() < 2 {
+        return None;
+    }
+
+    Some(transit_ms.windows(2).fold(0.0, |jitter, pair| {
+        jitter + ((pair[1] - pair[0]).abs() - jitter) / RFC3550_JITTER_GAIN
+    }))
+}
+
+#[derive(Debug, Clone, Copy)]
+struct TraceProbe {
+    ttl: u8,
+    reply: Option<(IpAddr, Duration)>,
+    reached: bool,
+}
+
+async fn measure_trace(
+    target: &str,
+    family: Option<AddressFamily>,
+    config: TraceConfig,
+) -> Result<TraceSummary> {
+    // Hop replies are collected with blocking reads that wait up to a second
+    // per cycle, so keep them off the runtime threads.
+    let target = target.to_string();
+    tokio::task::spawn_blocking(move || measure_trace_blocking(&target, family, config))
+        .await
+        .context("path probe task failed")?
+}
+
+fn measure_trace_blocking(
+    target: &str,
+    family: Option<AddressFamily>,
+    config: TraceConfig,
+) -> Result<TraceSummary> {
+    let address = resolve_target_address(target, family)?;
+    let socket = icmp::TraceSocket::open(address)?;
+    let mut last_ttl = config.max_hops.max(1);
+    let mut probes = Vec::new();
+
+    for cycle in 0..config.cycles.max(1) {
+        let mut in_flight = HashMap::new();
+        for ttl in 1..=last_ttl {
+            // resolve_probe_options keeps cycles * max_hops within u16.
+            let sequence = (cycle * u32::from(config.max_hops) + u32::from(ttl)) as u16;
+            socket.send(ttl, sequence, DEFAULT_PING_PAYLOAD_BYTES)?;
+            in_flight.insert(sequence, (ttl, Instant::now()));
+        }
+
+        let deadline = Instant::now() + TRACE_TIMEOUT;
+        let mut replies = HashMap::new();
+        while replies.len() < in_flight.len() {
+            let Some(reply) = socket.receive(deadline)? else {
+                break;
+            };
+            if let Some((ttl, sent_at)) = in_flight.get(&reply.sequence) {
+                replies
+                    .entry(*ttl)
+                    .or_insert((reply.responder, sent_at.elapsed(), reply.reached));
+            }
+        }
+
+        for ttl in 1..=last_ttl {
+            let reply = replies.get(&ttl);
+            probes.push(TraceProbe {
+                ttl,
+                reply: reply.map(|(responder, rtt, _)| (*responder, *rtt)),
+                reached: reply.is_some_and(|(_, _, reached)| *reached),
+            });
+        }
+
+        // Later cycles only need to probe up to the destination.
+        if let Some(destination_ttl) = destination_ttl(&probes) {
+            last_ttl = destination_ttl;
+        }
+    }
+
+    Ok(summarize_trace(address, config, &probes))
+}
+
+fn destination_ttl(probes: &[TraceProbe]) -> Option<u8> {
+    probes
+        .iter()
+        .filter(|probe| probe.reached)
+        .map(|probe| probe.ttl)
+        .min()
+}
+
+fn summarize_trace(address: IpAddr, config: TraceConfig, probes: &[TraceProbe]) -> TraceSummary {
+    let destination = destination_ttl(probes);
+    let last_ttl = destination
+        .or_else(|| {
+            probes
+                .iter()
+                .filter(|probe| probe.reply.is_some())
+                .map(|probe| probe.ttl)
+                .max()
+        })
+        .unwrap_or(0);
+
+    let hops = (1..=last_ttl)
+        .map(|ttl| {
+            let hop_probes = probes.iter().filter(|probe| probe.ttl == ttl);
+            let mut addresses = Vec::new();
+            let mut samples_ms = Vec::new();
+            let mut sent = 0_u32;
+            for probe in hop_probes {
+                sent += 1;
+                if let Some((responder, rtt)) = probe.reply {
+                    let responder = responder.to_string();
+                    if !addresses.contains(&responder) {
+                        addresses.push(responder);
+                    }
+                    samples_ms.push(duration_to_ms(rtt));
+                }
+            }
+            let received = samples_ms.len() as u32;
+
+            TraceHop {
+                ttl,
+                addresses,
+                sent,
+                received,
+                loss_pct: sent.saturating_sub(received) as f64 / sent.max(1) as f64 * 100.0,
+                rtt: calculate_stats(&samples_ms),
+                samples_ms,
+            }
+        })
+        .collect();
+
+    TraceSummary {
+        address: address.to_string(),
+        max_hops: config.max_hops,
+        cycles: config.cycles,
+        reached: destination.is_some(),
+        hops,
+    }
+}
+
+async fn measure_pmtu(
+    target: &str,
+    family: Option<AddressFamily>,
+    config: PmtuConfig,
+) -> Result<PmtuSummary> {
+    // Each probe size waits on blocking reads with a per-attempt timeout, so
+    // keep the search off the runtime threads.
+    let target = target.to_string();
+    tokio::task::spawn_blocking(move || measure_pmtu_blocking(&target, family, config))
+        .await
+        .context("path MTU task failed")?
+}
+
+fn measure_pmtu_blocking(
+    target: &str,
+    family: Option<AddressFamily>,
+    config: PmtuConfig,
+) -> Result<PmtuSummary> {
+    let address = resolve_target_address(target, family)?;
+    let socket = icmp::EchoSocket::open(address)?;
+    socket.set_dont_fragment()?;
+    // IP plus ICMP echo header bytes on top of the payload.
+    let (min_bytes, overhead) = match address {
+        IpAddr::V4(_) => (PMTU_MIN_BYTES_V4, 28),
+        IpAddr::V6(_) => (PMTU_MIN_BYTES_V6, 48),
+    };
+    // The CLI already rejects sizes below the IPv4 minimum.
+    if config.max_bytes < min_bytes {
+        anyhow::bail!(
+            "--pmtu-max-bytes {} is below the minimum MTU of {min_bytes} bytes for {address}",
+            config.max_bytes
+        );
+    }
+    let max_bytes = config.max_bytes;
+
+    let mut sequence = 0_u16;
+    let mut probes = 0_u32;
+    let search = search_pmtu(min_bytes, max_bytes, |size| {
+        for _ in 0..PMTU_ATTEMPTS {
+            probes += 1;
+            sequence = sequence.wrapping_add(1);
+            match socket.echo_unfragmented(sequence, (size - overhead) as usize, PMTU_TIMEOUT)? {
+                icmp::UnfragmentedEcho::Lost => continue,
+                outcome => return Ok(outcome),
+            }
+        }
+        Ok(icmp::UnfragmentedEcho::Lost)
+    })?;
+    // Silence only means a blackhole when a router's rejection would have
+    // been seen.
+    let (blackhole_from_bytes, no_reply_from_bytes) = if icmp::REPORTS_FRAGMENTATION_NEEDED {
+        (search.no_reply_from_bytes, None)
+    } else {
+        (None, search.no_reply_from_bytes)
+    };
+
+    Ok(PmtuSummary {
+        address: address.to_string(),
+        mtu_bytes: search.mtu_bytes,
+        min_probed_bytes: min_bytes,
+        max_probed_bytes: max_bytes,
+        blackhole_from_bytes,
+        signalled_limit_bytes: search.signalled_limit_bytes,
+        no_reply_from_bytes,
+        local_limit_bytes: search.local_limit_bytes,
+        probes,
+    })
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct PmtuSearch {
+    mtu_bytes: u32,
+    no_reply_from_bytes: Option<u32>,
+    signalled_limit_bytes: Option<u32>,
+    local_limit_bytes: Option<u32>,
+}
+
+fn search_pmtu(
+    min_bytes: u32,
+    max_bytes: u32,
+    mut probe: impl FnMut(u32) -> Result<icmp::UnfragmentedEcho>,
+) -> Result<PmtuSearch> {
+    let mut first_failure = match probe(max_bytes)? {
+        icmp::UnfragmentedEcho::Reply(_) => {
+            return Ok(PmtuSearch {
+                mtu_bytes: max_bytes,
+                no_reply_from_bytes: None,
+                signalled_limit_bytes: None,
+                local_limit_bytes: None,
+            })
+        }
+        failure => (max_bytes, failure),
+    };
+    if !matches!(probe(min_bytes)?, icmp::UnfragmentedEcho::Reply(_)) {
+        anyhow::bail!("target did not answer unfragmented {min_bytes} byte packets");
+    }
+
+    let mut fits = min_bytes;
+    while first_failure.0 - fits > 1 {
+        let size = fits + (first_failure.0 - fits) / 2;
+        match probe(size)? {
+            icmp::UnfragmentedEcho::Reply(_) => fits = size,
+            failure => first_failure = (size, failure),
+        }
+    }
+
+    let (size, failure) = first_failure;
+    Ok(PmtuSearch {
+        mtu_bytes: fits,
+        no_reply_from_bytes: (failure == icmp::UnfragmentedEcho::Lost).then_some(size),
+        signalled_limit_bytes: (failure == icmp::UnfragmentedEcho::FragmentationNeeded)
+            .then_some(size),
+        local_limit_bytes: (failure == icmp::UnfragmentedEcho::TooBig).then_some(size),
     })
 }
 
-async fn measure_bandwidth(config: &BandwidthConfig) -> Result<BandwidthSummary> {
+async fn measure_http_timing(
+    url: &str,
+    family: Option<AddressFamily>,
+    timeout: Duration,
+) -> Result<HttpTiming> {
+    let timings = http_timing::timed_get(url, &[], family, None, timeout)
+        .await
+        .map_err(|error| anyhow!("{url}: {error:#}"))?;
+    Ok(http_timing_summary(url, &timings))
+}
+
+fn http_timing_summary(url: &str, timings: &http_timing::PhaseTimings) -> HttpTiming {
+    HttpTiming {
+        url: url.to_string(),
+        remote_address: timings.remote_address.to_string(),
+        status: timings.status,
+        dns_ms: duration_to_ms(timings.dns),
+        connect_ms: duration_to_ms(timings.connect),
+        tls_ms: timings.tls.map(duration_to_ms),
+        ttfb_ms: duration_to_ms(timings.time_to_first_byte),
+        transfer_ms: duration_to_ms(timings.transfer),
+        total_ms: duration_to_ms(timings.total),
+        bytes: timings.body_bytes,
+    }
+}
+
+async fn measure_bandwidth(target: &str, config: &BandwidthConfig) -> Result<BandwidthSummary> {
+    let plan = TransferPlan {
+        window: config.transfer_duration_ms.map(Duration::from_millis),
+        ramp_up: Duration::from_millis(config.ramp_up_exclusion_ms),
+    };
     let client = Client::builder()
-        .timeout(Duration::from_secs(config.transfer_timeout_seconds))
+        .timeout(
+            Duration::from_secs(config.transfer_timeout_seconds) + plan.window.unwrap_or_default(),
+        )
         .user_agent(concat!("pantheon-probe/", env!("CARGO_PKG_VERSION")))
+        .local_address(config.family.map(AddressFamily::unspecified))
         .build()
         .context("failed to build HTTP client for bandwidth probe")?;
 
     let selected = select_bandwidth_endpoint(&client, config).await?;
+    let download_sizing = download_sizing(&selected);
+    let sampler = config
+        .loaded_latency
+        .then(|| loaded_latency_sampler(target, config, &selected));
+    let mut idle_latency = PhaseSamples::default();
+    if let Some(sampler) = &sampler {
+        idle_latency = sampler
+            .sample_while(tokio::time::sleep(IDLE_LATENCY_WINDOW))
+            .await
+            .1;
+    }
+    let mut download_latency = PhaseSamples::default();
+    let mut upload_latency = PhaseSamples::default();
     let bandwidth_started = Instant::now();
     let runs = config.runs.max(1);
     let download_streams = config.download_streams.max(1);
@@ -818,84 +3359,136 @@ async fn measure_bandwidth(config: &BandwidthConfig) -> Result<BandwidthSummary>
     let mut warmup_download_runs = Vec::with_capacity(config.warmup_runs as usize);
     let mut warmup_upload_runs = Vec::with_capacity(config.warmup_runs as usize);
 
-    for _ in 0..config.warmup_runs {
-        warmup_download_runs.push(
+    let mut failed_attempts = Vec::new();
+
+    // Failed runs are recorded and skipped; only too few measured successes
+    // fail the section.
+    for run in 1..=config.warmup_runs {
+        warmup_download_runs.extend(
             download_sample_with_retries(
                 &client,
                 &selected.endpoint.download_url,
-                config.download_size_bytes,
+                download_object_bytes(config, config.download_size_bytes),
+                download_sizing,
                 download_streams,
                 config.transfer_attempts,
+                plan,
             )
             .await
-            .with_context(|| {
-                format!(
-                    "download warmup failed for {}",
-                    selected.endpoint.download_url
-                )
-            })?,
+            .keep_sample(
+                &mut failed_attempts,
+                TransferPhase::Warmup,
+                TransferDirection::Download,
+                run,
+            ),
         );
-        warmup_upload_runs.push(
+        warmup_upload_runs.extend(
             upload_sample_with_retries(
                 &client,
                 &selected.endpoint.upload_url,
                 config.upload_size_bytes,
                 upload_streams,
                 config.transfer_attempts,
+                plan,
             )
             .await
-            .with_context(|| {
-                format!("upload warmup failed for {}", selected.endpoint.upload_url)
-            })?,
+            .keep_sample(
+                &mut failed_attempts,
+                TransferPhase::Warmup,
+                TransferDirection::Upload,
+                run,
+            ),
         );
     }
 
-    let calibrated_download_size_bytes = calibrate_transfer_size(
-        &warmup_download_runs,
-        config.download_size_bytes,
-        config.max_download_size_bytes,
-        config.target_transfer_duration_ms,
-    );
-    let calibrated_upload_size_bytes = calibrate_transfer_size(
-        &warmup_upload_runs,
-        config.upload_size_bytes,
-        config.max_upload_size_bytes,
-        config.target_transfer_duration_ms,
-    );
+    // A fixed time window replaces size calibration entirely.
+    let (calibrated_download_size_bytes, calibrated_upload_size_bytes) = match plan.window {
+        Some(_) => (config.download_size_bytes, config.upload_size_bytes),
+        None => (
+            calibrate_transfer_size(
+                &warmup_download_runs,
+                config.download_size_bytes,
+                config.max_download_size_bytes,
+                config.target_transfer_duration_ms,
+            ),
+            calibrate_transfer_size(
+                &warmup_upload_runs,
+                config.upload_size_bytes,
+                config.max_upload_size_bytes,
+                config.target_transfer_duration_ms,
+            ),
+        ),
+    };
+    let download_object_size_bytes = download_object_bytes(config, calibrated_download_size_bytes);
 
-    for _ in 0..runs {
-        download_runs.push(
+    for run in 1..=runs {
+        let (download_sample, latency) = sample_latency_while(
+            sampler.as_ref(),
             download_sample_with_retries(
                 &client,
                 &selected.endpoint.download_url,
-                calibrated_download_size_bytes,
+                download_object_size_bytes,
+                download_sizing,
                 download_streams,
                 config.transfer_attempts,
-            )
-            .await
-            .with_context(|| {
-                format!(
-                    "download throughput check failed for {}",
-                    selected.endpoint.download_url
-                )
-            })?,
-        );
-        upload_runs.push(
+                plan,
+            ),
+        )
+        .await;
+        download_runs.extend(download_sample.keep_sample(
+            &mut failed_attempts,
+            TransferPhase::Measured,
+            TransferDirection::Download,
+            run,
+        ));
+        download_latency.extend(latency);
+
+        let (upload_sample, latency) = sample_latency_while(
+            sampler.as_ref(),
             upload_sample_with_retries(
                 &client,
                 &selected.endpoint.upload_url,
                 calibrated_upload_size_bytes,
                 upload_streams,
                 config.transfer_attempts,
-            )
-            .await
-            .with_context(|| {
-                format!(
-                    "upload throughput check failed for {}",
-                    selected.endpoint.upload_url
-                )
-            })?,
-        );
+                plan,
+            ),
+        )
+        .await;
+        upload_runs.extend(upload_sample.keep_sample(
+            &mut failed_attempts,
+            TransferPhase::Measured,
+            TransferDirection::Upload,
+            run,
+        ));
+        upload_latency.extend(latency);
+    }
+
+    for (direction, succeeded, url) in [
+        (
+            TransferDirection::Download,
+            download_runs.len(),
+            &selected.endpoint.download_url,
+        ),
+        (
+            TransferDirection::Upload,
+            upload_runs.len(),
+            &selected.endpoint.upload_url,
+        ),
+    ] {
+        if succeeded < config.min_successful_runs as usize {
+            let last_error = failed_attempts
+                .iter()
+                .rev()
+                .find(|failure| {
+                    failure.phase == TransferPhase::Measured && failure.direction == direction
+                })
+                .map_or("no error recorded", |failure| failure.error.as_str());
+            return Err(anyhow!(
+                "{direction} throughput check failed for {url}: {succeeded} of {runs} measured runs succeeded, {} required: {last_error}",
+                config.min_successful_runs
+            ));
+        }
     }
 
     let download_values: Vec<f64> = download_runs.iter().map(|sample| sample.mbps).collect();
@@ -904,6 +3497,15 @@ async fn measure_bandwidth(config: &BandwidthConfig) -> Result<BandwidthSummary>
     let upload = calculate_stats(&upload_values).context("failed to derive upload stats")?;
     let download_bytes = download_runs.iter().map(|sample| sample.bytes).sum();
     let upload_bytes = upload_runs.iter().map(|sample| sample.bytes).sum();
+    let average_mbps = |runs: &[TransferSample]| {
+        let values = runs
+            .iter()
+            .map(|sample| sample.average_mbps)
+            .collect::<Vec<_>>();
+        calculate_stats(&values).map(|stats| stats.median)
+    };
+    let download_average_mbps = average_mbps(&download_runs);
+    let upload_average_mbps = average_mbps(&upload_runs);
 
     Ok(BandwidthSummary {
         provider: config.provider.to_string(),
@@ -923,23 +3525,340 @@ async fn measure_bandwidth(config: &BandwidthConfig) -> Result<BandwidthSummary>
         calibrated_download_size_bytes,
         calibrated_upload_size_bytes,
         target_transfer_duration_ms: config.target_transfer_duration_ms,
+        transfer_duration_ms: config.transfer_duration_ms,
+        ramp_up_exclusion_ms: config.ramp_up_exclusion_ms,
+        download_average_mbps,
+        upload_average_mbps,
         bandwidth_elapsed_ms: duration_to_ms(bandwidth_started.elapsed()),
         warmup_download_runs,
         warmup_upload_runs,
         runs,
         warmup_runs: config.warmup_runs,
+        min_successful_runs: config.min_successful_runs,
+        failed_attempts,
         transfer_attempts: config.transfer_attempts,
         transfer_timeout_seconds: config.transfer_timeout_seconds,
         download_streams,
         upload_streams,
         download_url: sized_download_url(
             &selected.endpoint.download_url,
-            calibrated_download_size_bytes,
+            download_object_size_bytes,
         ),
         upload_url: selected.endpoint.upload_url,
+        download_sizing: Some(download_sizing),
+        upload_payload: UploadPayload::Random,
+        loaded_latency: sampler.map(|sampler| {
+            summarize_loaded_latency(&sampler, idle_latency, download_latency, upload_latency)
+        }),
+    })
+}
+
+fn loaded_latency_sampler(
+    target: &str,
+    config: &BandwidthConfig,
+    selected: &SelectedEndpoint,
+) -> LatencySampler {
+    // Reuse the address the health check connected to instead of resolving again.
+    let endpoint = selected
+        .candidates
+        .iter()
+        .find(|candidate| candidate.name == selected.endpoint.name)
+        .and_then(|candidate| candidate.remote_address.as_deref())
+        .and_then(|address| address.parse::<SocketAddr>().ok());
+
+    LatencySampler {
+        target: resolve_target_address(target, config.family).ok(),
+        endpoint,
+    }
+}
+
+async fn sample_latency_while<F: std::future::Future>(
+    sampler: Option<&LatencySampler>,
+    future: F,
+) -> (F::Output, PhaseSamples) {
+    match sampler {
+        Some(sampler) => sampler.sample_while(future).await,
+        None => (future.await, PhaseSamples::default()),
+    }
+}
+
+fn summarize_loaded_latency(
+    sampler: &LatencySampler,
+    idle: PhaseSamples,
+    download: PhaseSamples,
+    upload: PhaseSamples,
+) -> LoadedLatencySummary {
+    let idle = summarize_latency_phase(idle);
+    let download = summarize_latency_phase(download);
+    let upload = summarize_latency_phase(upload);
+    let download_increase_ms = latency_increase_ms(&idle, &download);
+    let upload_increase_ms = latency_increase_ms(&idle, &upload);
+    let grade = match (download_increase_ms, upload_increase_ms) {
+        (None, None) => None,
+        (download, upload) => Some(bufferbloat_grade(
+            download.unwrap_or_default().max(upload.unwrap_or_default()),
+        )),
+    };
+
+    LoadedLatencySummary {
+        target_address: sampler.target.map(|address| address.to_string()),
+        endpoint_address: sampler.endpoint.map(|address| address.to_string()),
+        idle,
+        download,
+        upload,
+        download_increase_ms,
+        upload_increase_ms,
+        grade,
+    }
+}
+
+fn summarize_latency_phase(samples: PhaseSamples) -> LoadedLatencyPhase {
+    LoadedLatencyPhase {
+        target: samples
+            .target
+            .map(|series| summarize_latency_series(&series)),
+        endpoint: samples
+            .endpoint
+            .map(|series| summarize_latency_series(&series)),
+    }
+}
+
+fn summarize_latency_series(series: &[Option<Duration>]) -> LatencySeries {
+    let samples_ms = series
+        .iter()
+        .flatten()
+        .map(|rtt| duration_to_ms(*rtt))
+        .collect::<Vec<_>>();
+    let sent = series.len() as u32;
+    let received = samples_ms.len() as u32;
+
+    LatencySeries {
+        sent,
+        received,
+        loss_pct: sent.saturating_sub(received) as f64 / sent.max(1) as f64 * 100.0,
+        rtt: calculate_stats(&samples_ms),
+        samples_ms,
+    }
+}
+
+fn latency_increase_ms(idle: &LoadedLatencyPhase, loaded: &LoadedLatencyPhase) -> Option<f64> {
+    let median = |series: &Option<LatencySeries>| {
+        series
+            .as_ref()
+            .and_then(|series| series.rtt.as_ref())
+            .map(|stats| stats.median)
+    };
+
+    // Prefer the target, which is what ping reports on, and fall back to the endpoint.
+    [
+        (median(&idle.target), median(&loaded.target)),
+        (median(&idle.endpoint), median(&loaded.endpoint)),
+    ]
+    .into_iter()
+    .find_map(|pair| match pair {
+        (Some(idle), Some(loaded)) => Some((loaded - idle).max(0.0)),
+        _ => None,
+    })
+}
+
+fn bufferbloat_grade(increase_ms: f64) -> BufferbloatGrade {
+    match increase_ms {
+        value if value < 5.0 => BufferbloatGrade::APlus,
+        value if value < 30.0 => BufferbloatGrade::A,
+        value if value < 60.0 => BufferbloatGrade::B,
+        value if value < 200.0 => BufferbloatGrade::C,
+        value if value < 400.0 => BufferbloatGrade::D,
+        _ => BufferbloatGrade::F,
+    }
+}
+
+async fn measure_responsiveness(
+    bandwidth: &BandwidthSummary,
+    family: Option<AddressFamily>,
+    timeout: Duration,
+    ramp_up: Duration,
+    duration: Duration,
+) -> Result<ResponsivenessSummary> {
+    let client = Client::builder()
+        .timeout(timeout)
+        .user_agent(concat!("pantheon-probe/", env!("CARGO_PKG_VERSION")))
+        .local_address(family.map(AddressFamily::unspecified))
+        .build()
+        .context("failed to build HTTP client for responsiveness probe")?;
+    let probe_client = client.clone();
+    let probe_url = sized_download_url(&bandwidth.download_url, 1);
+    let upload_payload_size = split_size(
+        bandwidth.calibrated_upload_size_bytes,
+        bandwidth.upload_streams,
+    );
+
+    let mut load = JoinSet::new();
+    for _ in 0..bandwidth.download_streams {
+        let client = client.clone();
+        let url = bandwidth.download_url.clone();
+        load.spawn(async move {
+            loop {
+                if download_bytes(&client, &url, None, &TransferProgress::default())
+                    .await
+                    .is_err()
+                {
+                    tokio::time::sleep(retry_delay(1)).await;
+                }
+            }
+        });
+    }
+    for _ in 0..bandwidth.upload_streams {
+        let client = client.clone();
+        let url = bandwidth.upload_url.clone();
+        load.spawn(async move {
+            loop {
+                if upload_bytes(
+                    &client,
+                    &url,
+                    upload_payload_size,
+                    &TransferProgress::default(),
+                )
+                .await
+                .is_err()
+                {
+                    tokio::time::sleep(retry_delay(1)).await;
+                }
+            }
+        });
+    }
+
+    tokio::time::sleep(ramp_up).await;
+    // Open the reused connection before probing so its handshake is not counted.
+    let _ = reused_connection_probe(&probe_client, &probe_url).await;
+
+    let started = Instant::now();
+    let deadline = started + duration;
+    let foreign = async {
+        let mut timings = Vec::new();
+        let mut failures = 0_u32;
+        while Instant::now() < deadline {
+            let probe_started = Instant::now();
+            match http_timing::timed_get(
+                &probe_url,
+                &[("Range", "bytes=0-0")],
+                family,
+                Some(1),
+                timeout,
+            )
+            .await
+            {
+                Ok(timing) if (200..300).contains(&timing.status) => timings.push(timing),
+                _ => failures += 1,
+            }
+            tokio::time::sleep(
+                RESPONSIVENESS_PROBE_INTERVAL.saturating_sub(probe_started.elapsed()),
+            )
+            .await;
+        }
+        (timings, failures)
+    };
+    let reused = async {
+        let mut samples_ms = Vec::new();
+        let mut failures = 0_u32;
+        while Instant::now() < deadline {
+            let probe_started = Instant::now();
+            match reused_connection_probe(&probe_client, &probe_url).await {
+                Ok(elapsed) => samples_ms.push(duration_to_ms(elapsed)),
+                Err(_) => failures += 1,
+            }
+            tokio::time::sleep(
+                RESPONSIVENESS_PROBE_INTERVAL.saturating_sub(probe_started.elapsed()),
+            )
+            .await;
+        }
+        (samples_ms, failures)
+    };
+    let ((foreign, foreign_failures), (reused_ms, reused_failures)) = tokio::join!(foreign, reused);
+    let elapsed = started.elapsed();
+    load.abort_all();
+
+    let tcp_ms = foreign
+        .iter()
+        .map(|timing| duration_to_ms(timing.connect))
+        .collect::<Vec<_>>();
+    let tls_ms = foreign
+        .iter()
+        .filter_map(|timing| timing.tls.map(duration_to_ms))
+        .collect::<Vec<_>>();
+    let foreign_http_ms = foreign
+        .iter()
+        .map(|timing| duration_to_ms(timing.time_to_first_byte + timing.transfer))
+        .collect::<Vec<_>>();
+    let rpm =
+        responsiveness_rpm(&tcp_ms, &tls_ms, &foreign_http_ms, &reused_ms).ok_or_else(|| {
+            anyhow!(
+                "no successful responsiveness probes against {probe_url} ({} failed)",
+                foreign_failures + reused_failures
+            )
+        })?;
+
+    Ok(ResponsivenessSummary {
+        rpm,
+        endpoint: bandwidth.endpoint.clone(),
+        probe_url,
+        duration_ms: duration_to_ms(elapsed),
+        download_streams: bandwidth.download_streams,
+        upload_streams: bandwidth.upload_streams,
+        foreign_probes: foreign.len() as u32,
+        reused_probes: reused_ms.len() as u32,
+        failed_probes: foreign_failures + reused_failures,
+        tcp_connect: calculate_stats(&tcp_ms),
+        tls_handshake: calculate_stats(&tls_ms),
+        foreign_http: calculate_stats(&foreign_http_ms),
+        reused_http: calculate_stats(&reused_ms),
     })
 }
 
+async fn reused_connection_probe(client: &Client, url: &str) -> Result<Duration> {
+    let started = Instant::now();
+    client
+        .get(url)
+        .header("Range", "bytes=0-0")
+        .send()
+        .await
+        .with_context(|| format!("failed to GET {url}"))?
+        .error_for_status()
+        .with_context(|| format!("probe endpoint returned an error for {url}"))?
+        .bytes()
+        .await
+        .context("failed to read probe response")?;
+
+    Ok(started.elapsed())
+}
+
+fn responsiveness_rpm(
+    tcp_ms: &[f64],
+    tls_ms: &[f64],
+    foreign_http_ms: &[f64],
+    reused_http_ms: &[f64],
+) -> Option<f64> {
+    // Weighting from the IETF "Responsiveness under Working Conditions" draft; plain
+    // HTTP endpoints have no TLS round trip to count.
+    let foreign_ms = trimmed_mean(tcp_ms)?
+        + trimmed_mean(tls_ms).unwrap_or_default()
+        + trimmed_mean(foreign_http_ms)?;
+    let round_trip_ms = foreign_ms / 6.0 + trimmed_mean(reused_http_ms)? / 2.0;
+
+    (round_trip_ms > 0.0).then(|| 60_000.0 / round_trip_ms)
+}
+
+fn trimmed_mean(values: &[f64]) -> Option<f64> {
+    if values.is_empty() {
+        return None;
+    }
+
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    let kept = ((sorted.len() as f64 * RESPONSIVENESS_TRIM_FRACTION).ceil() as usize).max(1);
+
+    Some(sorted[..kept].iter().sum::<f64>() / kept as f64)
+}
+
 struct SelectedEndpoint {
     endpoint: BandwidthEndpoint,
     latency_ms: Option<f64>,
@@ -974,7 +3893,15 @@ async fn select_bandwidth_endpoint(
 
     let mut health = Vec::with_capacity(candidates.len());
     for endpoint in &candidates {
-        health.push(check_endpoint_health(client, endpoint).await);
+        health.push(
+            check_endpoint_health(
+                client,
+                endpoint,
+                config.family,
+                Duration::from_secs(config.transfer_timeout_seconds),
+            )
+            .await,
+        );
     }
 
     let selected = health
@@ -1050,6 +3977,15 @@ fn calibrate_transfer_size(
     target_bytes.clamp(minimum_bytes, maximum_bytes)
 }
 
+// In duration mode each stream re-requests the largest allowed object so it
+// rarely runs dry before the window closes.
+fn download_object_bytes(config: &BandwidthConfig, size_bytes: usize) -> usize {
+    match config.transfer_duration_ms {
+        Some(_) => config.max_download_size_bytes,
+        None => size_bytes,
+    }
+}
+
 fn median_sample_mbps(samples: &[TransferSample]) -> Option<f64> {
     let values = samples
         .iter()
@@ -1060,6 +3996,25 @@ fn median_sample_mbps(samples: &[TransferSample]) -> Option<f64> {
     calculate_stats(&values).map(|stats| stats.median)
 }
 
+// Providers that size downloads with a `bytes=` parameter get it rewritten;
+// static files rely on `Range`, which the health check already exercised.
+fn download_sizing(selected: &SelectedEndpoint) -> DownloadSizing {
+    if selected.endpoint.download_url.contains("bytes=") {
+        return DownloadSizing::QueryParameter;
+    }
+    let honours_range = selected
+        .candidates
+        .iter()
+        .find(|candidate| candidate.name == selected.endpoint.name)
+        .is_some_and(|candidate| candidate.status == Some(206));
+
+    if honours_range {
+        DownloadSizing::RangeRequest
+    } else {
+        DownloadSizing::StreamLimit
+    }
+}
+
 fn sized_download_url(download_url: &str, target_bytes: usize) -> String {
     let Some(bytes_position) = download_url.find("bytes=") else {
         return download_url.to_string();
@@ -1078,37 +4033,65 @@ fn sized_download_url(download_url: &str, target_bytes: usize) -> String {
     )
 }
 
-async fn check_endpoint_health(client: &Client, endpoint: &BandwidthEndpoint) -> EndpointHealth {
+// The reqwest check decides health, following redirects and proxies the same
+// way the transfers will, and only waits for the headers so a server that
+// ignores `Range` never gets to send the whole object. The phase breakdown
+// is best-effort on top.
+async fn check_endpoint_health(
+    client: &Client,
+    endpoint: &BandwidthEndpoint,
+    family: Option<AddressFamily>,
+    timeout: Duration,
+) -> EndpointHealth {
     let started = Instant::now();
     let result = client
         .get(&endpoint.download_url)
-        .header(RANGE, "bytes=0-1023")
+        .header(reqwest::header::RANGE, "bytes=0-1023")
         .send()
         .await
         .with_context(|| format!("failed to reach {}", endpoint.download_url))
         .and_then(|response| {
-            response.error_for_status().map(|_| ()).with_context(|| {
-                format!(
-                    "health check returned an error for {}",
-                    endpoint.download_url
-                )
-            })
+            response
+                .error_for_status()
+                .map(|response| (response.status().as_u16(), response.remote_addr()))
+                .with_context(|| {
+                    format!(
+                        "health check returned an error for {}",
+                        endpoint.download_url
+                    )
+                })
         });
+    let latency_ms = duration_to_ms(started.elapsed());
 
     match result {
-        Ok(()) => EndpointHealth {
+        Ok((status, remote_address)) => EndpointHealth {
             name: endpoint.name.clone(),
             download_url: endpoint.download_url.clone(),
             upload_url: endpoint.upload_url.clone(),
-            latency_ms: Some(duration_to_ms(started.elapsed())),
+            latency_ms: Some(latency_ms),
             error: None,
+            status: Some(status),
+            remote_address: remote_address.map(|address| address.to_string()),
+            timing: http_timing::timed_get(
+                &endpoint.download_url,
+                &[("Range", "bytes=0-1023")],
+                family,
+                Some(1_024),
+                timeout,
+            )
+            .await
+            .ok()
+            .map(|timings| http_timing_summary(&endpoint.download_url, &timings)),
         },
         Err(error) => EndpointHealth {
             name: endpoint.name.clone(),
             download_url: endpoint.download_url.clone(),
             upload_url: endpoint.upload_url.clone(),
             latency_ms: None,
-            error: Some(error.to_string()),
+            error: Some(format!("{error:#}")),
+            status: None,
+            remote_address: None,
+            timing: None,
         },
     }
 }
@@ -1117,15 +4100,17 @@ async fn download_sample_with_retries(
     client: &Client,
     url: &str,
     target_bytes: usize,
+    sizing: DownloadSizing,
     streams: u32,
     attempts: u32,
-) -> Result<TransferSample> {
-    let mut last_error = None;
+    plan: TransferPlan,
+) -> RetriedTransfer {
+    let mut errors = Vec::new();
     for attempt in 1..=attempts.max(1) {
-        match download_sample(client, url, target_bytes, streams).await {
-            Ok(sample) => return Ok(sample),
+        match download_sample(client, url, target_bytes, sizing, streams, plan).await {
+            Ok(sample) => return RetriedTransfer::succeeded(sample, errors),
             Err(error) => {
-                last_error = Some(error);
+                errors.push(error);
                 if attempt < attempts {
                     tokio::time::sleep(retry_delay(attempt)).await;
                 }
@@ -1133,7 +4118,7 @@ async fn download_sample_with_retries(
         }
     }
 
-    Err(last_error.expect("at least one transfer attempt runs"))
+    RetriedTransfer::failed(errors)
 }
 
 async fn upload_sample_with_retries(
@@ -1142,13 +4127,14 @@ async fn upload_sample_with_retries(
     upload_size_bytes: usize,
     streams: u32,
     attempts: u32,
-) -> Result<TransferSample> {
-    let mut last_error = None;
+    plan: TransferPlan,
+) -> RetriedTransfer {
+    let mut errors = Vec::new();
     for attempt in 1..=attempts.max(1) {
-        match upload_sample(client, upload_url, upload_size_bytes, streams).await {
-            Ok(sample) => return Ok(sample),
+        match upload_sample(client, upload_url, upload_size_bytes, streams, plan).await {
+            Ok(sample) => return RetriedTransfer::succeeded(sample, errors),
             Err(error) => {
-                last_error = Some(error);
+                errors.push(error);
                 if attempt < attempts {
                     tokio::time::sleep(retry_delay(attempt)).await;
                 }
@@ -1156,43 +4142,91 @@ async fn upload_sample_with_retries(
         }
     }
 
-    Err(last_error.expect("at least one transfer attempt runs"))
+    RetriedTransfer::failed(errors)
+}
+
+// Errors are in attempt order, so the first one belongs to attempt 1.
+struct RetriedTransfer {
+    sample: Option<TransferSample>,
+    errors: Vec<anyhow::Error>,
+}
+
+impl RetriedTransfer {
+    fn succeeded(sample: TransferSample, errors: Vec<anyhow::Error>) -> Self {
+        Self {
+            sample: Some(sample),
+            errors,
+        }
+    }
+
+    fn failed(errors: Vec<anyhow::Error>) -> Self {
+        Self {
+            sample: None,
+            errors,
+        }
+    }
+
+    fn keep_sample(
+        self,
+        failures: &mut Vec<TransferFailure>,
+        phase: TransferPhase,
+        direction: TransferDirection,
+        run: u32,
+    ) -> Option<TransferSample> {
+        failures.extend(
+            self.errors
+                .into_iter()
+                .zip(1..)
+                .map(|(error, attempt)| TransferFailure {
+                    phase,
+                    direction,
+                    run,
+                    attempt,
+                    error: format!("{error:#}"),
+                }),
+        );
+        self.sample
+    }
 }
 
 fn retry_delay(attempt: u32) -> Duration {
     Duration::from_millis(150 * u64::from(attempt))
 }
 
+// With a window, streams transfer until the deadline and throughput comes from
+// the bytes counted inside it, the way iperf-style tools measure.
 async fn download_sample(
     client: &Client,
     url: &str,
     target_bytes: usize,
+    sizing: DownloadSizing,
     streams: u32,
+    plan: TransferPlan,
 ) -> Result<TransferSample> {
-    let started = Instant::now();
+    let meter = TransferMeter::start(plan);
+    let deadline = meter.deadline();
     let mut tasks = JoinSet::new();
     let download_url = sized_download_url(url, target_bytes);
+    let limit = (sizing != DownloadSizing::QueryParameter).then_some(target_bytes);
 
     for _ in 0..streams {
         let client = client.clone();
         let url = download_url.clone();
-        tasks.spawn(async move { download_bytes(&client, &url).await });
-    }
-
-    let mut total_bytes = 0_u64;
-    while let Some(result) = tasks.join_next().await {
-        total_bytes += result.context("download worker failed to join")??;
+        let progress = meter.progress.clone();
+        tasks.spawn(async move {
+            match deadline {
+                Some(deadline) => {
+                    download_bytes_until(&client, &url, limit, deadline, &progress).await
+                }
+                None => download_bytes(&client, &url, limit, &progress).await,
+            }
+        });
     }
 
-    let elapsed = started.elapsed();
+    let (result, series) = meter.record(join_transfer(tasks, "download")).await;
+    result?;
 
-    Ok(TransferSample {
-        target_bytes,
-        mbps: bytes_to_mbps(total_bytes, elapsed),
-        bytes: total_bytes,
-        elapsed_ms: duration_to_ms(elapsed),
-        streams,
-    })
+    Ok(meter.finish(target_bytes, streams, series))
 }
 
 async fn upload_sample(
@@ -1200,68 +4234,361 @@ async fn upload_sample(
     upload_url: &str,
     upload_size_bytes: usize,
     streams: u32,
+    plan: TransferPlan,
 ) -> Result<TransferSample> {
-    let started = Instant::now();
+    let meter = TransferMeter::start(plan);
+    let deadline = meter.deadline();
     let mut tasks = JoinSet::new();
     let stream_payload_size = split_size(upload_size_bytes, streams);
 
     for _ in 0..streams {
         let client = client.clone();
         let upload_url = upload_url.to_string();
-        tasks.spawn(async move { upload_bytes(&client, &upload_url, stream_payload_size).await });
+        let progress = meter.progress.clone();
+        tasks.spawn(async move {
+            match deadline {
+                Some(deadline) => {
+                    upload_bytes_until(&client, &upload_url, deadline, &progress).await
+                }
+                None => upload_bytes(&client, &upload_url, stream_payload_size, &progress).await,
+            }
+        });
     }
 
-    let mut total_bytes = 0_u64;
+    let (result, series) = meter.record(join_transfer(tasks, "upload")).await;
+    result?;
+
+    let target_bytes = match deadline {
+        Some(_) => 0,
+        None => upload_size_bytes,
+    };
+    Ok(meter.finish(target_bytes, streams, series))
+}
+
+async fn join_transfer(mut tasks: JoinSet<Result<()>>, direction: &str) -> Result<()> {
     while let Some(result) = tasks.join_next().await {
-        total_bytes += result.context("upload worker failed to join")??;
+        result.with_context(|| format!("{direction} worker failed to join"))??;
     }
 
-    let elapsed = started.elapsed();
+    Ok(())
+}
 
-    Ok(TransferSample {
-        target_bytes: upload_size_bytes,
-        mbps: bytes_to_mbps(total_bytes, elapsed),
-        bytes: total_bytes,
-        elapsed_ms: duration_to_ms(elapsed),
-        streams,
-    })
+#[derive(Debug, Clone, Copy, Default)]
+struct TransferPlan {
+    window: Option<Duration>,
+    ramp_up: Duration,
 }
 
-async fn download_bytes(client: &Client, url: &str) -> Result<u64> {
-    let mut response = client
-        .get(url)
+// Cumulative bytes moved by every stream of one transfer.
+#[derive(Debug, Clone, Default)]
+struct TransferProgress(Arc<AtomicU64>);
+
+impl TransferProgress {
+    fn add(&self, bytes: usize) {
+        self.0.fetch_add(bytes as u64, Ordering::Relaxed);
+    }
+
+    fn bytes(&self) -> u64 {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+struct TransferMeter {
+    plan: TransferPlan,
+    started: Instant,
+    progress: TransferProgress,
+}
+
+impl TransferMeter {
+    fn start(plan: TransferPlan) -> Self {
+        Self {
+            plan,
+            started: Instant::now(),
+            progress: TransferProgress::default(),
+        }
+    }
+
+    fn deadline(&self) -> Option<Instant> {
+        self.plan.window.map(|window| self.started + window)
+    }
+
+    async fn record<F: std::future::Future>(
+        &self,
+        transfer: F,
+    ) -> (F::Output, Vec<ThroughputPoint>) {
+        let mut series = Vec::new();
+        let mut ticker = tokio::time::interval_at(
+            (self.started + THROUGHPUT_SAMPLE_INTERVAL).into(),
+            THROUGHPUT_SAMPLE_INTERVAL,
+        );
+        ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        tokio::pin!(transfer);
+
+        loop {
+            tokio::select! {
+                output = &mut transfer => return (output, series),
+                _ = ticker.tick() => series.push(ThroughputPoint {
+                    elapsed_ms: duration_to_ms(self.started.elapsed()),
+                    bytes: self.progress.bytes(),
+                }),
+            }
+        }
+    }
+
+    fn finish(
+        &self,
+        target_bytes: usize,
+        streams: u32,
+        mut series: Vec<ThroughputPoint>,
+    ) -> TransferSample {
+        let elapsed = self.started.elapsed();
+        let measured = self
+            .plan
+            .window
+            .map_or(elapsed, |window| elapsed.min(window));
+        let measured_ms = duration_to_ms(measured);
+        let bytes = self.progress.bytes();
+        // Ticks that land after a window closes only repeat its final count.
+        series.retain(|point| point.elapsed_ms < measured_ms);
+        series.push(ThroughputPoint {
+            elapsed_ms: measured_ms,
+            bytes,
+        });
+        let average_mbps = bytes_to_mbps(bytes, measured);
+        let steady_mbps = steady_state_mbps(&series, duration_to_ms(self.plan.ramp_up));
+
+        TransferSample {
+            target_bytes,
+            mbps: steady_mbps.unwrap_or(average_mbps),
+            average_mbps,
+            bytes,
+            elapsed_ms: duration_to_ms(elapsed),
+            streams,
+            window_ms: self.plan.window.map(|_| measured_ms),
+            series,
+            ramp_up_fallback: !self.plan.ramp_up.is_zero() && steady_mbps.is_none(),
+        }
+    }
+}
+
+// Runs that left too little after the cutoff report the whole-transfer
+// average as their headline rate.
+fn format_ramp_up_fallbacks(download: &[TransferSample], upload: &[TransferSample]) -> String {
+    let fallbacks = download
+        .iter()
+        .chain(upload)
+        .filter(|sample| sample.ramp_up_fallback)
+        .count();
+    match fallbacks {
+        0 => String::new(),
+        count => format!(
+            " (too short after the cutoff in {count} of {} runs, whole-transfer average used)",
+            download.len() + upload.len()
+        ),
+    }
+}
+
+// Throughput from the first sample at or past the ramp-up cutoff to the end of
+// the transfer. Transfers that leave less than half their duration or too few
+// samples after the cutoff fall back to the whole-transfer average.
+fn steady_state_mbps(series: &[ThroughputPoint], ramp_up_ms: f64) -> Option<f64> {
+    if ramp_up_ms <= 0.0 {
+        return None;
+    }
+
+    let start_index = series
+        .iter()
+        .position(|point| point.elapsed_ms >= ramp_up_ms)?;
+    let start = &series[start_index];
+    let end = series.last()?;
+    let span_ms = end.elapsed_ms - start.elapsed_ms;
+    let long_enough = span_ms > 0.0
+        && span_ms >= end.elapsed_ms * STEADY_STATE_MIN_SHARE
+        && series.len() - start_index >= STEADY_STATE_MIN_POINTS;
+
+    long_enough.then(|| end.bytes.saturating_sub(start.bytes) as f64 * 8.0 / span_ms / 1_000.0)
+}
+
+// A limit asks for just that many bytes with `Range` and stops reading there
+// if the server sends the whole file anyway.
+async fn download_bytes(
+    client: &Client,
+    url: &str,
+    limit: Option<usize>,
+    progress: &TransferProgress,
+) -> Result<()> {
+    let mut response = download_request(client, url, limit)
         .send()
         .await
         .with_context(|| format!("failed to GET {url}"))?
         .error_for_status()
         .with_context(|| format!("download endpoint returned an error for {url}"))?;
-    let mut total_bytes = 0_u64;
+    let mut remaining = limit.unwrap_or(usize::MAX);
 
     while let Some(chunk) = response
         .chunk()
         .await
         .context("failed to stream download body")?
     {
-        total_bytes += chunk.len() as u64;
+        let counted = chunk.len().min(remaining);
+        progress.add(counted);
+        remaining -= counted;
+        if remaining == 0 {
+            break;
+        }
+    }
+
+    Ok(())
+}
+
+// Keeps re-requesting the object until the deadline, counting only the bytes
+// that arrive before it.
+async fn download_bytes_until(
+    client: &Client,
+    url: &str,
+    limit: Option<usize>,
+    deadline: Instant,
+    progress: &TransferProgress,
+) -> Result<()> {
+    let deadline = tokio::time::Instant::from_std(deadline);
+
+    while tokio::time::Instant::now() < deadline {
+        let Ok(response) =
+            tokio::time::timeout_at(deadline, download_request(client, url, limit).send()).await
+        else {
+            break;
+        };
+        let mut response = response
+            .with_context(|| format!("failed to GET {url}"))?
+            .error_for_status()
+            .with_context(|| format!("download endpoint returned an error for {url}"))?;
+        let mut remaining = limit.unwrap_or(usize::MAX);
+
+        while remaining > 0 {
+            match tokio::time::timeout_at(deadline, response.chunk()).await {
+                Ok(chunk) => match chunk.context("failed to stream download body")? {
+                    Some(chunk) => {
+                        let counted = chunk.len().min(remaining);
+                        progress.add(counted);
+                        remaining -= counted;
+                    }
+                    None => break,
+                },
+                Err(_) => return Ok(()),
+            }
+        }
     }
 
-    Ok(total_bytes)
+    Ok(())
+}
+
+fn download_request(client: &Client, url: &str, limit: Option<usize>) -> reqwest::RequestBuilder {
+    let request = client.get(url);
+    match limit {
+        Some(limit) => request.header(
+            reqwest::header::RANGE,
+            format!("bytes=0-{}", limit.max(1) - 1),
+        ),
+        None => request,
+    }
 }
 
-async fn upload_bytes(client: &Client, upload_url: &str, payload_size: usize) -> Result<u64> {
-    let payload = vec![b'x'; payload_size];
-    let payload_len = payload.len() as u64;
+async fn upload_bytes(
+    client: &Client,
+    upload_url: &str,
+    payload_size: usize,
+    progress: &TransferProgress,
+) -> Result<()> {
+    let chunks = stream::iter(upload_chunks().scan(payload_size, |remaining, chunk| {
+        let chunk = chunk.slice(..chunk.len().min(*remaining));
+        *remaining -= chunk.len();
+        (!chunk.is_empty()).then_some(chunk)
+    }));
 
     client
         .post(upload_url)
-        .body(payload)
+        .header(reqwest::header::CONTENT_LENGTH, payload_size)
+        .body(counted_body(chunks, progress))
         .send()
         .await
         .with_context(|| format!("failed to POST {upload_url}"))?
         .error_for_status()
         .with_context(|| format!("upload endpoint returned an error for {upload_url}"))?;
 
-    Ok(payload_len)
+    Ok(())
+}
+
+// The body stops yielding once the deadline passes. Bytes still queued in the
+// socket at that point count as sent, the same as an iperf sender.
+async fn upload_bytes_until(
+    client: &Client,
+    upload_url: &str,
+    deadline: Instant,
+    progress: &TransferProgress,
+) -> Result<()> {
+    while Instant::now() < deadline {
+        let chunks = stream::iter(upload_chunks())
+            .take_while(move |_| future::ready(Instant::now() < deadline));
+
+        client
+            .post(upload_url)
+            .body(counted_body(chunks, progress))
+            .send()
+            .await
+            .with_context(|| format!("failed to POST {upload_url}"))?
+            .error_for_status()
+            .with_context(|| format!("upload endpoint returned an error for {upload_url}"))?;
+    }
+
+    Ok(())
+}
+
+// Every upload slices the same block, so memory stays flat whatever the
+// payload size or stream count. Each body starts at a different chunk so
+// parallel streams do not carry identical bytes.
+fn upload_chunks() -> impl Iterator<Item = Bytes> + Send + Sync + 'static {
+    static NEXT_CHUNK: AtomicUsize = AtomicUsize::new(0);
+    let block = upload_block();
+    let chunks_per_block = UPLOAD_BLOCK_BYTES / UPLOAD_CHUNK_BYTES;
+    let first = NEXT_CHUNK.fetch_add(1, Ordering::Relaxed);
+
+    (first..).map(move |index| {
+        let start = index % chunks_per_block * UPLOAD_CHUNK_BYTES;
+        block.slice(start..start + UPLOAD_CHUNK_BYTES)
+    })
+}
+
+fn upload_block() -> Bytes {
+    static BLOCK: OnceLock<Bytes> = OnceLock::new();
+    BLOCK
+        .get_or_init(|| {
+            let seed = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|elapsed| elapsed.as_nanos() as u64)
+                .unwrap_or_default();
+            // xorshift64* is plenty for defeating compression and needs no crate.
+            let mut state = seed | 1;
+            let mut block = Vec::with_capacity(UPLOAD_BLOCK_BYTES);
+            while block.len() < UPLOAD_BLOCK_BYTES {
+                state ^= state >> 12;
+                state ^= state << 25;
+                state ^= state >> 27;
+                block.extend_from_slice(&state.wrapping_mul(0x2545_f491_4f6c_dd1d).to_le_bytes());
+            }
+            Bytes::from(block)
+        })
+        .clone()
+}
+
+fn counted_body<S>(chunks: S, progress: &TransferProgress) -> Body
+where
+    S: futures_util::Stream<Item = Bytes> + Send + Sync + 'static,
+{
+    let progress = progress.clone();
+    Body::wrap(StreamBody::new(chunks.map(move |chunk| {
+        progress.add(chunk.len());
+        Ok::<_, Infallible>(Frame::data(chunk))
+    })))
 }
 
 fn bytes_to_mbps(bytes: u64, elapsed: Duration) -> f64 {
@@ -1272,11 +4599,11 @@ fn bytes_to_mbps(bytes: u64, elapsed: Duration) -> f64 {
     (bytes as f64 * 8.0) / elapsed.as_secs_f64() / 1_000_000.0
 }
 
-fn duration_to_ms(duration: Duration) -> f64 {
+pub fn duration_to_ms(duration: Duration) -> f64 {
     duration.as_secs_f64() * 1_000.0
 }
 
-fn calculate_stats(values: &[f64]) -> Option<MetricStats> {
+pub fn calculate_stats(values: &[f64]) -> Option<MetricStats> {
     if values.is_empty() {
         return None;
     }
@@ -1304,74 +4631,593 @@ fn calculate_stats(values: &[f64]) -> Option<MetricStats> {
     })
 }
 
-fn percentile(sorted_values: &[f64], percentile: f64) -> f64 {
-    if sorted_values.len() == 1 {
-        return sorted_values[0];
+fn percentile(sorted_values: &[f64], percentile: f64) -> f64 {
+    if sorted_values.len() == 1 {
+        return sorted_values[0];
+    }
+
+    let rank = (percentile / 100.0) * (sorted_values.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+
+    if lower == upper {
+        return sorted_values[lower];
+    }
+
+    let weight = rank - lower as f64;
+    sorted_values[lower] * (1.0 - weight) + sorted_values[upper] * weight
+}
+
+fn split_size(total_size: usize, streams: u32) -> usize {
+    let streams = streams.max(1) as usize;
+    total_size.div_ceil(streams)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        bufferbloat_grade, format_address_pings, format_dns_resolver, measure_address_pings,
+        measure_bandwidth, measure_dns, measure_http_timing, measure_responsiveness,
+        responsiveness_rpm, summarize_loaded_latency, summarize_udp_echo, trimmed_mean, udp,
+        AddressPing, BufferbloatGrade, DnsQueryConfig, DnsRecordType, DnsServer, DnsTransport,
+        LatencySampler, PhaseSamples, PingConfig, PmtuSearch, ProbeOutcome,
+    };
+    use super::{
+        calculate_interarrival_jitter_ms, calculate_jitter_ms, calculate_stats,
+        calibrate_transfer_size, format_provider_catalog, measure_tcp_connect, parse_ping_output,
+        provider_catalog, resolve_probe_options, resolve_target_address, search_pmtu,
+        select_bandwidth_endpoint, sized_download_url, split_size, steady_state_mbps,
+        subprocess_ping_args, summarize_ping, summarize_trace, upload_block, upload_chunks,
+        AddressFamily, BandwidthConfig, BandwidthEndpoint, BandwidthProviderPreset, DownloadSizing,
+        MeasurementProfile, PingEngine, ProbeOverrides, ProbeReport, ThroughputPoint, TraceConfig,
+        TraceProbe, TransferDirection, TransferPhase, TransferSample, UdpEchoConfig,
+        CLOUDFLARE_UPLOAD_URL, UPLOAD_BLOCK_BYTES, UPLOAD_CHUNK_BYTES,
+    };
+    use crate::{dns, icmp, server};
+    use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::time::Duration;
+    use tokio::net::TcpListener;
+
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn passes_only_overridden_settings_to_system_ping() {
+        assert_eq!(subprocess_ping_args(5, PingConfig::default()), ["-c", "5"]);
+
+        let config = PingConfig {
+            payload_bytes: 1_400,
+            interval_ms: 200,
+            ttl: Some(12),
+            timeout_ms: 1_500,
+        };
+        assert_eq!(
+            subprocess_ping_args(5, config),
+            ["-c", "5", "-s", "1400", "-i", "0.2", "-W", "2", "-t", "12"]
+        );
+    }
+
+    #[test]
+    fn parses_unix_ping_output_into_structured_stats() {
+        let output = "\
+PING example.com (93.184.216.34): 56 data bytes
+64 bytes from 93.184.216.34: icmp_seq=0 ttl=56 time=24.1 ms
+64 bytes from 93.184.216.34: icmp_seq=1 ttl=56 time=21.4 ms
+64 bytes from 93.184.216.34: icmp_seq=2 ttl=56 time=23.0 ms
+";
+
+        let parsed =
+            parse_ping_output(output, 3, Duration::from_secs(1)).expect("ping output should parse");
+
+        assert_eq!(parsed.engine, PingEngine::Subprocess);
+        assert_eq!(parsed.sent, 3);
+        assert_eq!(parsed.received, 3);
+        assert!((parsed.packet_loss_pct - 0.0).abs() < f64::EPSILON);
+        assert_eq!(parsed.samples_ms.len(), 3);
+        assert_eq!(parsed.min_ms, Some(21.4));
+        assert_eq!(parsed.max_ms, Some(24.1));
+        assert!(parsed.avg_ms.expect("avg latency should exist") > 22.0);
+        assert_eq!(parsed.median_ms, Some(23.0));
+        assert!(parsed.p95_ms.expect("p95 latency should exist") > 23.0);
+        assert!(parsed.jitter_ms.expect("jitter should exist") > 2.0);
+    }
+
+    #[test]
+    fn parses_windows_ping_output_with_sub_millisecond_response() {
+        let output = "\
+Reply from 1.1.1.1: bytes=32 time<1ms TTL=59
+Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
+";
+
+        let parsed = parse_ping_output(output, 2, Duration::from_secs(1))
+            .expect("windows ping should parse");
+
+        assert_eq!(parsed.received, 2);
+        assert_eq!(parsed.min_ms, Some(1.0));
+        assert_eq!(parsed.max_ms, Some(2.0));
+        assert!(parsed.sequence.is_none());
+    }
+
+    #[test]
+    fn parses_iputils_ping_output_and_cross_checks_summary() {
+        let output = "\
+PING 1.1.1.1 (1.1.1.1) 56(84) bytes of data.
+64 bytes from 1.1.1.1: icmp_seq=1 ttl=57 time=10.1 ms
+64 bytes from 1.1.1.1: icmp_seq=2 ttl=57 time=10.4 ms
+64 bytes from 1.1.1.1: icmp_seq=4 ttl=57 time=9.8 ms
+
+--- 1.1.1.1 ping statistics ---
+4 packets transmitted, 3 received, 25% packet loss, time 3004ms
+rtt min/avg/max/mdev = 9.800/10.100/10.400/0.245 ms
+";
+
+        let parsed = parse_ping_output(output, 4, Duration::from_secs(1))
+            .expect("iputils ping should parse");
+
+        assert_eq!(parsed.sent, 4);
+        assert_eq!(parsed.received, 3);
+        assert!((parsed.packet_loss_pct - 25.0).abs() < f64::EPSILON);
+        assert_eq!(parsed.min_ms, Some(9.8));
+        assert_eq!(
+            parsed
+                .sequence
+                .expect("sequence should be tracked")
+                .loss_bursts,
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn parses_localized_iputils_ping_output() {
+        let output = "\
+PING 1.1.1.1 (1.1.1.1) 56(84) Bytes an Daten.
+64 Bytes von 1.1.1.1: icmp_seq=1 ttl=57 Zeit=10,1 ms
+64 Bytes von 1.1.1.1: icmp_seq=2 ttl=57 Zeit=11,3 ms
+
+--- 1.1.1.1 Ping-Statistiken ---
+2 Pakete übertragen, 2 empfangen, 0% Paketverlust, Zeit 1001ms
+rtt min/avg/max/mdev = 10,100/10,700/11,300/0,600 ms
+";
+
+        let parsed = parse_ping_output(output, 2, Duration::from_secs(1))
+            .expect("localized ping should parse");
+
+        assert_eq!(parsed.received, 2);
+        assert_eq!(parsed.samples_ms, vec![10.1, 11.3]);
+        assert!(parsed.sequence.is_some());
+    }
+
+    #[test]
+    fn parses_macos_ping_output() {
+        let output = "\
+PING example.com (93.184.216.34): 1400 data bytes
+1408 bytes from 93.184.216.34: icmp_seq=0 ttl=56 time=24.117 ms
+Request timeout for icmp_seq 1
+1408 bytes from 93.184.216.34: icmp_seq=2 ttl=56 time=23.002 ms
+
+--- example.com ping statistics ---
+3 packets transmitted, 2 packets received, 33.3% packet loss
+round-trip min/avg/max/stddev = 23.002/23.560/24.117/0.558 ms
+";
+
+        let parsed =
+            parse_ping_output(output, 3, Duration::from_secs(1)).expect("macOS ping should parse");
+
+        assert_eq!(parsed.received, 2);
+        assert_eq!(parsed.min_ms, Some(23.002));
+        assert_eq!(parsed.max_ms, Some(24.117));
+        assert_eq!(
+            parsed
+                .sequence
+                .expect("sequence should be tracked")
+                .loss_bursts,
+            vec![1]
+        );
+    }
+
+    #[test]
+    fn parses_busybox_ping_output() {
+        let output = "\
+PING 1.1.1.1 (1.1.1.1): 56 data bytes
+64 bytes from 1.1.1.1: seq=0 ttl=57 time=10.512 ms
+64 bytes from 1.1.1.1: seq=1 ttl=57 time=11.204 ms
+64 bytes from 1.1.1.1: seq=2 ttl=57 time=10.733 ms
+
+--- 1.1.1.1 ping statistics ---
+3 packets transmitted, 3 packets received, 0% packet loss
+round-trip min/avg/max = 10.512/10.816/11.204 ms
+";
+
+        let parsed = parse_ping_output(output, 3, Duration::from_secs(1))
+            .expect("BusyBox ping should parse");
+
+        assert_eq!(parsed.received, 3);
+        assert_eq!(parsed.samples_ms, vec![10.512, 11.204, 10.733]);
+        let sequence = parsed.sequence.expect("sequence should be tracked");
+        assert_eq!(sequence.out_of_order, 0);
+        assert!(sequence.loss_bursts.is_empty());
+    }
+
+    #[test]
+    fn parses_localized_windows_ping_output_with_total_loss() {
+        let replies = "\
+Ping wird ausgeführt für 1.1.1.1 mit 32 Bytes Daten:
+Antwort von 1.1.1.1: Bytes=32 Zeit=12ms TTL=57
+Antwort von 1.1.1.1: Bytes=32 Zeit<1ms TTL=57
+
+Ping-Statistik für 1.1.1.1:
+    Pakete: Gesendet = 2, Empfangen = 2, Verloren = 0
+    (0% Verlust),
+Ca. Zeitangaben in Millisek.:
+    Minimum = 0ms, Maximum = 12ms, Mittelwert = 6ms
+";
+        let timeouts = "\
+Pinging 10.255.255.1 with 32 bytes of data:
+Request timed out.
+Request timed out.
+
+Ping statistics for 10.255.255.1:
+    Packets: Sent = 2, Received = 0, Lost = 2 (100% loss),
+";
+
+        let parsed = parse_ping_output(replies, 2, Duration::from_secs(1))
+            .expect("localized windows ping should parse");
+        let lost = parse_ping_output(timeouts, 2, Duration::from_secs(1))
+            .expect("windows ping with total loss should parse");
+
+        assert_eq!(parsed.samples_ms, vec![12.0, 1.0]);
+        assert!(parsed.sequence.is_none());
+        assert_eq!(lost.received, 0);
+        assert!((lost.packet_loss_pct - 100.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn counts_windows_unreachable_replies_as_loss() {
+        let output = "\
+Pinging 192.0.2.55 with 32 bytes of data:
+Reply from 192.0.2.1: Destination host unreachable.
+Reply from 192.0.2.1: Destination host unreachable.
+Request timed out.
+
+Ping statistics for 192.0.2.55:
+    Packets: Sent = 3, Received = 2, Lost = 1 (33% loss),
+";
+
+        let parsed = parse_ping_output(output, 3, Duration::from_secs(1))
+            .expect("unreachable replies should parse");
+
+        assert_eq!(parsed.sent, 3);
+        assert_eq!(parsed.received, 0);
+        assert!((parsed.packet_loss_pct - 100.0).abs() < f64::EPSILON);
+    }
+
+    #[test]
+    fn numbers_ping_sequences_by_dialect_when_the_first_echo_is_lost() {
+        let busybox = "\
+64 bytes from 1.1.1.1: seq=2 ttl=57 time=10.5 ms
+64 bytes from 1.1.1.1: seq=3 ttl=57 time=10.7 ms
+";
+        let macos = "\
+PING example.com (93.184.216.34): 56 data bytes
+Request timeout for icmp_seq 0
+Request timeout for icmp_seq 1
+64 bytes from 93.184.216.34: icmp_seq=2 ttl=56 time=24.1 ms
+64 bytes from 93.184.216.34: icmp_seq=3 ttl=56 time=23.0 ms
+";
+        let iputils = "\
+PING 1.1.1.1 (1.1.1.1) 56(84) bytes of data.
+64 bytes from 1.1.1.1: icmp_seq=2 ttl=57 time=10.1 ms
+64 bytes from 1.1.1.1: icmp_seq=3 ttl=57 time=10.4 ms
+";
+
+        let loss_bursts = |output| {
+            parse_ping_output(output, 4, Duration::from_secs(1))
+                .expect("ping output should parse")
+                .sequence
+                .expect("sequence should be tracked")
+                .loss_bursts
+        };
+
+        assert_eq!(loss_bursts(busybox), vec![2]);
+        assert_eq!(loss_bursts(macos), vec![2]);
+        assert_eq!(loss_bursts(iputils), vec![1, 1]);
+    }
+
+    #[test]
+    fn falls_back_to_ping_summary_when_replies_are_quiet() {
+        let output = "\
+PING 1.1.1.1 (1.1.1.1) 56(84) bytes of data.
+
+--- 1.1.1.1 ping statistics ---
+5 packets transmitted, 4 received, 20% packet loss, time 4005ms
+rtt min/avg/max/mdev = 9.812/10.204/10.977/0.412 ms
+";
+
+        let parsed = parse_ping_output(output, 5, Duration::from_secs(1))
+            .expect("summary-only ping should parse");
+
+        assert_eq!(parsed.received, 4);
+        assert!((parsed.packet_loss_pct - 20.0).abs() < f64::EPSILON);
+        assert_eq!(parsed.min_ms, Some(9.812));
+        assert_eq!(parsed.avg_ms, Some(10.204));
+        assert_eq!(parsed.stddev_ms, Some(0.412));
+        assert!(parsed.samples_ms.is_empty());
+        assert!(parsed.median_ms.is_none());
+    }
+
+    #[test]
+    fn rejects_unknown_or_inconsistent_ping_output() {
+        let unknown = parse_ping_output(
+            "ping: unknown host nowhere.invalid\n",
+            3,
+            Duration::from_secs(1),
+        )
+        .expect_err("unknown output should be rejected");
+        let inconsistent = parse_ping_output(
+            "\
+64 bytes from 1.1.1.1: icmp_seq=1 ttl=57 time=10.1 ms
+3 packets transmitted, 3 received, 0% packet loss, time 2003ms
+",
+            3,
+            Duration::from_secs(1),
+        )
+        .expect_err("missed reply lines should be rejected");
+
+        assert_eq!(
+            unknown.to_string(),
+            "unrecognized ping output: ping: unknown host nowhere.invalid"
+        );
+        assert!(inconsistent
+            .to_string()
+            .contains("ping reported 3 replies but 1 reply lines were recognized"));
+    }
+
+    #[test]
+    fn tracks_ping_sequence_duplicates_reordering_and_loss_bursts() {
+        let output = "\
+PING 1.1.1.1 (1.1.1.1) 56(84) bytes of data.
+64 bytes from 1.1.1.1: icmp_seq=1 ttl=57 time=10.1 ms
+64 bytes from 1.1.1.1: icmp_seq=2 ttl=57 time=10.4 ms
+64 bytes from 1.1.1.1: icmp_seq=2 ttl=57 time=10.9 ms (DUP!)
+64 bytes from 1.1.1.1: icmp_seq=6 ttl=57 time=12.0 ms
+64 bytes from 1.1.1.1: icmp_seq=5 ttl=57 time=1100 ms
+64 bytes from 1.1.1.1: icmp_seq=8 ttl=57 time=11.0 ms
+";
+
+        let parsed =
+            parse_ping_output(output, 8, Duration::from_secs(1)).expect("ping output should parse");
+        let sequence = parsed.sequence.expect("sequence numbers should be tracked");
+
+        assert_eq!(parsed.received, 5);
+        assert_eq!(parsed.samples_ms, vec![10.1, 10.4, 12.0, 1100.0, 11.0]);
+        assert_eq!(sequence.duplicates, 1);
+        assert_eq!(sequence.out_of_order, 1);
+        assert_eq!(sequence.loss_bursts, vec![2, 1]);
+        assert_eq!(sequence.max_loss_burst, 2);
+        let longest_gap_ms = sequence.longest_gap_ms.expect("gap should exist");
+        assert!((longest_gap_ms - 4_001.6).abs() < 1e-6);
+    }
+
+    #[test]
+    fn summarizes_native_ping_samples_with_loss() {
+        let summary = summarize_ping(PingEngine::IcmpDatagram, 4, vec![10.0, 12.0, 11.0])
+            .expect("native samples should summarize");
+
+        assert_eq!(summary.engine, PingEngine::IcmpDatagram);
+        assert_eq!(summary.received, 3);
+        assert!((summary.packet_loss_pct - 25.0).abs() < f64::EPSILON);
+        assert_eq!(summary.min_ms, Some(10.0));
+        assert_eq!(summary.max_ms, Some(12.0));
+    }
+
+    #[tokio::test]
+    async fn tabulates_ping_per_resolved_address() {
+        let healthy = summarize_ping(PingEngine::IcmpDatagram, 4, vec![10.0, 12.0, 11.0, 11.0])
+            .expect("samples should summarize");
+        let lossy = summarize_ping(PingEngine::IcmpDatagram, 4, vec![80.0, 95.0])
+            .expect("samples should summarize");
+        let table = format_address_pings(&[
+            AddressPing {
+                address: "192.0.2.10".to_string(),
+                ping: ProbeOutcome::success(healthy),
+            },
+            AddressPing {
+                address: "192.0.2.11".to_string(),
+                ping: ProbeOutcome::success(lossy),
+            },
+            AddressPing {
+                address: "2001:db8::10".to_string(),
+                ping: ProbeOutcome::failure(anyhow::anyhow!("network unreachable")),
+            },
+        ]);
+        let lines = table.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with("  address "));
+        assert!(lines[1].contains("4/4"));
+        assert!(lines[1].contains("11.00 ms"));
+        assert!(lines[2].contains("2/4"));
+        assert!(lines[2].contains("50.0%"));
+        assert!(lines[3].ends_with("error: network unreachable"));
+        assert!(measure_address_pings(
+            &["2001:db8::10".to_string()],
+            Some(AddressFamily::V4),
+            1,
+            PingConfig::default()
+        )
+        .await
+        .is_empty());
     }
 
-    let rank = (percentile / 100.0) * (sorted_values.len() - 1) as f64;
-    let lower = rank.floor() as usize;
-    let upper = rank.ceil() as usize;
+    #[tokio::test]
+    async fn measures_tcp_connect_latency_against_local_listener() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("local listener should bind");
+        let port = listener
+            .local_addr()
+            .expect("listener should have an address")
+            .port();
+        tokio::spawn(async move { while listener.accept().await.is_ok() {} });
+
+        let summary = measure_tcp_connect("127.0.0.1", None, port, 3)
+            .await
+            .expect("tcp probe should run");
+
+        assert_eq!(summary.port, port);
+        assert_eq!(summary.connected, 3);
+        assert_eq!(summary.loss_pct, 0.0);
+        assert_eq!(summary.samples_ms.len(), 3);
+        assert!(summary.connect.is_some());
+        assert!(summary.last_error.is_none());
+    }
 
-    if lower == upper {
-        return sorted_values[lower];
+    #[tokio::test]
+    async fn pins_http_timing_to_the_requested_family() {
+        let listener = server::bind_server("127.0.0.1:0")
+            .await
+            .expect("server should bind");
+        let address = listener
+            .local_addr()
+            .expect("server should have an address");
+        tokio::spawn(server::serve(listener));
+        let url = format!("http://{address}/__down?bytes=1000");
+
+        let timing = measure_http_timing(&url, Some(AddressFamily::V4), Duration::from_secs(5))
+            .await
+            .expect("IPv4 timing should succeed");
+        assert_eq!(timing.remote_address, address.to_string());
+
+        let error = measure_http_timing(&url, Some(AddressFamily::V6), Duration::from_secs(5))
+            .await
+            .expect_err("an IPv4 literal has no IPv6 address");
+        assert!(error.to_string().contains("no IPv6 addresses"));
     }
 
-    let weight = rank - lower as f64;
-    sorted_values[lower] * (1.0 - weight) + sorted_values[upper] * weight
-}
+    #[tokio::test]
+    async fn refused_tcp_connects_count_as_loss() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("local listener should bind");
+        let port = listener
+            .local_addr()
+            .expect("listener should have an address")
+            .port();
+        drop(listener);
+
+        let summary = measure_tcp_connect("127.0.0.1", None, port, 2)
+            .await
+            .expect("tcp probe should run");
 
-fn split_size(total_size: usize, streams: u32) -> usize {
-    let streams = streams.max(1) as usize;
-    total_size.div_ceil(streams)
-}
+        assert_eq!(summary.connected, 0);
+        assert_eq!(summary.loss_pct, 100.0);
+        assert!(summary.connect.is_none());
+        assert!(summary.last_error.is_some());
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::{
-        calculate_jitter_ms, calculate_stats, calibrate_transfer_size, format_provider_catalog,
-        parse_ping_output, provider_catalog, resolve_probe_options, select_bandwidth_endpoint,
-        sized_download_url, split_size, BandwidthConfig, BandwidthEndpoint,
-        BandwidthProviderPreset, MeasurementProfile, ProbeOverrides, ProbeReport, TransferSample,
-        CLOUDFLARE_UPLOAD_URL,
-    };
-    use reqwest::Client;
+    #[test]
+    fn pins_target_addresses_to_requested_family() {
+        assert_eq!(
+            resolve_target_address("127.0.0.1", Some(AddressFamily::V4))
+                .expect("v4 literal should resolve"),
+            IpAddr::V4(Ipv4Addr::LOCALHOST)
+        );
+        let error = resolve_target_address("127.0.0.1", Some(AddressFamily::V6))
+            .expect_err("v4 literal is not an IPv6 target");
+        assert_eq!(error.to_string(), "127.0.0.1 is not an IPv6 address");
+        assert!(AddressFamily::V6.matches(&"::1".parse().expect("valid address")));
+        assert_eq!(
+            AddressFamily::V6.unspecified(),
+            "::".parse::<IpAddr>().expect("valid address")
+        );
+    }
+
+    #[tokio::test]
+    async fn measures_dns_resolvers_against_local_stub() {
+        let address = dns::stub::spawn(Ipv4Addr::new(192, 0, 2, 10), 120).await;
+        let doh_url = dns::stub::spawn_doh(Ipv4Addr::new(192, 0, 2, 10), 120).await;
+
+        let summary = measure_dns(
+            "localhost",
+            Some(&DnsQueryConfig {
+                servers: vec![
+                    DnsServer::Plain {
+                        address,
+                        transport: DnsTransport::Tcp,
+                    },
+                    DnsServer::Https {
+                        url: doh_url.clone(),
+                    },
+                ],
+                record_type: DnsRecordType::A,
+            }),
+            3,
+        )
+        .await
+        .expect("dns probe should run");
+
+        let resolver = &summary.resolvers[0];
+        assert_eq!(resolver.server, address.to_string());
+        assert!(resolver.handshake.is_none());
+        assert_eq!(resolver.responses, 3);
+        assert_eq!(resolver.loss_pct, 0.0);
+        assert_eq!(resolver.rcodes.get("NOERROR"), Some(&3));
+        assert_eq!(resolver.min_ttl_s, Some(120));
+        assert_eq!(resolver.answers, vec!["alias.test", "192.0.2.10"]);
+        assert_eq!(resolver.samples_ms.len(), 3);
+        assert!(resolver.last_error.is_none());
+
+        let report = format_dns_resolver(resolver);
+        assert!(report.contains("(A over tcp): 3/3 answered | NOERROR x3 | ttl 120 s"));
+
+        let doh = &summary.resolvers[1];
+        assert_eq!(doh.server, doh_url);
+        assert_eq!(doh.transport, DnsTransport::Doh);
+        assert_eq!(doh.responses, 3);
+        assert_eq!(doh.answers, vec!["alias.test", "192.0.2.10"]);
+        let handshake = doh.handshake.as_ref().expect("DoH records a handshake");
+        assert!(handshake.connect_ms >= 0.0);
+        assert!(handshake.tls_ms.is_none());
+        assert!(format_dns_resolver(doh).contains("handshake: connect "));
+    }
 
     #[test]
-    fn parses_unix_ping_output_into_structured_stats() {
-        let output = "\
-PING example.com (93.184.216.34): 56 data bytes
-64 bytes from 93.184.216.34: icmp_seq=0 ttl=56 time=24.1 ms
-64 bytes from 93.184.216.34: icmp_seq=1 ttl=56 time=21.4 ms
-64 bytes from 93.184.216.34: icmp_seq=2 ttl=56 time=23.0 ms
-";
+    fn summarizes_udp_echo_loss_duplicates_and_reordering() {
+        let arrival = |sequence, sent_ms, received_ms| udp::EchoArrival {
+            sequence,
+            sent_offset: Duration::from_millis(sent_ms),
+            received_offset: Duration::from_millis(received_ms),
+        };
+        let arrivals = [
+            arrival(0, 0, 10),
+            arrival(2, 40, 52),
+            arrival(1, 20, 55),
+            arrival(2, 40, 56),
+            arrival(4, 80, 90),
+        ];
+        let config = UdpEchoConfig {
+            port: 40_000,
+            packets: 5,
+            interval_ms: 20,
+            payload_bytes: 160,
+        };
 
-        let parsed = parse_ping_output(output, 3).expect("ping output should parse");
+        let summary = summarize_udp_echo("127.0.0.1:40000".parse().unwrap(), config, &arrivals);
 
-        assert_eq!(parsed.sent, 3);
-        assert_eq!(parsed.received, 3);
-        assert!((parsed.packet_loss_pct - 0.0).abs() < f64::EPSILON);
-        assert_eq!(parsed.samples_ms.len(), 3);
-        assert_eq!(parsed.min_ms, Some(21.4));
-        assert_eq!(parsed.max_ms, Some(24.1));
-        assert!(parsed.avg_ms.expect("avg latency should exist") > 22.0);
-        assert_eq!(parsed.median_ms, Some(23.0));
-        assert!(parsed.p95_ms.expect("p95 latency should exist") > 23.0);
-        assert!(parsed.jitter_ms.expect("jitter should exist") > 2.0);
+        assert_eq!(summary.received, 4);
+        assert!((summary.loss_pct - 20.0).abs() < f64::EPSILON);
+        assert_eq!(summary.duplicates, 1);
+        assert_eq!(summary.out_of_order, 1);
+        assert_eq!(summary.samples_ms, vec![10.0, 12.0, 35.0, 10.0]);
+        assert!(summary.jitter_ms.is_some());
     }
 
     #[test]
-    fn parses_windows_ping_output_with_sub_millisecond_response() {
-        let output = "\
-Reply from 1.1.1.1: bytes=32 time<1ms TTL=59
-Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
-";
+    fn interarrival_jitter_follows_rfc3550_smoothing() {
+        let jitter =
+            calculate_interarrival_jitter_ms(&[10.0, 26.0, 10.0]).expect("jitter should exist");
 
-        let parsed = parse_ping_output(output, 2).expect("windows ping should parse");
-
-        assert_eq!(parsed.received, 2);
-        assert_eq!(parsed.min_ms, Some(1.0));
-        assert_eq!(parsed.max_ms, Some(2.0));
+        // 0 + (16 - 0) / 16 = 1, then 1 + (16 - 1) / 16 = 1.9375
+        assert!((jitter - 1.9375).abs() < f64::EPSILON);
+        assert_eq!(calculate_interarrival_jitter_ms(&[10.0]), None);
     }
 
     #[test]
@@ -1404,16 +5250,24 @@ Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
             TransferSample {
                 target_bytes: 1_000,
                 mbps: 80.0,
+                average_mbps: 80.0,
                 bytes: 10_000_000,
                 elapsed_ms: 1_000.0,
                 streams: 1,
+                window_ms: None,
+                series: Vec::new(),
+                ramp_up_fallback: false,
             },
             TransferSample {
                 target_bytes: 1_000,
                 mbps: 120.0,
+                average_mbps: 120.0,
                 bytes: 15_000_000,
                 elapsed_ms: 1_000.0,
                 streams: 1,
+                window_ms: None,
+                series: Vec::new(),
+                ramp_up_fallback: false,
             },
         ];
 
@@ -1427,6 +5281,50 @@ Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
         );
     }
 
+    #[test]
+    fn excludes_ramp_up_from_steady_state_throughput() {
+        let series = [
+            (100.0, 10_000),
+            (200.0, 50_000),
+            (300.0, 150_000),
+            (500.0, 400_000),
+            (600.0, 525_000),
+        ]
+        .map(|(elapsed_ms, bytes)| ThroughputPoint { elapsed_ms, bytes });
+
+        let steady = steady_state_mbps(&series, 300.0).expect("cutoff leaves a span");
+
+        assert!((steady - 10.0).abs() < 1e-9);
+        assert_eq!(steady_state_mbps(&series, 0.0), None);
+        // Less than half the transfer left after the cutoff.
+        assert_eq!(steady_state_mbps(&series, 400.0), None);
+        // Half the transfer left, but only two samples to measure it.
+        assert_eq!(steady_state_mbps(&[series[2], series[4]], 300.0), None);
+        assert_eq!(steady_state_mbps(&series, 600.0), None);
+        assert_eq!(steady_state_mbps(&series, 700.0), None);
+    }
+
+    #[test]
+    fn slices_uploads_from_a_shared_random_block() {
+        let block = upload_block();
+        let mut counts = [0usize; 256];
+        for byte in block.iter() {
+            counts[*byte as usize] += 1;
+        }
+        let expected = UPLOAD_BLOCK_BYTES / 256;
+
+        assert_eq!(block.len(), UPLOAD_BLOCK_BYTES);
+        assert!(counts
+            .iter()
+            .all(|count| count.abs_diff(expected) < expected / 20));
+
+        let first = upload_chunks().next().expect("chunks never run out");
+        let second = upload_chunks().next().expect("chunks never run out");
+        assert_eq!(first.len(), UPLOAD_CHUNK_BYTES);
+        assert_ne!(first, second);
+        assert_eq!(upload_block().as_ptr(), block.as_ptr());
+    }
+
     #[test]
     fn rewrites_cloudflare_download_size_without_touching_custom_urls() {
         assert_eq!(
@@ -1450,6 +5348,27 @@ Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
             provider: BandwidthProviderPreset::Cloudflare,
             endpoint: None,
             samples: None,
+            family: None,
+            ping_payload_bytes: None,
+            ping_interval_ms: None,
+            ping_ttl: None,
+            ping_timeout_ms: None,
+            ping_all_addresses: false,
+            tcp_port: None,
+            tls_port: None,
+            dns_servers: Vec::new(),
+            dns_record: None,
+            dns_transport: None,
+            udp_port: None,
+            udp_packets: None,
+            udp_interval_ms: None,
+            udp_payload_bytes: None,
+            trace: false,
+            trace_max_hops: None,
+            trace_cycles: None,
+            pmtu: false,
+            pmtu_max_bytes: None,
+            http_urls: Vec::new(),
             download_urls: Vec::new(),
             upload_urls: Vec::new(),
             download_size_bytes: None,
@@ -1461,13 +5380,19 @@ Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
             download_streams: None,
             upload_streams: None,
             target_transfer_duration_ms: None,
+            transfer_duration_ms: None,
+            ramp_up_exclusion_ms: None,
+            min_successful_runs: None,
             max_download_size_bytes: None,
             max_upload_size_bytes: None,
+            loaded_latency: false,
+            responsiveness: false,
         })
         .expect("probe options should resolve");
 
         assert_eq!(options.profile, MeasurementProfile::Standard);
         assert_eq!(options.samples, 5);
+        assert_eq!(options.ping, PingConfig::default());
         assert_eq!(options.bandwidth.warmup_runs, 1);
         assert_eq!(options.bandwidth.transfer_attempts, 2);
         assert_eq!(options.bandwidth.transfer_timeout_seconds, 30);
@@ -1496,6 +5421,27 @@ Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
             provider: BandwidthProviderPreset::Cloudflare,
             endpoint: None,
             samples: None,
+            family: None,
+            ping_payload_bytes: Some(1_400),
+            ping_interval_ms: Some(200),
+            ping_ttl: Some(0),
+            ping_timeout_ms: None,
+            ping_all_addresses: false,
+            tcp_port: None,
+            tls_port: None,
+            dns_servers: Vec::new(),
+            dns_record: None,
+            dns_transport: None,
+            udp_port: None,
+            udp_packets: None,
+            udp_interval_ms: None,
+            udp_payload_bytes: None,
+            trace: false,
+            trace_max_hops: None,
+            trace_cycles: None,
+            pmtu: false,
+            pmtu_max_bytes: None,
+            http_urls: Vec::new(),
             download_urls: vec!["https://downloads.example.test/file.bin".to_string()],
             upload_urls: vec!["https://uploads.example.test".to_string()],
             download_size_bytes: Some(9_000_000),
@@ -1507,12 +5453,26 @@ Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
             download_streams: Some(3),
             upload_streams: Some(2),
             target_transfer_duration_ms: Some(1_500),
+            transfer_duration_ms: None,
+            ramp_up_exclusion_ms: None,
+            min_successful_runs: None,
             max_download_size_bytes: Some(12_000_000),
             max_upload_size_bytes: Some(4_000_000),
+            loaded_latency: false,
+            responsiveness: false,
         })
         .expect("probe options should resolve");
 
         assert_eq!(options.bandwidth.provider, BandwidthProviderPreset::Custom);
+        assert_eq!(
+            options.ping,
+            PingConfig {
+                payload_bytes: 1_400,
+                interval_ms: 200,
+                ttl: Some(1),
+                timeout_ms: 1_000,
+            }
+        );
         assert_eq!(
             options.bandwidth.endpoints[0].download_url,
             "https://downloads.example.test/file.bin"
@@ -1531,6 +5491,27 @@ Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
             provider: BandwidthProviderPreset::Custom,
             endpoint: Some("custom-2".to_string()),
             samples: None,
+            family: None,
+            ping_payload_bytes: None,
+            ping_interval_ms: None,
+            ping_ttl: None,
+            ping_timeout_ms: None,
+            ping_all_addresses: false,
+            tcp_port: None,
+            tls_port: None,
+            dns_servers: Vec::new(),
+            dns_record: None,
+            dns_transport: None,
+            udp_port: None,
+            udp_packets: None,
+            udp_interval_ms: None,
+            udp_payload_bytes: None,
+            trace: false,
+            trace_max_hops: None,
+            trace_cycles: None,
+            pmtu: false,
+            pmtu_max_bytes: None,
+            http_urls: Vec::new(),
             download_urls: vec![
                 "https://downloads.example.test/a.bin".to_string(),
                 "https://downloads.example.test/b.bin".to_string(),
@@ -1548,8 +5529,13 @@ Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
             download_streams: None,
             upload_streams: None,
             target_transfer_duration_ms: None,
+            transfer_duration_ms: None,
+            ramp_up_exclusion_ms: None,
+            min_successful_runs: None,
             max_download_size_bytes: None,
             max_upload_size_bytes: None,
+            loaded_latency: false,
+            responsiveness: false,
         })
         .expect("probe options should resolve");
 
@@ -1558,11 +5544,39 @@ Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
         assert_eq!(options.bandwidth.endpoints[1].name, "custom-2");
     }
 
+    // Small transfers against a local `server::serve` instance; tests override
+    // what they exercise.
+    fn local_bandwidth_config(address: SocketAddr) -> BandwidthConfig {
+        BandwidthConfig {
+            provider: BandwidthProviderPreset::Custom,
+            endpoint: None,
+            endpoints: vec![BandwidthEndpoint {
+                name: "custom-1".to_string(),
+                download_url: format!("http://{address}/__down?bytes=100000"),
+                upload_url: format!("http://{address}/__up"),
+            }],
+            download_size_bytes: 100_000,
+            upload_size_bytes: 50_000,
+            runs: 2,
+            warmup_runs: 0,
+            transfer_attempts: 1,
+            transfer_timeout_seconds: 10,
+            download_streams: 2,
+            upload_streams: 2,
+            target_transfer_duration_ms: 50,
+            transfer_duration_ms: None,
+            ramp_up_exclusion_ms: 0,
+            min_successful_runs: 1,
+            max_download_size_bytes: 400_000,
+            max_upload_size_bytes: 200_000,
+            loaded_latency: false,
+            family: None,
+        }
+    }
+
     #[tokio::test]
     async fn requested_endpoint_error_lists_available_candidates() {
-        let client = Client::new();
         let config = BandwidthConfig {
-            provider: BandwidthProviderPreset::Custom,
             endpoint: Some("custom-3".to_string()),
             endpoints: vec![
                 BandwidthEndpoint {
@@ -1576,20 +5590,10 @@ Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
                     upload_url: "https://uploads.example.test/b".to_string(),
                 },
             ],
-            download_size_bytes: 1,
-            upload_size_bytes: 1,
-            runs: 1,
-            warmup_runs: 0,
-            transfer_attempts: 1,
-            transfer_timeout_seconds: 30,
-            download_streams: 1,
-            upload_streams: 1,
-            target_transfer_duration_ms: 1_000,
-            max_download_size_bytes: 1,
-            max_upload_size_bytes: 1,
+            ..local_bandwidth_config(SocketAddr::from(([127, 0, 0, 1], 9)))
         };
 
-        let error = match select_bandwidth_endpoint(&client, &config).await {
+        let error = match select_bandwidth_endpoint(&reqwest::Client::new(), &config).await {
             Ok(_) => panic!("unknown endpoint should fail before probing"),
             Err(error) => error,
         };
@@ -1599,6 +5603,448 @@ Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
         assert!(message.contains("available endpoints: custom-1, custom-2"));
     }
 
+    #[tokio::test]
+    async fn measures_bandwidth_against_local_throughput_server() {
+        let listener = server::bind_server("127.0.0.1:0")
+            .await
+            .expect("server should bind");
+        let address = listener
+            .local_addr()
+            .expect("server should have an address");
+        tokio::spawn(server::serve(listener));
+
+        let config = BandwidthConfig {
+            warmup_runs: 1,
+            loaded_latency: true,
+            ..local_bandwidth_config(address)
+        };
+
+        let summary = measure_bandwidth("127.0.0.1", &config)
+            .await
+            .expect("local bandwidth run should succeed");
+
+        assert_eq!(summary.endpoint, "custom-1");
+        assert!(summary.endpoint_latency_ms.is_some());
+        assert_eq!(summary.download_runs.len(), 2);
+        assert_eq!(summary.upload_runs.len(), 2);
+        assert!(summary.download_mbps > 0.0);
+        assert!(summary.upload_mbps > 0.0);
+        assert_eq!(
+            summary.download_runs[0].bytes,
+            summary.calibrated_download_size_bytes as u64 * 2
+        );
+
+        let loaded = summary
+            .loaded_latency
+            .expect("loaded latency should be sampled");
+        assert_eq!(loaded.endpoint_address, Some(address.to_string()));
+        for phase in [&loaded.idle, &loaded.download, &loaded.upload] {
+            let endpoint = phase
+                .endpoint
+                .as_ref()
+                .expect("endpoint latency should be sampled");
+            assert!(endpoint.received > 0);
+        }
+        assert!(loaded.download_increase_ms.is_some());
+        assert!(loaded.grade.is_some());
+    }
+
+    #[tokio::test]
+    async fn sizes_static_file_downloads_with_range_or_stream_limit() {
+        let listener = server::bind_server("127.0.0.1:0")
+            .await
+            .expect("server should bind");
+        let address = listener
+            .local_addr()
+            .expect("server should have an address");
+        tokio::spawn(server::serve(listener));
+        // A static file server that ignores `Range` and starts sending a 4 GB
+        // file for every request; only reading past the limit could finish it.
+        let static_files = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("static server should bind");
+        let static_address = static_files
+            .local_addr()
+            .expect("static server should have an address");
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = static_files.accept().await {
+                tokio::spawn(async move {
+                    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+                    let mut request = Vec::new();
+                    let mut buffer = [0_u8; 1024];
+                    while let Ok(read @ 1..) = stream.read(&mut buffer).await {
+                        request.extend_from_slice(&buffer[..read]);
+                        if request.windows(4).any(|window| window == b"\r\n\r\n") {
+                            break;
+                        }
+                    }
+                    if stream
+                        .write_all(b"HTTP/1.1 200 OK\r\ncontent-length: 4000000000\r\n\r\n")
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                    let chunk = vec![0_u8; 64 * 1024];
+                    while stream.write_all(&chunk).await.is_ok() {}
+                });
+            }
+        });
+
+        for (download_url, sizing) in [
+            (
+                format!("http://{address}/__down"),
+                DownloadSizing::RangeRequest,
+            ),
+            (
+                format!("http://{static_address}/4GB.bin"),
+                DownloadSizing::StreamLimit,
+            ),
+        ] {
+            let config = BandwidthConfig {
+                endpoints: vec![BandwidthEndpoint {
+                    name: "custom-1".to_string(),
+                    download_url: download_url.clone(),
+                    upload_url: format!("http://{address}/__up"),
+                }],
+                transfer_timeout_seconds: 3,
+                upload_streams: 1,
+                max_upload_size_bytes: 50_000,
+                ..local_bandwidth_config(address)
+            };
+
+            let summary = measure_bandwidth("127.0.0.1", &config)
+                .await
+                .expect("static file bandwidth run should succeed");
+
+            assert_eq!(summary.download_sizing, Some(sizing));
+            assert_eq!(summary.download_url, download_url);
+            let timing = summary.endpoint_candidates[0]
+                .timing
+                .as_ref()
+                .expect("healthy endpoint should have a timing breakdown");
+            assert!(timing.bytes < 1_000_000);
+            for sample in &summary.download_runs {
+                assert_eq!(sample.bytes, 2 * 100_000);
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn keeps_partial_bandwidth_results_when_some_runs_fail() {
+        let listener = server::bind_server("127.0.0.1:0")
+            .await
+            .expect("server should bind");
+        let address = listener
+            .local_addr()
+            .expect("server should have an address");
+        tokio::spawn(server::serve(listener));
+        // Drops every other upload connection and proxies the rest.
+        let flaky = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("proxy should bind");
+        let flaky_address = flaky.local_addr().expect("proxy should have an address");
+        tokio::spawn(async move {
+            for connection in 0.. {
+                let Ok((mut inbound, _)) = flaky.accept().await else {
+                    return;
+                };
+                if connection % 2 == 0 {
+                    continue;
+                }
+                tokio::spawn(async move {
+                    if let Ok(mut outbound) = tokio::net::TcpStream::connect(address).await {
+                        let _ = tokio::io::copy_bidirectional(&mut inbound, &mut outbound).await;
+                    }
+                });
+            }
+        });
+
+        let mut config = BandwidthConfig {
+            endpoints: vec![BandwidthEndpoint {
+                name: "custom-1".to_string(),
+                download_url: format!("http://{address}/__down?bytes=100000"),
+                upload_url: format!("http://{flaky_address}/__up"),
+            }],
+            runs: 3,
+            download_streams: 1,
+            upload_streams: 1,
+            min_successful_runs: 2,
+            max_download_size_bytes: 100_000,
+            max_upload_size_bytes: 50_000,
+            ..local_bandwidth_config(address)
+        };
+
+        let summary = measure_bandwidth("127.0.0.1", &config)
+            .await
+            .expect("two successful upload runs should be enough");
+
+        assert_eq!(summary.download_runs.len(), 3);
+        assert_eq!(summary.upload_runs.len(), 2);
+        assert!((summary.upload_success_ratio() - 2.0 / 3.0).abs() < 1e-9);
+        assert_eq!(summary.failed_attempts.len(), 1);
+        let failure = &summary.failed_attempts[0];
+        assert_eq!(
+            (
+                failure.phase,
+                failure.direction,
+                failure.run,
+                failure.attempt
+            ),
+            (TransferPhase::Measured, TransferDirection::Upload, 1, 1)
+        );
+        assert!(failure.error.contains("failed to POST"));
+
+        config.min_successful_runs = 3;
+        let error = measure_bandwidth("127.0.0.1", &config)
+            .await
+            .expect_err("too few successful upload runs should fail the section");
+        assert!(format!("{error:#}").contains("2 of 3 measured runs succeeded, 3 required"));
+    }
+
+    #[tokio::test]
+    async fn measures_bandwidth_for_a_fixed_transfer_window() {
+        let listener = server::bind_server("127.0.0.1:0")
+            .await
+            .expect("server should bind");
+        let address = listener
+            .local_addr()
+            .expect("server should have an address");
+        tokio::spawn(server::serve(listener));
+
+        let config = BandwidthConfig {
+            transfer_duration_ms: Some(300),
+            ramp_up_exclusion_ms: 100,
+            max_download_size_bytes: 1_000_000,
+            ..local_bandwidth_config(address)
+        };
+
+        let summary = measure_bandwidth("127.0.0.1", &config)
+            .await
+            .expect("duration-based bandwidth run should succeed");
+
+        assert_eq!(summary.transfer_duration_ms, Some(300));
+        assert_eq!(summary.calibrated_download_size_bytes, 100_000);
+        assert_eq!(
+            summary.download_url,
+            format!("http://{address}/__down?bytes=1000000")
+        );
+        for sample in summary.download_runs.iter().chain(&summary.upload_runs) {
+            let window_ms = sample.window_ms.expect("window should be recorded");
+            assert!(window_ms > 290.0 && window_ms <= 300.0 + 1e-6);
+            assert!(sample.elapsed_ms >= window_ms);
+            assert!(sample.bytes > 0);
+            let mbps = sample.bytes as f64 * 8.0 / (window_ms / 1_000.0) / 1_000_000.0;
+            assert!((sample.average_mbps - mbps).abs() < 1e-6);
+            assert!(sample.mbps > 0.0);
+            let last = sample.series.last().expect("series should be sampled");
+            assert_eq!((last.elapsed_ms, last.bytes), (window_ms, sample.bytes));
+            assert!(sample.series.len() >= 3);
+            assert!(sample
+                .series
+                .windows(2)
+                .all(|pair| pair[0].elapsed_ms < pair[1].elapsed_ms
+                    && pair[0].bytes <= pair[1].bytes));
+        }
+        // Streams re-request the object, so a window moves more than one body.
+        assert!(summary.download_runs[0].bytes > 2 * 1_000_000);
+    }
+
+    #[tokio::test]
+    async fn measures_responsiveness_against_local_throughput_server() {
+        let listener = server::bind_server("127.0.0.1:0")
+            .await
+            .expect("server should bind");
+        let address = listener
+            .local_addr()
+            .expect("server should have an address");
+        tokio::spawn(server::serve(listener));
+
+        let config = BandwidthConfig {
+            endpoints: vec![BandwidthEndpoint {
+                name: "custom-1".to_string(),
+                download_url: format!("http://{address}/__down?bytes=200000"),
+                upload_url: format!("http://{address}/__up"),
+            }],
+            download_size_bytes: 200_000,
+            upload_size_bytes: 100_000,
+            runs: 1,
+            upload_streams: 1,
+            max_download_size_bytes: 200_000,
+            max_upload_size_bytes: 100_000,
+            ..local_bandwidth_config(address)
+        };
+        let bandwidth = measure_bandwidth("127.0.0.1", &config)
+            .await
+            .expect("local bandwidth run should succeed");
+
+        let summary = measure_responsiveness(
+            &bandwidth,
+            Some(AddressFamily::V4),
+            Duration::from_secs(5),
+            Duration::from_millis(100),
+            Duration::from_millis(500),
+        )
+        .await
+        .expect("local responsiveness run should succeed");
+
+        assert!(summary.rpm > 0.0);
+        assert_eq!(
+            summary.probe_url,
+            format!("http://{address}/__down?bytes=1")
+        );
+        assert!(summary.foreign_probes > 0);
+        assert!(summary.reused_probes > 0);
+        assert!(summary.tls_handshake.is_none());
+        assert_eq!((summary.download_streams, summary.upload_streams), (2, 1));
+    }
+
+    #[test]
+    fn computes_rpm_from_trimmed_probe_means() {
+        assert_eq!(trimmed_mean(&[]), None);
+        assert_eq!(
+            trimmed_mean(&(1..=20).map(f64::from).collect::<Vec<_>>()),
+            Some(10.0)
+        );
+
+        let rpm =
+            responsiveness_rpm(&[20.0], &[40.0], &[60.0], &[30.0]).expect("rpm should be computed");
+        assert_eq!(rpm, 60_000.0 / (120.0 / 6.0 + 30.0 / 2.0));
+        assert_eq!(
+            responsiveness_rpm(&[20.0], &[], &[60.0], &[30.0]),
+            Some(60_000.0 / (80.0 / 6.0 + 15.0))
+        );
+        assert_eq!(responsiveness_rpm(&[20.0], &[40.0], &[60.0], &[]), None);
+    }
+
+    #[test]
+    fn summarizes_trace_hops_up_to_destination() {
+        let hop = |octet: u8| IpAddr::V4(Ipv4Addr::new(192, 0, 2, octet));
+        let probe = |ttl: u8, reply: Option<(u8, u64)>, reached: bool| TraceProbe {
+            ttl,
+            reply: reply.map(|(octet, ms)| (hop(octet), Duration::from_millis(ms))),
+            reached,
+        };
+        let probes = [
+            probe(1, Some((1, 2)), false),
+            probe(2, None, false),
+            probe(3, Some((3, 20)), true),
+            probe(4, Some((3, 21)), true),
+            probe(1, Some((11, 4)), false),
+            probe(2, None, false),
+            probe(3, Some((3, 22)), true),
+        ];
+
+        let summary = summarize_trace(
+            hop(3),
+            TraceConfig {
+                max_hops: 30,
+                cycles: 2,
+            },
+            &probes,
+        );
+
+        assert!(summary.reached);
+        assert_eq!(summary.hops.len(), 3);
+        assert_eq!(summary.hops[0].addresses, vec!["192.0.2.1", "192.0.2.11"]);
+        assert_eq!(summary.hops[1].loss_pct, 100.0);
+        assert!(summary.hops[1].addresses.is_empty());
+        assert_eq!((summary.hops[2].sent, summary.hops[2].received), (2, 2));
+        assert_eq!(
+            summary.hops[2].rtt.as_ref().map(|stats| stats.median),
+            Some(21.0)
+        );
+    }
+
+    #[test]
+    fn binary_searches_largest_unfragmented_size() {
+        let reply = icmp::UnfragmentedEcho::Reply(Duration::from_millis(5));
+        let mut probed = Vec::new();
+        let search = search_pmtu(576, 1_500, |size| {
+            probed.push(size);
+            Ok(match size {
+                size if size <= 1_420 => reply,
+                size if size <= 1_480 => icmp::UnfragmentedEcho::Lost,
+                _ => icmp::UnfragmentedEcho::FragmentationNeeded,
+            })
+        })
+        .expect("search should succeed");
+
+        assert_eq!(
+            search,
+            PmtuSearch {
+                mtu_bytes: 1_420,
+                no_reply_from_bytes: Some(1_421),
+                signalled_limit_bytes: None,
+                local_limit_bytes: None,
+            }
+        );
+        assert!(probed.len() < 15);
+
+        let unrestricted = search_pmtu(576, 1_500, |_| Ok(reply)).expect("search should succeed");
+        assert_eq!(unrestricted.mtu_bytes, 1_500);
+        assert_eq!(unrestricted.no_reply_from_bytes, None);
+
+        let signalled = search_pmtu(576, 1_500, |size| {
+            Ok(if size <= 1_400 {
+                reply
+            } else {
+                icmp::UnfragmentedEcho::FragmentationNeeded
+            })
+        })
+        .expect("search should succeed");
+        assert_eq!(signalled.mtu_bytes, 1_400);
+        assert_eq!(signalled.signalled_limit_bytes, Some(1_401));
+        assert_eq!(signalled.no_reply_from_bytes, None);
+
+        let local = search_pmtu(1_280, 9_000, |size| {
+            Ok(if size <= 1_500 {
+                reply
+            } else {
+                icmp::UnfragmentedEcho::TooBig
+            })
+        })
+        .expect("search should succeed");
+        assert_eq!(local.mtu_bytes, 1_500);
+        assert_eq!(local.local_limit_bytes, Some(1_501));
+
+        assert!(search_pmtu(576, 1_500, |_| Ok(icmp::UnfragmentedEcho::Lost)).is_err());
+    }
+
+    #[test]
+    fn grades_bufferbloat_from_worst_loaded_increase() {
+        let samples = |values: &[Option<u64>]| PhaseSamples {
+            target: Some(
+                values
+                    .iter()
+                    .map(|value| value.map(Duration::from_millis))
+                    .collect(),
+            ),
+            endpoint: None,
+        };
+        let sampler = LatencySampler {
+            target: Some("192.0.2.1".parse().expect("valid address")),
+            endpoint: None,
+        };
+
+        let summary = summarize_loaded_latency(
+            &sampler,
+            samples(&[Some(10), Some(12), Some(11)]),
+            samples(&[Some(40), None, Some(50), Some(45)]),
+            samples(&[Some(90), Some(95), Some(100)]),
+        );
+
+        assert_eq!(summary.download_increase_ms, Some(34.0));
+        assert_eq!(summary.upload_increase_ms, Some(84.0));
+        assert_eq!(summary.grade, Some(BufferbloatGrade::C));
+        let download = summary.download.target.expect("target series exists");
+        assert_eq!((download.sent, download.received), (4, 3));
+        assert_eq!(download.loss_pct, 25.0);
+        assert!(summary.idle.endpoint.is_none());
+        assert_eq!(bufferbloat_grade(2.0), BufferbloatGrade::APlus);
+        assert_eq!(bufferbloat_grade(450.0), BufferbloatGrade::F);
+    }
+
     #[test]
     fn saved_runs_without_endpoint_metadata_use_defaults() {
         let report: ProbeReport = serde_json::from_str(
@@ -1651,6 +6097,8 @@ Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
 
         assert_eq!(report.profile, MeasurementProfile::Standard);
         assert_eq!(report.bandwidth_provider, "cloudflare");
+        assert!(report.tcp.is_none());
+        assert!(report.udp.is_none());
         assert_eq!(bandwidth.provider, "cloudflare");
         assert_eq!(bandwidth.endpoint, "global");
         assert_eq!(bandwidth.endpoint_latency_ms, None);
@@ -1685,6 +6133,27 @@ Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
             provider: BandwidthProviderPreset::Custom,
             endpoint: None,
             samples: None,
+            family: None,
+            ping_payload_bytes: None,
+            ping_interval_ms: None,
+            ping_ttl: None,
+            ping_timeout_ms: None,
+            ping_all_addresses: false,
+            tcp_port: None,
+            tls_port: None,
+            dns_servers: Vec::new(),
+            dns_record: None,
+            dns_transport: None,
+            udp_port: None,
+            udp_packets: None,
+            udp_interval_ms: None,
+            udp_payload_bytes: None,
+            trace: false,
+            trace_max_hops: None,
+            trace_cycles: None,
+            pmtu: false,
+            pmtu_max_bytes: None,
+            http_urls: Vec::new(),
             download_urls: vec!["https://downloads.example.test/file.bin".to_string()],
             upload_urls: Vec::new(),
             download_size_bytes: None,
@@ -1696,11 +6165,69 @@ Reply from 1.1.1.1: bytes=32 time=2ms TTL=59
             download_streams: None,
             upload_streams: None,
             target_transfer_duration_ms: None,
+            transfer_duration_ms: None,
+            ramp_up_exclusion_ms: None,
+            min_successful_runs: None,
             max_download_size_bytes: None,
             max_upload_size_bytes: None,
+            loaded_latency: false,
+            responsiveness: false,
         })
         .expect_err("custom provider should require both urls");
 
         assert!(error.to_string().contains("--upload-url"));
     }
+
+    #[test]
+    fn rejects_trace_settings_beyond_16_bit_sequence_numbers() {
+        let error = resolve_probe_options(ProbeOverrides {
+            target: "example.com".to_string(),
+            profile: MeasurementProfile::Standard,
+            provider: BandwidthProviderPreset::Cloudflare,
+            endpoint: None,
+            samples: None,
+            family: None,
+            ping_payload_bytes: None,
+            ping_interval_ms: None,
+            ping_ttl: None,
+            ping_timeout_ms: None,
+            ping_all_addresses: false,
+            tcp_port: None,
+            tls_port: None,
+            dns_servers: Vec::new(),
+            dns_record: None,
+            dns_transport: None,
+            udp_port: None,
+            udp_packets: None,
+            udp_interval_ms: None,
+            udp_payload_bytes: None,
+            trace: true,
+            trace_max_hops: Some(255),
+            trace_cycles: Some(258),
+            pmtu: false,
+            pmtu_max_bytes: None,
+            http_urls: Vec::new(),
+            download_urls: Vec::new(),
+            upload_urls: Vec::new(),
+            download_size_bytes: None,
+            upload_size_bytes: None,
+            bandwidth_runs: None,
+            bandwidth_warmup_runs: None,
+            transfer_attempts: None,
+            transfer_timeout_seconds: None,
+            download_streams: None,
+            upload_streams: None,
+            target_transfer_duration_ms: None,
+            transfer_duration_ms: None,
+            ramp_up_exclusion_ms: None,
+            min_successful_runs: None,
+            max_download_size_bytes: None,
+            max_upload_size_bytes: None,
+            loaded_latency: false,
+            responsiveness: false,
+        })
+        .expect_err("trace sequence numbers should fit in 16 bits");
+
+        assert!(error.to_string().contains("--trace-cycles"));
+    }
 }
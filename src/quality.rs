@@ -0,0 +1,233 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+use crate::probe::PingSummary;
+
+// ITU-T G.107 E-model defaults with the G.113 impairment factors for G.711
+// with packet loss concealment, the codec calls fall back to on a bad link.
+const DEFAULT_R_FACTOR: f64 = 93.2;
+const CODEC_IMPAIRMENT: f64 = 0.0;
+const PACKET_LOSS_ROBUSTNESS: f64 = 25.1;
+const DELAY_KNEE_MS: f64 = 177.3;
+// Mouth-to-ear delay adds one 20 ms G.711 frame and a jitter buffer sized to
+// twice the measured jitter on top of half the round trip.
+const PACKETIZATION_DELAY_MS: f64 = 20.0;
+const JITTER_BUFFER_FACTOR: f64 = 2.0;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QualityEstimate {
+    pub one_way_delay_ms: f64,
+    pub r_factor: f64,
+    pub mos: f64,
+    pub verdicts: Vec<UseCaseVerdict>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct UseCaseVerdict {
+    pub use_case: UseCase,
+    pub verdict: Verdict,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum UseCase {
+    Voip,
+    VideoConferencing,
+    Gaming,
+    Streaming,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    Good,
+    Fair,
+    Poor,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct QualityLimits {
+    pub rtt_ms: f64,
+    pub jitter_ms: f64,
+    pub loss_pct: f64,
+}
+
+impl UseCase {
+    pub const ALL: [Self; 4] = [
+        Self::Voip,
+        Self::VideoConferencing,
+        Self::Gaming,
+        Self::Streaming,
+    ];
+
+    // Upper bounds for a good and a fair verdict; anything past fair is poor.
+    pub fn limits(self) -> (QualityLimits, QualityLimits) {
+        let limits = |rtt_ms, jitter_ms, loss_pct| QualityLimits {
+            rtt_ms,
+            jitter_ms,
+            loss_pct,
+        };
+        match self {
+            Self::Voip => (limits(150.0, 20.0, 1.0), limits(300.0, 50.0, 3.0)),
+            Self::VideoConferencing => (limits(200.0, 30.0, 1.0), limits(400.0, 50.0, 2.0)),
+            Self::Gaming => (limits(50.0, 10.0, 0.5), limits(100.0, 25.0, 2.0)),
+            Self::Streaming => (limits(300.0, 100.0, 1.0), limits(1_000.0, 300.0, 5.0)),
+        }
+    }
+
+    fn verdict(self, rtt_ms: f64, jitter_ms: f64, loss_pct: f64) -> Verdict {
+        let (good, fair) = self.limits();
+        if good.admits(rtt_ms, jitter_ms, loss_pct) {
+            Verdict::Good
+        } else if fair.admits(rtt_ms, jitter_ms, loss_pct) {
+            Verdict::Fair
+        } else {
+            Verdict::Poor
+        }
+    }
+}
+
+impl QualityLimits {
+    fn admits(self, rtt_ms: f64, jitter_ms: f64, loss_pct: f64) -> bool {
+        rtt_ms <= self.rtt_ms && jitter_ms <= self.jitter_ms && loss_pct <= self.loss_pct
+    }
+}
+
+impl fmt::Display for UseCase {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::Voip => "VoIP",
+            Self::VideoConferencing => "video conferencing",
+            Self::Gaming => "gaming",
+            Self::Streaming => "streaming",
+        })
+    }
+}
+
+impl fmt::Display for Verdict {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str(match self {
+            Self::Good => "good",
+            Self::Fair => "fair",
+            Self::Poor => "poor",
+        })
+    }
+}
+
+impl fmt::Display for QualityLimits {
+    fn fmt(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            formatter,
+            "{} ms RTT, {} ms jitter, {}% loss",
+            self.rtt_ms, self.jitter_ms, self.loss_pct
+        )
+    }
+}
+
+pub fn estimate(ping: &PingSummary) -> Option<QualityEstimate> {
+    let rtt_ms = ping.avg_ms?;
+    // Summary-only ping output carries a standard deviation but no jitter.
+    let jitter_ms = ping.jitter_ms.or(ping.stddev_ms).unwrap_or(0.0);
+    let loss_pct = ping.packet_loss_pct;
+
+    let one_way_delay_ms = rtt_ms / 2.0 + JITTER_BUFFER_FACTOR * jitter_ms + PACKETIZATION_DELAY_MS;
+    let r_factor = (DEFAULT_R_FACTOR
+        - delay_impairment(one_way_delay_ms)
+        - effective_equipment_impairment(loss_pct))
+    .clamp(0.0, 100.0);
+
+    Some(QualityEstimate {
+        one_way_delay_ms,
+        r_factor,
+        mos: mos_from_r_factor(r_factor),
+        verdicts: UseCase::ALL
+            .into_iter()
+            .map(|use_case| UseCaseVerdict {
+                use_case,
+                verdict: use_case.verdict(rtt_ms, jitter_ms, loss_pct),
+            })
+            .collect(),
+    })
+}
+
+fn delay_impairment(one_way_delay_ms: f64) -> f64 {
+    let knee = if one_way_delay_ms > DELAY_KNEE_MS {
+        0.11 * (one_way_delay_ms - DELAY_KNEE_MS)
+    } else {
+        0.0
+    };
+    0.024 * one_way_delay_ms + knee
+}
+
+fn effective_equipment_impairment(loss_pct: f64) -> f64 {
+    CODEC_IMPAIRMENT + (95.0 - CODEC_IMPAIRMENT) * loss_pct / (loss_pct + PACKET_LOSS_ROBUSTNESS)
+}
+
+fn mos_from_r_factor(r_factor: f64) -> f64 {
+    if r_factor <= 0.0 {
+        1.0
+    } else if r_factor >= 100.0 {
+        4.5
+    } else {
+        1.0 + 0.035 * r_factor + 7e-6 * r_factor * (r_factor - 60.0) * (100.0 - r_factor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{estimate, mos_from_r_factor, UseCase, Verdict};
+    use crate::probe::{PingEngine, PingSummary};
+
+    fn ping(avg_ms: f64, jitter_ms: f64, packet_loss_pct: f64) -> PingSummary {
+        PingSummary {
+            engine: PingEngine::IcmpDatagram,
+            sent: 20,
+            received: 20,
+            packet_loss_pct,
+            min_ms: Some(avg_ms),
+            avg_ms: Some(avg_ms),
+            median_ms: Some(avg_ms),
+            p95_ms: Some(avg_ms),
+            max_ms: Some(avg_ms),
+            stddev_ms: Some(jitter_ms),
+            jitter_ms: Some(jitter_ms),
+            samples_ms: Vec::new(),
+            sequence: None,
+        }
+    }
+
+    #[test]
+    fn derives_r_factor_and_mos_from_ping_stats() {
+        let clean = estimate(&ping(20.0, 1.0, 0.0)).expect("latency should yield an estimate");
+        let lossy = estimate(&ping(180.0, 40.0, 8.0)).expect("latency should yield an estimate");
+
+        assert!((clean.one_way_delay_ms - 32.0).abs() < 1e-9);
+        assert!((clean.r_factor - 92.432).abs() < 1e-9);
+        assert!(clean.mos > 4.3 && clean.mos < 4.5);
+        assert!(lossy.r_factor < 70.0);
+        assert!(lossy.mos < 3.6);
+        assert_eq!(mos_from_r_factor(-5.0), 1.0);
+        assert_eq!(mos_from_r_factor(120.0), 4.5);
+    }
+
+    #[test]
+    fn grades_use_cases_against_their_thresholds() {
+        let quality = estimate(&ping(80.0, 12.0, 0.0)).expect("latency should yield an estimate");
+        let verdict = |use_case| {
+            quality
+                .verdicts
+                .iter()
+                .find(|verdict| verdict.use_case == use_case)
+                .map(|verdict| verdict.verdict)
+        };
+
+        assert_eq!(verdict(UseCase::Voip), Some(Verdict::Good));
+        assert_eq!(verdict(UseCase::VideoConferencing), Some(Verdict::Good));
+        assert_eq!(verdict(UseCase::Gaming), Some(Verdict::Fair));
+        assert_eq!(verdict(UseCase::Streaming), Some(Verdict::Good));
+
+        let mut unreachable = ping(0.0, 0.0, 100.0);
+        unreachable.avg_ms = None;
+        assert!(estimate(&unreachable).is_none());
+    }
+}
@@ -0,0 +1,232 @@
+use anyhow::{Context, Result};
+use bytes::Bytes;
+use futures_util::stream;
+use http_body_util::{combinators::BoxBody, BodyExt, Full, StreamBody};
+use hyper::body::{Frame, Incoming};
+use hyper::header::{CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, RANGE};
+use hyper::server::conn::http1;
+use hyper::service::service_fn;
+use hyper::{Method, Request, Response, StatusCode};
+use hyper_util::rt::TokioIo;
+use std::convert::Infallible;
+use tokio::net::TcpListener;
+
+pub const DOWNLOAD_PATH: &str = "/__down";
+pub const UPLOAD_PATH: &str = "/__up";
+pub const HEALTH_PATH: &str = "/health";
+
+const DEFAULT_DOWNLOAD_BYTES: u64 = 1_000_000;
+const MAX_DOWNLOAD_BYTES: u64 = 4_000_000_000;
+const DOWNLOAD_CHUNK_BYTES: usize = 64 * 1024;
+
+type ResponseBody = BoxBody<Bytes, Infallible>;
+
+pub async fn bind_server(bind: &str) -> Result<TcpListener> {
+    TcpListener::bind(bind)
+        .await
+        .with_context(|| format!("failed to bind throughput server on {bind}"))
+}
+
+pub async fn serve(listener: TcpListener) -> Result<()> {
+    let chunk = Bytes::from(vec![0_u8; DOWNLOAD_CHUNK_BYTES]);
+
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(accepted) => accepted,
+            Err(_) => continue,
+        };
+        let chunk = chunk.clone();
+
+        tokio::spawn(async move {
+            let service = service_fn(move |request| handle(request, chunk.clone()));
+            let _ = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await;
+        });
+    }
+}
+
+async fn handle(request: Request<Incoming>, chunk: Bytes) -> Result<Response<ResponseBody>> {
+    match (request.method(), request.uri().path()) {
+        (&Method::GET, DOWNLOAD_PATH) => Ok(download(&request, chunk)),
+        (&Method::POST, UPLOAD_PATH) => upload(request).await,
+        (&Method::GET, HEALTH_PATH) => Ok(text_response(
+            StatusCode::OK,
+            "application/json",
+            format!(
+                "{{\"status\":\"ok\",\"version\":\"{}\"}}",
+                env!("CARGO_PKG_VERSION")
+            ),
+        )),
+        _ => Ok(text_response(
+            StatusCode::NOT_FOUND,
+            "text/plain",
+            "not found".to_string(),
+        )),
+    }
+}
+
+fn download(request: &Request<Incoming>, chunk: Bytes) -> Response<ResponseBody> {
+    let total_bytes = request
+        .uri()
+        .query()
+        .and_then(requested_bytes)
+        .unwrap_or(DEFAULT_DOWNLOAD_BYTES)
+        .min(MAX_DOWNLOAD_BYTES);
+    let range = request
+        .headers()
+        .get(RANGE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| parse_range(value, total_bytes));
+
+    let mut response = Response::builder().header(CONTENT_TYPE, "application/octet-stream");
+    let length = match range {
+        Some((start, end)) => {
+            response = response
+                .status(StatusCode::PARTIAL_CONTENT)
+                .header(CONTENT_RANGE, format!("bytes {start}-{end}/{total_bytes}"));
+            end - start + 1
+        }
+        None => total_bytes,
+    };
+
+    response
+        .header(CONTENT_LENGTH, length)
+        .body(zero_body(length, chunk))
+        .expect("download response is valid")
+}
+
+async fn upload(request: Request<Incoming>) -> Result<Response<ResponseBody>> {
+    let mut body = request.into_body();
+    let mut received = 0_u64;
+
+    while let Some(frame) = body.frame().await {
+        let frame = frame.context("failed to read upload body")?;
+        if let Some(data) = frame.data_ref() {
+            received += data.len() as u64;
+        }
+    }
+
+    Ok(text_response(
+        StatusCode::OK,
+        "application/json",
+        format!("{{\"bytes\":{received}}}"),
+    ))
+}
+
+fn zero_body(length: u64, chunk: Bytes) -> ResponseBody {
+    let chunk_len = chunk.len() as u64;
+    let frames = stream::iter((0..length.div_ceil(chunk_len)).map(move |index| {
+        let remaining = length - index * chunk_len;
+        Ok(Frame::data(
+            chunk.slice(..remaining.min(chunk_len) as usize),
+        ))
+    }));
+
+    StreamBody::new(frames).boxed()
+}
+
+fn text_response(status: StatusCode, content_type: &str, body: String) -> Response<ResponseBody> {
+    Response::builder()
+        .status(status)
+        .header(CONTENT_TYPE, content_type)
+        .body(Full::new(Bytes::from(body)).boxed())
+        .expect("text response is valid")
+}
+
+fn requested_bytes(query: &str) -> Option<u64> {
+    query
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("bytes="))
+        .and_then(|value| value.parse().ok())
+}
+
+fn parse_range(value: &str, total_bytes: u64) -> Option<(u64, u64)> {
+    let (start, end) = value.strip_prefix("bytes=")?.split_once('-')?;
+    let start = start.trim().parse::<u64>().ok()?;
+    let end = match end.trim() {
+        "" => total_bytes.checked_sub(1)?,
+        end => end.parse::<u64>().ok()?.min(total_bytes.checked_sub(1)?),
+    };
+
+    (start <= end).then_some((start, end))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{bind_server, parse_range, requested_bytes, serve, HEALTH_PATH};
+    use reqwest::Client;
+
+    #[test]
+    fn parses_requested_download_size() {
+        assert_eq!(requested_bytes("bytes=2048"), Some(2_048));
+        assert_eq!(requested_bytes("cache=1&bytes=10"), Some(10));
+        assert_eq!(requested_bytes("cache=1"), None);
+    }
+
+    #[test]
+    fn parses_single_byte_ranges() {
+        assert_eq!(parse_range("bytes=0-1023", 4_096), Some((0, 1_023)));
+        assert_eq!(parse_range("bytes=100-", 200), Some((100, 199)));
+        assert_eq!(parse_range("bytes=0-9999", 100), Some((0, 99)));
+        assert_eq!(parse_range("bytes=50-10", 100), None);
+        assert_eq!(parse_range("items=0-1", 100), None);
+    }
+
+    #[tokio::test]
+    async fn serves_sized_downloads_ranges_and_upload_sink() {
+        let listener = bind_server("127.0.0.1:0")
+            .await
+            .expect("server should bind");
+        let address = listener
+            .local_addr()
+            .expect("server should have an address");
+        tokio::spawn(serve(listener));
+        let client = Client::new();
+        let base = format!("http://{address}");
+
+        let download = client
+            .get(format!("{base}/__down?bytes=200000"))
+            .send()
+            .await
+            .expect("download should respond")
+            .bytes()
+            .await
+            .expect("download body should stream");
+        assert_eq!(download.len(), 200_000);
+
+        let partial = client
+            .get(format!("{base}/__down?bytes=200000"))
+            .header("Range", "bytes=0-1023")
+            .send()
+            .await
+            .expect("range request should respond");
+        assert_eq!(partial.status(), 206);
+        assert_eq!(
+            partial
+                .bytes()
+                .await
+                .expect("range body should stream")
+                .len(),
+            1_024
+        );
+
+        let upload = client
+            .post(format!("{base}/__up"))
+            .body(vec![b'x'; 50_000])
+            .send()
+            .await
+            .expect("upload should respond")
+            .text()
+            .await
+            .expect("upload response should read");
+        assert_eq!(upload, "{\"bytes\":50000}");
+
+        let health = client
+            .get(format!("{base}{HEALTH_PATH}"))
+            .send()
+            .await
+            .expect("health should respond");
+        assert!(health.status().is_success());
+    }
+}
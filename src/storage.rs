@@ -4,7 +4,11 @@ use std::env;
 use std::fs;
 use std::path::{Path, PathBuf};
 
-use crate::probe::ProbeReport;
+use crate::probe::{
+    AddressFamily, BandwidthSummary, FamilyReport, MeasurementProfile, MetricStats, PingConfig,
+    ProbeReport, TcpSummary, TlsSummary, UdpSummary, UploadPayload,
+};
+use crate::quality::{self, QualityEstimate, UseCase, Verdict};
 
 const APP_DIR_NAME: &str = ".pantheon-probe";
 const RUNS_DIR_NAME: &str = "runs";
@@ -23,9 +27,87 @@ pub struct RunComparison {
     pub ping_avg_delta_ms: Option<f64>,
     pub ping_median_delta_ms: Option<f64>,
     pub packet_loss_delta_pct: Option<f64>,
+    pub r_factor_delta: Option<f64>,
+    pub mos_delta: Option<f64>,
     pub dns_delta_ms: Option<f64>,
+    pub tcp_avg_delta_ms: Option<f64>,
+    pub tcp_median_delta_ms: Option<f64>,
+    pub tcp_loss_delta_pct: Option<f64>,
+    pub udp_jitter_delta_ms: Option<f64>,
+    pub udp_loss_delta_pct: Option<f64>,
     pub download_delta_mbps: Option<f64>,
     pub upload_delta_mbps: Option<f64>,
+    pub responsiveness_delta_rpm: Option<f64>,
+    pub families: Vec<FamilyComparison>,
+    pub changes: Vec<RunChange>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FamilyComparison {
+    pub family: AddressFamily,
+    pub ping_median_delta_ms: Option<f64>,
+    pub packet_loss_delta_pct: Option<f64>,
+    pub tcp_median_delta_ms: Option<f64>,
+    pub download_delta_mbps: Option<f64>,
+    pub upload_delta_mbps: Option<f64>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum RunChange {
+    ProfileMismatch {
+        previous: MeasurementProfile,
+        current: MeasurementProfile,
+    },
+    ProviderMismatch {
+        previous: String,
+        current: String,
+    },
+    PingConfigMismatch {
+        previous: PingConfig,
+        current: PingConfig,
+    },
+    UploadPayloadMismatch {
+        previous: UploadPayload,
+        current: UploadPayload,
+    },
+    DnsAddressesChanged {
+        added: Vec<String>,
+        removed: Vec<String>,
+    },
+    EndpointSwitched {
+        previous: String,
+        current: String,
+    },
+    EndpointAddressChanged {
+        endpoint: String,
+        previous: String,
+        current: String,
+    },
+    CandidateHealthChanged {
+        name: String,
+        healthy: bool,
+        error: Option<String>,
+    },
+    PathMtuChanged {
+        previous: u32,
+        current: u32,
+    },
+    FamilyReachabilityChanged {
+        family: AddressFamily,
+        reachable: bool,
+    },
+    CertificateRotated {
+        previous_serial: String,
+        current_serial: String,
+        issuer: String,
+        days_until_expiry: i64,
+    },
+    QualityVerdictChanged {
+        use_case: UseCase,
+        previous: Verdict,
+        current: Verdict,
+    },
 }
 
 #[derive(Debug, Clone, Serialize)]
@@ -158,6 +240,14 @@ pub fn compare_reports(previous: &ProbeReport, current: &ProbeReport) -> RunComp
                 .as_ref()
                 .map(|value| value.packet_loss_pct),
         ),
+        r_factor_delta: difference(
+            quality_estimate(previous).map(|value| value.r_factor),
+            quality_estimate(current).map(|value| value.r_factor),
+        ),
+        mos_delta: difference(
+            quality_estimate(previous).map(|value| value.mos),
+            quality_estimate(current).map(|value| value.mos),
+        ),
         dns_delta_ms: difference(
             previous
                 .dns
@@ -170,6 +260,26 @@ pub fn compare_reports(previous: &ProbeReport, current: &ProbeReport) -> RunComp
                 .as_ref()
                 .map(|value| value.resolution_time_ms),
         ),
+        tcp_avg_delta_ms: difference(
+            tcp_connect_stat(previous, |stats| stats.mean),
+            tcp_connect_stat(current, |stats| stats.mean),
+        ),
+        tcp_median_delta_ms: difference(
+            tcp_connect_stat(previous, |stats| stats.median),
+            tcp_connect_stat(current, |stats| stats.median),
+        ),
+        tcp_loss_delta_pct: difference(
+            tcp_summary(previous).map(|value| value.loss_pct),
+            tcp_summary(current).map(|value| value.loss_pct),
+        ),
+        udp_jitter_delta_ms: difference(
+            udp_summary(previous).and_then(|value| value.jitter_ms),
+            udp_summary(current).and_then(|value| value.jitter_ms),
+        ),
+        udp_loss_delta_pct: difference(
+            udp_summary(previous).map(|value| value.loss_pct),
+            udp_summary(current).map(|value| value.loss_pct),
+        ),
         download_delta_mbps: difference(
             previous
                 .bandwidth
@@ -194,7 +304,220 @@ pub fn compare_reports(previous: &ProbeReport, current: &ProbeReport) -> RunComp
                 .as_ref()
                 .map(|value| value.upload_mbps),
         ),
+        responsiveness_delta_rpm: difference(
+            responsiveness_rpm(previous),
+            responsiveness_rpm(current),
+        ),
+        families: compare_families(previous, current),
+        changes: detect_changes(previous, current),
+    }
+}
+
+fn compare_families(previous: &ProbeReport, current: &ProbeReport) -> Vec<FamilyComparison> {
+    current
+        .families
+        .iter()
+        .filter_map(|current_family| {
+            let previous_family = previous
+                .families
+                .iter()
+                .find(|previous_family| previous_family.family == current_family.family)?;
+            let stat = |value: fn(&FamilyReport) -> Option<f64>| {
+                difference(value(previous_family), value(current_family))
+            };
+            Some(FamilyComparison {
+                family: current_family.family,
+                ping_median_delta_ms: stat(|family| family.ping.value.as_ref()?.median_ms),
+                packet_loss_delta_pct: stat(|family| {
+                    Some(family.ping.value.as_ref()?.packet_loss_pct)
+                }),
+                tcp_median_delta_ms: stat(|family| {
+                    let tcp = family.tcp.as_ref()?.value.as_ref()?;
+                    Some(tcp.connect.as_ref()?.median)
+                }),
+                download_delta_mbps: stat(|family| {
+                    Some(family.bandwidth.value.as_ref()?.download_mbps)
+                }),
+                upload_delta_mbps: stat(|family| {
+                    Some(family.bandwidth.value.as_ref()?.upload_mbps)
+                }),
+            })
+        })
+        .collect()
+}
+
+fn detect_changes(previous: &ProbeReport, current: &ProbeReport) -> Vec<RunChange> {
+    let mut changes = Vec::new();
+
+    if previous.profile != current.profile {
+        changes.push(RunChange::ProfileMismatch {
+            previous: previous.profile,
+            current: current.profile,
+        });
+    }
+    if previous.bandwidth_provider != current.bandwidth_provider {
+        changes.push(RunChange::ProviderMismatch {
+            previous: previous.bandwidth_provider.clone(),
+            current: current.bandwidth_provider.clone(),
+        });
+    }
+
+    if previous.ping_config != current.ping_config {
+        changes.push(RunChange::PingConfigMismatch {
+            previous: previous.ping_config,
+            current: current.ping_config,
+        });
+    }
+
+    if let (Some(previous_quality), Some(current_quality)) =
+        (quality_estimate(previous), quality_estimate(current))
+    {
+        for current_verdict in &current_quality.verdicts {
+            let Some(previous_verdict) = previous_quality
+                .verdicts
+                .iter()
+                .find(|verdict| verdict.use_case == current_verdict.use_case)
+            else {
+                continue;
+            };
+            if previous_verdict.verdict != current_verdict.verdict {
+                changes.push(RunChange::QualityVerdictChanged {
+                    use_case: current_verdict.use_case,
+                    previous: previous_verdict.verdict,
+                    current: current_verdict.verdict,
+                });
+            }
+        }
+    }
+
+    if let (Some(previous_dns), Some(current_dns)) = (&previous.dns.value, &current.dns.value) {
+        let added = current_dns
+            .addresses
+            .iter()
+            .filter(|address| !previous_dns.addresses.contains(address))
+            .cloned()
+            .collect::<Vec<_>>();
+        let removed = previous_dns
+            .addresses
+            .iter()
+            .filter(|address| !current_dns.addresses.contains(address))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !added.is_empty() || !removed.is_empty() {
+            changes.push(RunChange::DnsAddressesChanged { added, removed });
+        }
+    }
+
+    if let (Some(previous_mtu), Some(current_mtu)) = (path_mtu(previous), path_mtu(current)) {
+        if previous_mtu != current_mtu {
+            changes.push(RunChange::PathMtuChanged {
+                previous: previous_mtu,
+                current: current_mtu,
+            });
+        }
+    }
+
+    if let (Some(previous_tls), Some(current_tls)) = (tls_summary(previous), tls_summary(current)) {
+        if let (Some(previous_leaf), Some(current_leaf)) = (previous_tls.leaf(), current_tls.leaf())
+        {
+            if previous_leaf.serial != current_leaf.serial {
+                changes.push(RunChange::CertificateRotated {
+                    previous_serial: previous_leaf.serial.clone(),
+                    current_serial: current_leaf.serial.clone(),
+                    issuer: current_leaf.issuer.clone(),
+                    days_until_expiry: current_tls.days_until_expiry,
+                });
+            }
+        }
+    }
+
+    for current_family in &current.families {
+        let Some(previous_family) = previous
+            .families
+            .iter()
+            .find(|previous_family| previous_family.family == current_family.family)
+        else {
+            continue;
+        };
+        let reachable = family_reachable(current_family);
+        if reachable != family_reachable(previous_family) {
+            changes.push(RunChange::FamilyReachabilityChanged {
+                family: current_family.family,
+                reachable,
+            });
+        }
+    }
+
+    if let (Some(previous_bandwidth), Some(current_bandwidth)) =
+        (&previous.bandwidth.value, &current.bandwidth.value)
+    {
+        if previous_bandwidth.upload_payload != current_bandwidth.upload_payload {
+            changes.push(RunChange::UploadPayloadMismatch {
+                previous: previous_bandwidth.upload_payload,
+                current: current_bandwidth.upload_payload,
+            });
+        }
+        if previous_bandwidth.endpoint != current_bandwidth.endpoint {
+            changes.push(RunChange::EndpointSwitched {
+                previous: previous_bandwidth.endpoint.clone(),
+                current: current_bandwidth.endpoint.clone(),
+            });
+        } else if let (Some(previous_address), Some(current_address)) = (
+            selected_endpoint_address(previous_bandwidth),
+            selected_endpoint_address(current_bandwidth),
+        ) {
+            if previous_address != current_address {
+                changes.push(RunChange::EndpointAddressChanged {
+                    endpoint: current_bandwidth.endpoint.clone(),
+                    previous: previous_address.to_string(),
+                    current: current_address.to_string(),
+                });
+            }
+        }
+
+        for candidate in &current_bandwidth.endpoint_candidates {
+            let Some(previous_candidate) = previous_bandwidth
+                .endpoint_candidates
+                .iter()
+                .find(|previous_candidate| previous_candidate.name == candidate.name)
+            else {
+                continue;
+            };
+            let healthy = candidate.latency_ms.is_some();
+            if healthy != previous_candidate.latency_ms.is_some() {
+                changes.push(RunChange::CandidateHealthChanged {
+                    name: candidate.name.clone(),
+                    healthy,
+                    error: candidate.error.clone(),
+                });
+            }
+        }
     }
+
+    changes
+}
+
+fn family_reachable(family: &FamilyReport) -> bool {
+    family
+        .ping
+        .value
+        .as_ref()
+        .is_some_and(|ping| ping.received > 0)
+        || family
+            .tcp
+            .as_ref()
+            .and_then(|tcp| tcp.value.as_ref())
+            .is_some_and(|tcp| tcp.connected > 0)
+        || family.bandwidth.value.is_some()
+}
+
+fn selected_endpoint_address(bandwidth: &BandwidthSummary) -> Option<&str> {
+    bandwidth
+        .endpoint_candidates
+        .iter()
+        .find(|candidate| candidate.name == bandwidth.endpoint)?
+        .remote_address
+        .as_deref()
 }
 
 pub fn format_history(runs: &[StoredRun]) -> String {
@@ -275,7 +598,7 @@ pub fn format_compared_runs(compared_runs: &ComparedRuns) -> String {
 }
 
 pub fn format_comparison(comparison: &RunComparison) -> String {
-    [
+    let mut lines = vec![
         format!(
             "Compared with run {} ({})",
             comparison.previous_run_id, comparison.previous_created_at_unix_ms
@@ -292,10 +615,35 @@ pub fn format_comparison(comparison: &RunComparison) -> String {
             "  packet loss delta: {}",
             format_signed(comparison.packet_loss_delta_pct, "pct")
         ),
+        format!(
+            "  R-factor/MOS delta: {}/{}",
+            format_signed(comparison.r_factor_delta, ""),
+            format_signed(comparison.mos_delta, "")
+        ),
         format!(
             "  dns delta: {}",
             format_signed(comparison.dns_delta_ms, "ms")
         ),
+        format!(
+            "  tcp connect avg delta: {}",
+            format_signed(comparison.tcp_avg_delta_ms, "ms")
+        ),
+        format!(
+            "  tcp connect median delta: {}",
+            format_signed(comparison.tcp_median_delta_ms, "ms")
+        ),
+        format!(
+            "  tcp connect loss delta: {}",
+            format_signed(comparison.tcp_loss_delta_pct, "pct")
+        ),
+        format!(
+            "  udp jitter delta: {}",
+            format_signed(comparison.udp_jitter_delta_ms, "ms")
+        ),
+        format!(
+            "  udp loss delta: {}",
+            format_signed(comparison.udp_loss_delta_pct, "pct")
+        ),
         format!(
             "  download delta: {}",
             format_signed(comparison.download_delta_mbps, "Mbps")
@@ -304,8 +652,109 @@ pub fn format_comparison(comparison: &RunComparison) -> String {
             "  upload delta: {}",
             format_signed(comparison.upload_delta_mbps, "Mbps")
         ),
-    ]
-    .join("\n")
+        format!(
+            "  responsiveness delta: {}",
+            format_signed(comparison.responsiveness_delta_rpm, "RPM")
+        ),
+    ];
+
+    lines.extend(comparison.families.iter().map(|family| {
+        format!(
+            "  {} deltas: ping median {} | loss {} | tcp connect median {} | download {} | upload {}",
+            family.family,
+            format_signed(family.ping_median_delta_ms, "ms"),
+            format_signed(family.packet_loss_delta_pct, "pct"),
+            format_signed(family.tcp_median_delta_ms, "ms"),
+            format_signed(family.download_delta_mbps, "Mbps"),
+            format_signed(family.upload_delta_mbps, "Mbps"),
+        )
+    }));
+
+    if comparison.changes.is_empty() {
+        lines.push("  changes: none".to_string());
+    } else {
+        lines.push("  changes:".to_string());
+        lines.extend(
+            comparison
+                .changes
+                .iter()
+                .map(|change| format!("    - {}", format_change(change))),
+        );
+    }
+
+    lines.join("\n")
+}
+
+fn format_change(change: &RunChange) -> String {
+    match change {
+        RunChange::ProfileMismatch { previous, current } => {
+            format!("profile differs: {previous} -> {current}")
+        }
+        RunChange::ProviderMismatch { previous, current } => {
+            format!("bandwidth provider differs: {previous} -> {current}")
+        }
+        RunChange::PingConfigMismatch { previous, current } => {
+            format!("ping settings differ: {previous} -> {current}")
+        }
+        RunChange::UploadPayloadMismatch { previous, current } => {
+            format!("upload payload differs: {previous} -> {current}")
+        }
+        RunChange::DnsAddressesChanged { added, removed } => {
+            let mut parts = Vec::new();
+            if !added.is_empty() {
+                parts.push(format!("added {}", added.join(", ")));
+            }
+            if !removed.is_empty() {
+                parts.push(format!("removed {}", removed.join(", ")));
+            }
+            format!("dns addresses changed: {}", parts.join("; "))
+        }
+        RunChange::QualityVerdictChanged {
+            use_case,
+            previous,
+            current,
+        } => format!("{use_case} quality verdict changed: {previous} -> {current}"),
+        RunChange::PathMtuChanged { previous, current } => {
+            format!("path MTU changed: {previous} -> {current} bytes")
+        }
+        RunChange::FamilyReachabilityChanged {
+            family,
+            reachable: true,
+        } => format!("{family} became reachable"),
+        RunChange::FamilyReachabilityChanged {
+            family,
+            reachable: false,
+        } => format!("{family} became unreachable"),
+        RunChange::CertificateRotated {
+            previous_serial,
+            current_serial,
+            issuer,
+            days_until_expiry,
+        } => format!(
+            "TLS certificate rotated: serial {previous_serial} -> {current_serial} ({issuer}, expires in {days_until_expiry} days)"
+        ),
+        RunChange::EndpointSwitched { previous, current } => {
+            format!("bandwidth endpoint switched: {previous} -> {current}")
+        }
+        RunChange::EndpointAddressChanged {
+            endpoint,
+            previous,
+            current,
+        } => format!("endpoint {endpoint} address changed: {previous} -> {current}"),
+        RunChange::CandidateHealthChanged {
+            name,
+            healthy: true,
+            ..
+        } => format!("endpoint candidate {name} recovered"),
+        RunChange::CandidateHealthChanged {
+            name,
+            healthy: false,
+            error,
+        } => format!(
+            "endpoint candidate {name} started failing: {}",
+            error.as_deref().unwrap_or("unknown error")
+        ),
+    }
 }
 
 pub fn export_runs_json(runs: &[StoredRun]) -> Result<String> {
@@ -314,12 +763,12 @@ pub fn export_runs_json(runs: &[StoredRun]) -> Result<String> {
 
 pub fn export_runs_csv(runs: &[StoredRun]) -> String {
     let mut output = String::from(
-        "created_at_unix_ms,target,profile,bandwidth_provider,bandwidth_endpoint,ping_avg_ms,ping_median_ms,ping_p95_ms,packet_loss_pct,dns_resolution_ms,download_mbps,upload_mbps,calibrated_download_size_bytes,calibrated_upload_size_bytes,bandwidth_elapsed_ms\n",
+        "created_at_unix_ms,target,profile,bandwidth_provider,bandwidth_endpoint,ping_avg_ms,ping_median_ms,ping_p95_ms,packet_loss_pct,dns_resolution_ms,download_mbps,upload_mbps,calibrated_download_size_bytes,calibrated_upload_size_bytes,bandwidth_elapsed_ms,download_success_ratio,upload_success_ratio,tcp_port,tcp_connect_avg_ms,tcp_connect_median_ms,tcp_connect_p95_ms,tcp_connect_loss_pct,responsiveness_rpm\n",
     );
 
     for run in runs {
         output.push_str(&format!(
-            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
+            "{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{},{}\n",
             run.report.created_at_unix_ms,
             csv_escape(&run.target),
             run.report.profile,
@@ -400,6 +849,28 @@ pub fn export_runs_csv(runs: &[StoredRun]) -> String {
                     .as_ref()
                     .map(|value| value.bandwidth_elapsed_ms)
             ),
+            csv_number(
+                run.report
+                    .bandwidth
+                    .value
+                    .as_ref()
+                    .map(BandwidthSummary::download_success_ratio)
+            ),
+            csv_number(
+                run.report
+                    .bandwidth
+                    .value
+                    .as_ref()
+                    .map(BandwidthSummary::upload_success_ratio)
+            ),
+            tcp_summary(&run.report)
+                .map(|value| value.port.to_string())
+                .unwrap_or_default(),
+            csv_number(tcp_connect_stat(&run.report, |stats| stats.mean)),
+            csv_number(tcp_connect_stat(&run.report, |stats| stats.median)),
+            csv_number(tcp_connect_stat(&run.report, |stats| stats.p95)),
+            csv_number(tcp_summary(&run.report).map(|value| value.loss_pct)),
+            csv_number(responsiveness_rpm(&run.report)),
         ));
     }
 
@@ -455,6 +926,39 @@ fn sanitize_target(target: &str) -> String {
     sanitized.trim_matches('-').to_lowercase()
 }
 
+// Runs saved before the estimate was stored still carry the ping stats it
+// derives from.
+fn quality_estimate(report: &ProbeReport) -> Option<QualityEstimate> {
+    report
+        .quality
+        .clone()
+        .or_else(|| report.ping.value.as_ref().and_then(quality::estimate))
+}
+
+fn tcp_summary(report: &ProbeReport) -> Option<&TcpSummary> {
+    report.tcp.as_ref()?.value.as_ref()
+}
+
+fn tls_summary(report: &ProbeReport) -> Option<&TlsSummary> {
+    report.tls.as_ref()?.value.as_ref()
+}
+
+fn udp_summary(report: &ProbeReport) -> Option<&UdpSummary> {
+    report.udp.as_ref()?.value.as_ref()
+}
+
+fn path_mtu(report: &ProbeReport) -> Option<u32> {
+    Some(report.pmtu.as_ref()?.value.as_ref()?.mtu_bytes)
+}
+
+fn responsiveness_rpm(report: &ProbeReport) -> Option<f64> {
+    Some(report.responsiveness.as_ref()?.value.as_ref()?.rpm)
+}
+
+fn tcp_connect_stat(report: &ProbeReport, stat: impl Fn(&MetricStats) -> f64) -> Option<f64> {
+    tcp_summary(report)?.connect.as_ref().map(stat)
+}
+
 fn difference(previous: Option<f64>, current: Option<f64>) -> Option<f64> {
     Some(current? - previous?)
 }
@@ -467,7 +971,7 @@ fn format_optional(value: Option<f64>, unit: &str) -> String {
 
 fn format_signed(value: Option<f64>, unit: &str) -> String {
     value
-        .map(|value| format!("{value:+.2} {unit}"))
+        .map(|value| format!("{value:+.2} {unit}").trim_end().to_string())
         .unwrap_or_else(|| "unavailable".to_string())
 }
 
@@ -487,12 +991,16 @@ fn csv_escape(value: &str) -> String {
 mod tests {
     use super::{
         build_run_id, compare_reports, csv_escape, export_runs_csv, format_compared_runs,
-        format_history, sanitize_target, ComparedRuns, StoredRun,
+        format_comparison, format_history, sanitize_target, ComparedRuns, RunChange, StoredRun,
     };
     use crate::probe::{
-        BandwidthProviderPreset, BandwidthSummary, DnsSummary, MeasurementProfile, MetricStats,
-        PingSummary, ProbeOutcome, ProbeReport, TransferSample,
+        AddressFamily, BandwidthProviderPreset, BandwidthSummary, DnsSummary, DownloadSizing,
+        EndpointHealth, FamilyMode, FamilyReport, MeasurementProfile, MetricStats, PingConfig,
+        PingEngine, PingSummary, ProbeOutcome, ProbeReport, ResponsivenessSummary, TcpSummary,
+        TlsSummary, TransferSample, UploadPayload,
     };
+    use crate::quality::{UseCase, Verdict};
+    use crate::x509::Certificate;
 
     #[test]
     fn sanitizes_targets_for_storage_ids() {
@@ -508,6 +1016,8 @@ mod tests {
 
         assert!(csv.contains("created_at_unix_ms,target"));
         assert!(csv.contains("42,example.com,"));
+        assert!(csv.contains(",download_success_ratio,upload_success_ratio,tcp_port,"));
+        assert!(csv.contains(",1.0000,1.0000,443,22.0000,21.0000,25.0000,0.0000,600.0000\n"));
     }
 
     #[test]
@@ -535,9 +1045,271 @@ mod tests {
             ping.packet_loss_pct = 5.0;
         }
 
+        if let Some(tcp) = current
+            .tcp
+            .as_mut()
+            .and_then(|outcome| outcome.value.as_mut())
+        {
+            tcp.loss_pct = 20.0;
+        }
+
+        if let Some(responsiveness) = current
+            .responsiveness
+            .as_mut()
+            .and_then(|outcome| outcome.value.as_mut())
+        {
+            responsiveness.rpm = 450.0;
+        }
+
         let comparison = compare_reports(&previous, &current);
         assert!(comparison.ping_avg_delta_ms.is_some());
         assert!(comparison.packet_loss_delta_pct.is_some());
+        assert_eq!(comparison.tcp_loss_delta_pct, Some(20.0));
+        assert_eq!(comparison.tcp_avg_delta_ms, Some(0.0));
+        assert_eq!(comparison.responsiveness_delta_rpm, Some(-150.0));
+        assert!(comparison.mos_delta.expect("MOS should compare") < 0.0);
+        assert!(comparison
+            .changes
+            .contains(&RunChange::QualityVerdictChanged {
+                use_case: UseCase::Voip,
+                previous: Verdict::Good,
+                current: Verdict::Poor,
+            }));
+        assert!(
+            format_comparison(&comparison).contains("VoIP quality verdict changed: good -> poor")
+        );
+    }
+
+    #[test]
+    fn reports_what_changed_between_runs() {
+        let candidate = |name: &str, error: Option<&str>| EndpointHealth {
+            name: name.to_string(),
+            download_url: format!("https://{name}.example.test/down"),
+            upload_url: format!("https://{name}.example.test/up"),
+            latency_ms: error.is_none().then_some(30.0),
+            error: error.map(str::to_string),
+            status: error.is_none().then_some(206),
+            remote_address: None,
+            timing: None,
+        };
+        let mut previous = fixture_run(1).report;
+        let mut current = fixture_run(2).report;
+        current.profile = MeasurementProfile::Full;
+        current.ping_config.payload_bytes = 1_400;
+        if let Some(dns) = current.dns.value.as_mut() {
+            dns.addresses = vec!["93.184.216.35".to_string()];
+        }
+        if let Some(bandwidth) = previous.bandwidth.value.as_mut() {
+            bandwidth.endpoint_candidates = vec![candidate("global", None), candidate("eu", None)];
+        }
+        if let Some(bandwidth) = current.bandwidth.value.as_mut() {
+            bandwidth.endpoint = "eu".to_string();
+            bandwidth.endpoint_candidates = vec![
+                candidate("global", Some("timed out")),
+                candidate("eu", None),
+            ];
+        }
+
+        let comparison = compare_reports(&previous, &current);
+
+        assert_eq!(
+            comparison.changes,
+            vec![
+                RunChange::ProfileMismatch {
+                    previous: MeasurementProfile::Standard,
+                    current: MeasurementProfile::Full,
+                },
+                RunChange::PingConfigMismatch {
+                    previous: PingConfig::default(),
+                    current: PingConfig {
+                        payload_bytes: 1_400,
+                        ..PingConfig::default()
+                    },
+                },
+                RunChange::DnsAddressesChanged {
+                    added: vec!["93.184.216.35".to_string()],
+                    removed: vec!["93.184.216.34".to_string()],
+                },
+                RunChange::EndpointSwitched {
+                    previous: "global".to_string(),
+                    current: "eu".to_string(),
+                },
+                RunChange::CandidateHealthChanged {
+                    name: "global".to_string(),
+                    healthy: false,
+                    error: Some("timed out".to_string()),
+                },
+            ]
+        );
+        let formatted = format_comparison(&comparison);
+        assert!(formatted.contains("bandwidth endpoint switched: global -> eu"));
+        assert!(formatted.contains("endpoint candidate global started failing: timed out"));
+        let json = serde_json::to_value(&comparison).expect("comparison should serialize");
+        assert!(formatted.contains(
+            "ping settings differ: 56-byte payload, 1000 ms interval, 1000 ms timeout, default TTL -> 1400-byte payload"
+        ));
+        assert_eq!(json["changes"][1]["kind"], "ping_config_mismatch");
+        assert_eq!(json["changes"][2]["kind"], "dns_addresses_changed");
+    }
+
+    #[test]
+    fn flags_endpoint_address_changes_seen_by_the_health_check() {
+        let candidate = |remote_address: &str| EndpointHealth {
+            name: "global".to_string(),
+            download_url: "https://global.example.test/down".to_string(),
+            upload_url: "https://global.example.test/up".to_string(),
+            latency_ms: Some(30.0),
+            error: None,
+            status: Some(206),
+            remote_address: Some(remote_address.to_string()),
+            timing: None,
+        };
+        let mut previous = fixture_run(1_000).report;
+        let mut current = fixture_run(2_000).report;
+        if let Some(bandwidth) = previous.bandwidth.value.as_mut() {
+            bandwidth.endpoint_candidates = vec![candidate("104.16.0.1:443")];
+        }
+        if let Some(bandwidth) = current.bandwidth.value.as_mut() {
+            bandwidth.endpoint_candidates = vec![candidate("104.16.0.2:443")];
+        }
+
+        let comparison = compare_reports(&previous, &current);
+
+        assert_eq!(
+            comparison.changes,
+            vec![RunChange::EndpointAddressChanged {
+                endpoint: "global".to_string(),
+                previous: "104.16.0.1:443".to_string(),
+                current: "104.16.0.2:443".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn flags_upload_payload_mismatch_with_older_runs() {
+        let mut previous = fixture_run(1_000).report;
+        let current = fixture_run(2_000).report;
+        if let Some(bandwidth) = previous.bandwidth.value.as_mut() {
+            bandwidth.upload_payload = UploadPayload::RepeatedByte;
+        }
+
+        let comparison = compare_reports(&previous, &current);
+
+        assert_eq!(
+            comparison.changes,
+            vec![RunChange::UploadPayloadMismatch {
+                previous: UploadPayload::RepeatedByte,
+                current: UploadPayload::Random,
+            }]
+        );
+        assert!(format_comparison(&comparison)
+            .contains("upload payload differs: repeated-byte -> random"));
+    }
+
+    #[test]
+    fn flags_tls_certificate_rotation() {
+        let tls_run = |created_at_unix_ms, serial: &str| {
+            let mut report = fixture_run(created_at_unix_ms).report;
+            report.tls = Some(ProbeOutcome {
+                value: Some(TlsSummary {
+                    address: "93.184.216.34".to_string(),
+                    port: 443,
+                    server_name: "example.com".to_string(),
+                    connect_ms: 20.0,
+                    handshake_ms: 45.0,
+                    protocol: "TLSv1.3".to_string(),
+                    cipher: "TLS13_AES_128_GCM_SHA256".to_string(),
+                    alpn: Some("h2".to_string()),
+                    days_until_expiry: 89,
+                    validation_error: None,
+                    chain: vec![Certificate {
+                        subject: "CN=example.com".to_string(),
+                        issuer: "C=US, O=Let's Encrypt, CN=R11".to_string(),
+                        serial: serial.to_string(),
+                        not_before_unix_s: 1_790_000_000,
+                        not_after_unix_s: 1_797_776_000,
+                        subject_alt_names: vec!["example.com".to_string()],
+                    }],
+                    chain_errors: Vec::new(),
+                }),
+                error: None,
+            });
+            report
+        };
+
+        let unchanged = compare_reports(&tls_run(1, "0a:01"), &tls_run(2, "0a:01"));
+        assert!(unchanged.changes.is_empty());
+
+        let rotated = compare_reports(&tls_run(1, "0a:01"), &tls_run(2, "0b:02"));
+        assert_eq!(
+            rotated.changes,
+            vec![RunChange::CertificateRotated {
+                previous_serial: "0a:01".to_string(),
+                current_serial: "0b:02".to_string(),
+                issuer: "C=US, O=Let's Encrypt, CN=R11".to_string(),
+                days_until_expiry: 89,
+            }]
+        );
+        assert!(
+            format_comparison(&rotated).contains("TLS certificate rotated: serial 0a:01 -> 0b:02")
+        );
+    }
+
+    #[test]
+    fn compares_address_families_side_by_side() {
+        fn unreachable<T>() -> ProbeOutcome<T> {
+            ProbeOutcome {
+                value: None,
+                error: Some("network unreachable".to_string()),
+            }
+        }
+        let family_run = |created_at_unix_ms, v6_ping_ms: Option<f64>| {
+            let mut report = fixture_run(created_at_unix_ms).report;
+            report.family = Some(FamilyMode::Both);
+            let mut v6_ping = report.ping.clone();
+            match v6_ping_ms {
+                Some(median) => {
+                    if let Some(ping) = v6_ping.value.as_mut() {
+                        ping.median_ms = Some(median);
+                    }
+                }
+                None => v6_ping = unreachable(),
+            }
+            report.families = vec![
+                FamilyReport {
+                    family: AddressFamily::V4,
+                    address: Some("93.184.216.34".to_string()),
+                    ping: report.ping.clone(),
+                    tcp: report.tcp.clone(),
+                    bandwidth: report.bandwidth.clone(),
+                },
+                FamilyReport {
+                    family: AddressFamily::V6,
+                    address: Some("2606:2800:220:1::".to_string()),
+                    ping: v6_ping,
+                    tcp: None,
+                    bandwidth: unreachable(),
+                },
+            ];
+            report
+        };
+
+        let comparison = compare_reports(&family_run(1, Some(18.0)), &family_run(2, None));
+
+        assert_eq!(comparison.families.len(), 2);
+        assert_eq!(comparison.families[0].ping_median_delta_ms, Some(0.0));
+        assert_eq!(comparison.families[1].family, AddressFamily::V6);
+        assert_eq!(comparison.families[1].ping_median_delta_ms, None);
+        assert_eq!(
+            comparison.changes,
+            vec![RunChange::FamilyReachabilityChanged {
+                family: AddressFamily::V6,
+                reachable: false,
+            }]
+        );
+        let formatted = format_comparison(&comparison);
+        assert!(formatted.contains("  IPv4 deltas: ping median +0.00 ms"));
+        assert!(formatted.contains("IPv6 became unreachable"));
     }
 
     #[test]
@@ -553,6 +1325,7 @@ mod tests {
         let formatted = format_compared_runs(&compared_runs);
         assert!(formatted.contains("Current run: 2-example-com"));
         assert!(formatted.contains("Previous run: 1-example-com"));
+        assert!(formatted.contains("changes: none"));
     }
 
     fn fixture_run(created_at_unix_ms: u128) -> StoredRun {
@@ -565,8 +1338,11 @@ mod tests {
                 bandwidth_provider: BandwidthProviderPreset::Cloudflare.to_string(),
                 samples: 5,
                 created_at_unix_ms,
+                family: None,
+                ping_config: PingConfig::default(),
                 ping: ProbeOutcome {
                     value: Some(PingSummary {
+                        engine: PingEngine::IcmpDatagram,
                         sent: 5,
                         received: 5,
                         packet_loss_pct: 0.0,
@@ -578,16 +1354,46 @@ mod tests {
                         stddev_ms: Some(1.5),
                         jitter_ms: Some(1.0),
                         samples_ms: vec![10.0, 11.0, 12.0, 13.0, 15.0],
+                        sequence: None,
                     }),
                     error: None,
                 },
+                quality: None,
                 dns: ProbeOutcome {
                     value: Some(DnsSummary {
                         resolution_time_ms: 1.2,
                         addresses: vec!["93.184.216.34".to_string()],
+                        resolvers: Vec::new(),
                     }),
                     error: None,
                 },
+                address_pings: Vec::new(),
+                tcp: Some(ProbeOutcome {
+                    value: Some(TcpSummary {
+                        address: "93.184.216.34".to_string(),
+                        port: 443,
+                        sent: 5,
+                        connected: 5,
+                        loss_pct: 0.0,
+                        connect: Some(MetricStats {
+                            min: 20.0,
+                            mean: 22.0,
+                            median: 21.0,
+                            p95: 25.0,
+                            max: 26.0,
+                            stddev: 2.0,
+                        }),
+                        jitter_ms: Some(1.5),
+                        samples_ms: vec![20.0, 21.0, 21.0, 22.0, 26.0],
+                        last_error: None,
+                    }),
+                    error: None,
+                }),
+                tls: None,
+                udp: None,
+                trace: None,
+                pmtu: None,
+                http: Vec::new(),
                 bandwidth: ProbeOutcome {
                     value: Some(BandwidthSummary {
                         provider: BandwidthProviderPreset::Cloudflare.to_string(),
@@ -615,30 +1421,46 @@ mod tests {
                         download_runs: vec![TransferSample {
                             target_bytes: 4_000_000,
                             mbps: 50.0,
+                            average_mbps: 50.0,
                             bytes: 4_000_000,
                             elapsed_ms: 640.0,
                             streams: 2,
+                            window_ms: None,
+                            series: Vec::new(),
+                            ramp_up_fallback: false,
                         }],
                         upload_runs: vec![TransferSample {
                             target_bytes: 1_000_000,
                             mbps: 20.0,
+                            average_mbps: 20.0,
                             bytes: 1_000_000,
                             elapsed_ms: 400.0,
                             streams: 2,
+                            window_ms: None,
+                            series: Vec::new(),
+                            ramp_up_fallback: false,
                         }],
                         warmup_download_runs: vec![TransferSample {
                             target_bytes: 4_000_000,
                             mbps: 48.0,
+                            average_mbps: 48.0,
                             bytes: 4_000_000,
                             elapsed_ms: 666.0,
                             streams: 2,
+                            window_ms: None,
+                            series: Vec::new(),
+                            ramp_up_fallback: false,
                         }],
                         warmup_upload_runs: vec![TransferSample {
                             target_bytes: 1_000_000,
                             mbps: 18.0,
+                            average_mbps: 18.0,
                             bytes: 1_000_000,
                             elapsed_ms: 444.0,
                             streams: 2,
+                            window_ms: None,
+                            series: Vec::new(),
+                            ramp_up_fallback: false,
                         }],
                         download_bytes: 4_000_000,
                         upload_bytes: 1_000_000,
@@ -647,9 +1469,15 @@ mod tests {
                         calibrated_download_size_bytes: 6_000_000,
                         calibrated_upload_size_bytes: 2_000_000,
                         target_transfer_duration_ms: 2_500,
+                        transfer_duration_ms: None,
+                        ramp_up_exclusion_ms: 0,
+                        download_average_mbps: None,
+                        upload_average_mbps: None,
                         bandwidth_elapsed_ms: 3_000.0,
                         runs: 1,
                         warmup_runs: 1,
+                        min_successful_runs: 1,
+                        failed_attempts: Vec::new(),
                         transfer_attempts: 2,
                         transfer_timeout_seconds: 30,
                         download_streams: 2,
@@ -657,9 +1485,31 @@ mod tests {
                         download_url: "https://speed.cloudflare.com/__down?bytes=4000000"
                             .to_string(),
                         upload_url: "https://speed.cloudflare.com/__up".to_string(),
+                        download_sizing: Some(DownloadSizing::QueryParameter),
+                        upload_payload: UploadPayload::Random,
+                        loaded_latency: None,
                     }),
                     error: None,
                 },
+                responsiveness: Some(ProbeOutcome {
+                    value: Some(ResponsivenessSummary {
+                        rpm: 600.0,
+                        endpoint: "global".to_string(),
+                        probe_url: "https://speed.cloudflare.com/__down?bytes=1".to_string(),
+                        duration_ms: 8_000.0,
+                        download_streams: 2,
+                        upload_streams: 2,
+                        foreign_probes: 60,
+                        reused_probes: 70,
+                        failed_probes: 0,
+                        tcp_connect: None,
+                        tls_handshake: None,
+                        foreign_http: None,
+                        reused_http: None,
+                    }),
+                    error: None,
+                }),
+                families: Vec::new(),
             },
         }
     }
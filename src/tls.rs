@@ -0,0 +1,258 @@
+use anyhow::{anyhow, Context, Result};
+use rustls_platform_verifier::Verifier;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::TcpStream;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use tokio_rustls::TlsConnector;
+
+use crate::probe::AddressFamily;
+use crate::x509::{self, Certificate};
+
+const ALPN_PROTOCOLS: [&[u8]; 2] = [b"h2", b"http/1.1"];
+
+#[derive(Debug, Clone)]
+pub struct TlsInspection {
+    pub remote_address: SocketAddr,
+    pub connect: Duration,
+    pub handshake: Duration,
+    pub protocol: String,
+    pub cipher: String,
+    pub alpn: Option<String>,
+    pub chain: Vec<Certificate>,
+    pub chain_errors: Vec<String>,
+    pub validation_error: Option<String>,
+}
+
+pub async fn inspect(
+    host: &str,
+    port: u16,
+    family: Option<AddressFamily>,
+    timeout: Duration,
+) -> Result<TlsInspection> {
+    tokio::time::timeout(timeout, run_inspect(host, port, family))
+        .await
+        .map_err(|_| {
+            anyhow!(
+                "TLS probe of {host}:{port} timed out after {}s",
+                timeout.as_secs()
+            )
+        })?
+}
+
+async fn run_inspect(
+    host: &str,
+    port: u16,
+    family: Option<AddressFamily>,
+) -> Result<TlsInspection> {
+    let remote_address = tokio::net::lookup_host((host, port))
+        .await
+        .with_context(|| format!("failed to resolve {host}"))?
+        .find(|address| family.is_none_or(|family| family.matches(&address.ip())))
+        .ok_or_else(|| match family {
+            Some(family) => anyhow!("no {family} addresses resolved for {host}"),
+            None => anyhow!("no addresses resolved for {host}"),
+        })?;
+    let server_name = ServerName::try_from(host.to_string())
+        .with_context(|| format!("invalid TLS server name {host}"))?;
+
+    let connect_started = Instant::now();
+    let stream = TcpStream::connect(remote_address)
+        .await
+        .with_context(|| format!("failed to connect to {remote_address}"))?;
+    let connect = connect_started.elapsed();
+
+    let verifier = Arc::new(RecordingVerifier::new()?);
+    let handshake_started = Instant::now();
+    let stream = TlsConnector::from(Arc::new(client_config(verifier.clone())))
+        .connect(server_name, stream)
+        .await
+        .with_context(|| format!("TLS handshake with {host}:{port} failed"))?;
+    let handshake = handshake_started.elapsed();
+
+    let (_, connection) = stream.get_ref();
+    let (chain, chain_errors) =
+        x509::parse_chain(connection.peer_certificates().unwrap_or_default())
+            .with_context(|| format!("failed to read the certificate chain from {host}"))?;
+
+    Ok(TlsInspection {
+        remote_address,
+        connect,
+        handshake,
+        protocol: connection
+            .protocol_version()
+            .and_then(|version| version.as_str())
+            .map(|version| version.replace('_', "."))
+            .unwrap_or_else(|| "unknown".to_string()),
+        cipher: connection
+            .negotiated_cipher_suite()
+            .and_then(|suite| suite.suite().as_str())
+            .unwrap_or("unknown")
+            .to_string(),
+        alpn: connection
+            .alpn_protocol()
+            .map(|protocol| String::from_utf8_lossy(protocol).into_owned()),
+        chain,
+        chain_errors,
+        validation_error: verifier.take_error(),
+    })
+}
+
+fn client_config(verifier: Arc<RecordingVerifier>) -> ClientConfig {
+    let mut config = ClientConfig::builder()
+        .dangerous()
+        .with_custom_certificate_verifier(verifier)
+        .with_no_client_auth();
+    config.alpn_protocols = ALPN_PROTOCOLS
+        .iter()
+        .map(|protocol| protocol.to_vec())
+        .collect();
+    config
+}
+
+// Validates the chain with the platform verifier but lets the handshake finish
+// either way, so expired or untrusted certificates can still be inspected.
+#[derive(Debug)]
+struct RecordingVerifier {
+    inner: Verifier,
+    error: Mutex<Option<String>>,
+}
+
+impl RecordingVerifier {
+    fn new() -> Result<Self> {
+        let provider = ClientConfig::builder().crypto_provider().clone();
+        Ok(Self {
+            inner: Verifier::new(provider).context("failed to load TLS trust roots")?,
+            error: Mutex::new(None),
+        })
+    }
+
+    fn take_error(&self) -> Option<String> {
+        self.error.lock().ok()?.take()
+    }
+}
+
+impl ServerCertVerifier for RecordingVerifier {
+    fn verify_server_cert(
+        &self,
+        end_entity: &CertificateDer<'_>,
+        intermediates: &[CertificateDer<'_>],
+        server_name: &ServerName<'_>,
+        ocsp_response: &[u8],
+        now: UnixTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        if let Err(error) = self.inner.verify_server_cert(
+            end_entity,
+            intermediates,
+            server_name,
+            ocsp_response,
+            now,
+        ) {
+            if let Ok(mut recorded) = self.error.lock() {
+                *recorded = Some(error.to_string());
+            }
+        }
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        self.inner.verify_tls12_signature(message, cert, dss)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        self.inner.verify_tls13_signature(message, cert, dss)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.inner.supported_verify_schemes()
+    }
+}
+
+#[cfg(test)]
+pub mod stub {
+    use crate::x509::fixture;
+    use std::net::SocketAddr;
+    use std::sync::Arc;
+    use tokio::net::TcpListener;
+    use tokio_rustls::rustls::pki_types::pem::PemObject;
+    use tokio_rustls::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+    use tokio_rustls::rustls::ServerConfig;
+    use tokio_rustls::TlsAcceptor;
+
+    pub async fn spawn() -> SocketAddr {
+        let certificate = CertificateDer::from_pem_slice(fixture::CERTIFICATE_PEM.as_bytes())
+            .expect("fixture certificate should be valid PEM");
+        let key = PrivateKeyDer::from_pem_slice(fixture::PRIVATE_KEY_PEM.as_bytes())
+            .expect("fixture key should be valid PEM");
+        let mut config = ServerConfig::builder()
+            .with_no_client_auth()
+            .with_single_cert(vec![certificate], key)
+            .expect("fixture certificate and key should match");
+        config.alpn_protocols = vec![b"h2".to_vec()];
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("TLS stub should bind");
+        let address = listener
+            .local_addr()
+            .expect("TLS stub should have an address");
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let _ = acceptor.accept(stream).await;
+                });
+            }
+        });
+
+        address
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{inspect, stub};
+    use crate::probe::AddressFamily;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn inspects_handshake_and_chain_of_untrusted_server() {
+        let address = stub::spawn().await;
+
+        let inspection = inspect(
+            "localhost",
+            address.port(),
+            Some(AddressFamily::V4),
+            Duration::from_secs(5),
+        )
+        .await
+        .expect("handshake should complete despite the untrusted certificate");
+
+        assert_eq!(inspection.remote_address, address);
+        assert_eq!(inspection.protocol, "TLSv1.3");
+        assert_eq!(inspection.alpn.as_deref(), Some("h2"));
+        assert!(inspection.cipher.starts_with("TLS13_"));
+        assert_eq!(inspection.chain.len(), 1);
+        assert_eq!(
+            inspection.chain[0].subject,
+            "O=PantheonProbe Test, CN=probe.test"
+        );
+        assert!(inspection.validation_error.is_some());
+    }
+}
@@ -118,12 +118,28 @@ fn restore_terminal(terminal: &mut Terminal<CrosstermBackend<Stdout>>) -> Result
     Ok(())
 }
 
+fn format_call_quality(report: Option<&ProbeReport>) -> String {
+    let Some(quality) = report.and_then(|report| report.quality.as_ref()) else {
+        return "Call quality: unavailable".to_string();
+    };
+    let verdicts = quality
+        .verdicts
+        .iter()
+        .map(|verdict| format!("{} {}", verdict.use_case, verdict.verdict))
+        .collect::<Vec<_>>()
+        .join(" | ");
+    format!(
+        "Call quality: MOS {:.2} (R {:.1}) | {verdicts}",
+        quality.mos, quality.r_factor
+    )
+}
+
 fn draw(terminal: &mut Terminal<CrosstermBackend<Stdout>>, state: &TuiState) -> Result<()> {
     terminal.draw(|frame| {
         let layout = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
-                Constraint::Length(3),
+                Constraint::Length(5),
                 Constraint::Min(10),
                 Constraint::Length(3),
             ])
@@ -138,6 +154,7 @@ fn draw(terminal: &mut Terminal<CrosstermBackend<Stdout>>, state: &TuiState) ->
                 "Refresh every {}s | completed runs: {}",
                 state.interval_seconds, state.refresh_count
             )),
+            Line::from(format_call_quality(state.report.as_ref())),
         ])
         .block(Block::default().borders(Borders::ALL).title("Overview"));
         frame.render_widget(header, layout[0]);
@@ -0,0 +1,177 @@
+use anyhow::{Context, Result};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::time::{Instant, MissedTickBehavior};
+
+const ECHO_MAGIC: &[u8; 4] = b"PPUE";
+const ECHO_HEADER_LEN: usize = 16;
+const RECEIVE_BUFFER_LEN: usize = 65_536;
+
+#[derive(Debug, Clone, Copy)]
+pub struct EchoSessionConfig {
+    pub packets: u32,
+    pub interval: Duration,
+    pub payload_bytes: usize,
+    pub timeout: Duration,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EchoArrival {
+    pub sequence: u32,
+    pub sent_offset: Duration,
+    pub received_offset: Duration,
+}
+
+pub async fn bind_reflector(bind: &str) -> Result<UdpSocket> {
+    UdpSocket::bind(bind)
+        .await
+        .with_context(|| format!("failed to bind UDP reflector on {bind}"))
+}
+
+pub async fn reflect(socket: UdpSocket) -> Result<()> {
+    let mut buffer = vec![0_u8; RECEIVE_BUFFER_LEN];
+
+    loop {
+        let (length, peer) = match socket.recv_from(&mut buffer).await {
+            Ok(received) => received,
+            Err(_) => continue,
+        };
+
+        if decode_packet(&buffer[..length]).is_some() {
+            let _ = socket.send_to(&buffer[..length], peer).await;
+        }
+    }
+}
+
+pub async fn run_echo_session(
+    address: SocketAddr,
+    config: EchoSessionConfig,
+) -> Result<Vec<EchoArrival>> {
+    let bind_address = if address.is_ipv4() {
+        "0.0.0.0:0"
+    } else {
+        "[::]:0"
+    };
+    let socket = UdpSocket::bind(bind_address)
+        .await
+        .context("failed to bind UDP echo socket")?;
+    socket
+        .connect(address)
+        .await
+        .with_context(|| format!("failed to connect UDP echo socket to {address}"))?;
+
+    let started = Instant::now();
+    let packets = config.packets.max(1);
+    let mut ticker = tokio::time::interval(config.interval.max(Duration::from_millis(1)));
+    ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+    let mut next_sequence = 0_u32;
+    let mut drain_deadline = Instant::now() + Duration::from_secs(86_400);
+    let mut arrivals = Vec::with_capacity(packets as usize);
+    let mut received = HashSet::with_capacity(packets as usize);
+    let mut buffer = vec![0_u8; RECEIVE_BUFFER_LEN];
+
+    loop {
+        tokio::select! {
+            _ = ticker.tick(), if next_sequence < packets => {
+                let packet = encode_packet(next_sequence, started.elapsed(), config.payload_bytes);
+                socket
+                    .send(&packet)
+                    .await
+                    .with_context(|| format!("failed to send UDP echo to {address}"))?;
+                next_sequence += 1;
+                if next_sequence == packets {
+                    drain_deadline = Instant::now() + config.timeout;
+                }
+            }
+            result = socket.recv(&mut buffer) => {
+                // Connected UDP sockets surface ICMP errors as receive failures.
+                let Ok(length) = result else { continue };
+                if let Some((sequence, sent_offset)) = decode_packet(&buffer[..length]) {
+                    received.insert(sequence);
+                    arrivals.push(EchoArrival {
+                        sequence,
+                        sent_offset,
+                        received_offset: started.elapsed(),
+                    });
+                }
+            }
+            _ = tokio::time::sleep_until(drain_deadline), if next_sequence == packets => break,
+        }
+
+        if next_sequence == packets && received.len() == packets as usize {
+            break;
+        }
+    }
+
+    Ok(arrivals)
+}
+
+fn encode_packet(sequence: u32, sent_offset: Duration, payload_bytes: usize) -> Vec<u8> {
+    let mut packet = Vec::with_capacity(ECHO_HEADER_LEN + payload_bytes);
+    packet.extend_from_slice(ECHO_MAGIC);
+    packet.extend_from_slice(&sequence.to_be_bytes());
+    packet.extend_from_slice(&(sent_offset.as_nanos() as u64).to_be_bytes());
+    packet.resize(ECHO_HEADER_LEN + payload_bytes, 0);
+    packet
+}
+
+fn decode_packet(packet: &[u8]) -> Option<(u32, Duration)> {
+    if packet.len() < ECHO_HEADER_LEN || &packet[..4] != ECHO_MAGIC {
+        return None;
+    }
+
+    let sequence = u32::from_be_bytes(packet[4..8].try_into().ok()?);
+    let sent_nanos = u64::from_be_bytes(packet[8..16].try_into().ok()?);
+
+    Some((sequence, Duration::from_nanos(sent_nanos)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        bind_reflector, decode_packet, encode_packet, reflect, run_echo_session, EchoSessionConfig,
+    };
+    use std::time::Duration;
+
+    #[test]
+    fn round_trips_echo_packets() {
+        let packet = encode_packet(42, Duration::from_micros(1_500), 32);
+
+        assert_eq!(packet.len(), 48);
+        assert_eq!(
+            decode_packet(&packet),
+            Some((42, Duration::from_micros(1_500)))
+        );
+        assert_eq!(decode_packet(b"not an echo packet"), None);
+    }
+
+    #[tokio::test]
+    async fn echoes_every_packet_through_local_reflector() {
+        let socket = bind_reflector("127.0.0.1:0")
+            .await
+            .expect("reflector should bind");
+        let address = socket
+            .local_addr()
+            .expect("reflector should have an address");
+        tokio::spawn(reflect(socket));
+
+        let arrivals = run_echo_session(
+            address,
+            EchoSessionConfig {
+                packets: 10,
+                interval: Duration::from_millis(2),
+                payload_bytes: 64,
+                timeout: Duration::from_secs(1),
+            },
+        )
+        .await
+        .expect("echo session should run");
+
+        assert_eq!(arrivals.len(), 10);
+        assert!(arrivals
+            .iter()
+            .all(|arrival| arrival.received_offset >= arrival.sent_offset));
+    }
+}